@@ -0,0 +1,420 @@
+use clap::{Args, Parser, Subcommand};
+use color_eyre::eyre::{bail, Result};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use carrier_pigeon_core::{
+    bench::{self, BenchOptions, Limit},
+    executor::HttpClient,
+    runner::{
+        self,
+        report::{ReportFormat, RunReport},
+        Outcome, RunOptions, RunResult, RunSummary,
+    },
+};
+
+use crate::state::Collection;
+use crate::{load_collection, APP_USER_AGENT};
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum CliCommand {
+    /// Load test requests, printing a JSON summary and exiting with a non-zero status on errors
+    Bench(BenchArgs),
+    /// Run a collection without the TUI, exiting with a non-zero status if anything fails
+    Run(RunArgs),
+}
+
+#[derive(Debug, PartialEq, Args)]
+pub struct BenchArgs {
+    /// Collection directory or .http file
+    pub collection: PathBuf,
+    /// Environment to resolve variables from
+    #[arg(short, long)]
+    pub env: Option<String>,
+    /// Requests to send, as a sequence in collection order. Defaults to every request
+    #[arg(short, long = "request", value_name = "NAME")]
+    pub requests: Vec<String>,
+    #[command(flatten)]
+    pub load: LoadArgs,
+}
+
+/// Load test settings, shared with the `:bench` command.
+#[derive(Debug, PartialEq, Args)]
+pub struct LoadArgs {
+    /// Number of workers sending requests at the same time
+    #[arg(short, long, default_value_t = 1)]
+    pub concurrency: usize,
+    /// Target requests per second across all workers, as fast as possible when unset
+    #[arg(long)]
+    pub rate: Option<f64>,
+    /// Send requests for this many seconds
+    #[arg(long, value_name = "SECS", conflicts_with = "count")]
+    pub duration: Option<u64>,
+    /// Send this many requests, 100 when neither this nor --duration is set
+    #[arg(short = 'n', long)]
+    pub count: Option<usize>,
+}
+
+impl LoadArgs {
+    pub fn options(&self) -> BenchOptions {
+        BenchOptions {
+            concurrency: self.concurrency,
+            rate: self.rate,
+            limit: match self.duration {
+                Some(secs) => Limit::Duration(Duration::from_secs(secs)),
+                None => Limit::Count(self.count.unwrap_or(100)),
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Args)]
+pub struct RunArgs {
+    /// Collection directory or .http file
+    pub collection: PathBuf,
+    /// Environment to resolve variables from
+    #[arg(short, long)]
+    pub env: Option<String>,
+    /// Only run the requests with these names, in collection order
+    #[arg(short, long = "request", value_name = "NAME")]
+    pub requests: Vec<String>,
+    /// Stop after the first failed request
+    #[arg(long)]
+    pub stop_on_failure: bool,
+    /// Run the requests once per row of a CSV file or JSON array, with the row's values as variables
+    #[arg(short, long, value_name = "PATH")]
+    pub data: Option<PathBuf>,
+    /// Pause between requests, in milliseconds
+    #[arg(long, value_name = "MS", default_value_t = 0)]
+    pub delay: u64,
+    /// Write a report, as JUnit (.xml), JSON (.json) or HTML (.html) by extension. May be repeated
+    #[arg(long = "report", value_name = "PATH")]
+    pub reports: Vec<PathBuf>,
+}
+
+/// Runs the collection, writing a line per request and a summary to stdout.
+/// Returns whether every request passed.
+pub async fn run(args: RunArgs) -> Result<bool> {
+    let reports = args
+        .reports
+        .iter()
+        .map(|path| Ok((ReportFormat::from_path(path)?, path)))
+        .collect::<Result<Vec<_>>>()?;
+    let name = collection_name(&args.collection);
+    let mut coll = load_collection(args.collection.clone())?;
+    if let Some(env) = &args.env {
+        if !coll.environments.iter().any(|e| e.name == *env) {
+            bail!("Environment '{}' not found in collection", env);
+        }
+    }
+    let selection = selection(&coll, &args.requests)?;
+    let data = match &args.data {
+        Some(path) => runner::data::parse(path, &fs::read_to_string(path)?)?,
+        None => vec![],
+    };
+    let options = RunOptions {
+        stop_on_failure: args.stop_on_failure,
+        delay: Duration::from_millis(args.delay),
+        data,
+    };
+    let client = HttpClient::new(APP_USER_AGENT)?;
+
+    let mut stdout = std::io::stdout();
+    let mut results = vec![];
+    let summary = runner::run(
+        &client,
+        &mut coll,
+        &selection,
+        args.env.as_deref(),
+        &options,
+        |result| {
+            if options.data.len() > 1
+                && results
+                    .last()
+                    .is_none_or(|last: &RunResult| last.iteration != result.iteration)
+            {
+                let _ = writeln!(
+                    stdout,
+                    "Iteration {}/{}",
+                    result.iteration + 1,
+                    options.data.len()
+                );
+            }
+            let _ = write_result(&mut stdout, result);
+            results.push(result.clone());
+        },
+    )
+    .await;
+    write_summary(&mut stdout, &summary)?;
+
+    if !reports.is_empty() {
+        let report = RunReport::new(name, &results, &summary, &coll, args.env.as_deref());
+        for (format, path) in reports {
+            fs::write(path, report.render(format)?)?;
+        }
+    }
+
+    Ok(summary.failed == 0)
+}
+
+/// Load tests the collection, writing the JSON summary to stdout.
+/// Returns whether every request received a response.
+pub async fn bench(args: BenchArgs) -> Result<bool> {
+    let coll = load_collection(args.collection)?;
+    if let Some(env) = &args.env {
+        if !coll.environments.iter().any(|e| e.name == *env) {
+            bail!("Environment '{}' not found in collection", env);
+        }
+    }
+    let selection = selection(&coll, &args.requests)?;
+    let client = HttpClient::new(APP_USER_AGENT)?;
+
+    let stats = bench::bench(
+        &client,
+        &coll,
+        &selection,
+        args.env.as_deref(),
+        &args.load.options(),
+        Default::default(),
+        |_| {},
+    )
+    .await?;
+    println!("{}", serde_json::to_string_pretty(&stats.summary())?);
+
+    Ok(stats.error_count() == 0)
+}
+
+/// The name of a collection, taken from the project directory for `.pigeon` collections.
+fn collection_name(path: &Path) -> String {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let name = if path.is_file() {
+        path.file_stem()
+    } else if path.file_name().is_some_and(|name| name == ".pigeon") {
+        path.parent().and_then(|dir| dir.file_name())
+    } else {
+        path.file_name()
+    };
+    name.map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| String::from("Carrier Pigeon"))
+}
+
+fn selection(coll: &Collection, names: &[String]) -> Result<Vec<usize>> {
+    if let Some(missing) = names
+        .iter()
+        .find(|name| !coll.requests.iter().any(|req| req.name == **name))
+    {
+        bail!("Request '{}' not found in collection", missing);
+    }
+    Ok(coll
+        .requests
+        .iter()
+        .enumerate()
+        .filter(|(_, req)| names.is_empty() || names.contains(&req.name))
+        .map(|(idx, _)| idx)
+        .collect())
+}
+
+fn write_result(out: &mut impl Write, result: &RunResult) -> std::io::Result<()> {
+    let mark = if result.passed() { "PASS" } else { "FAIL" };
+    match &result.outcome {
+        Outcome::Completed(execution) => {
+            writeln!(
+                out,
+                "{} {} {} ({}, {}ms)",
+                mark,
+                result.method,
+                result.name,
+                execution.response.status,
+                execution.response.duration.as_millis()
+            )?;
+            for test in &execution.tests {
+                writeln!(out, "    {}", test)?;
+            }
+        }
+        Outcome::Failed(err) => {
+            writeln!(out, "{} {} {}: {}", mark, result.method, result.name, err)?
+        }
+    }
+    Ok(())
+}
+
+fn write_summary(out: &mut impl Write, summary: &RunSummary) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "\n{} passed, {} failed of {} requests{}, tests {}/{} passed in {}ms",
+        summary.passed,
+        summary.failed,
+        summary.total,
+        if summary.iterations > 1 {
+            format!(" over {} iterations", summary.iterations)
+        } else {
+            String::new()
+        },
+        summary.tests_passed,
+        summary.tests_passed + summary.tests_failed,
+        summary.duration.as_millis()
+    )?;
+    if summary.stopped {
+        writeln!(out, "Stopped on failure")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use carrier_pigeon_core::{assertion::TestResult, Execution, Method, Request, Response};
+    use time::OffsetDateTime;
+
+    #[test]
+    fn parse_run() {
+        let cli = Cli::try_parse_from([
+            "carrier-pigeon",
+            "run",
+            ".pigeon",
+            "--env",
+            "staging",
+            "-r",
+            "Login",
+            "-r",
+            "Me",
+            "--stop-on-failure",
+            "--data",
+            "accounts.csv",
+            "--report",
+            "results.xml",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            cli.command,
+            Some(CliCommand::Run(RunArgs {
+                collection: ".pigeon".into(),
+                env: Some("staging".into()),
+                requests: vec!["Login".into(), "Me".into()],
+                stop_on_failure: true,
+                data: Some("accounts.csv".into()),
+                delay: 0,
+                reports: vec!["results.xml".into()],
+            }))
+        );
+        assert_eq!(
+            Cli::try_parse_from(["carrier-pigeon"]).unwrap().command,
+            None
+        );
+    }
+
+    #[test]
+    fn parse_bench() {
+        let cli = Cli::try_parse_from([
+            "carrier-pigeon",
+            "bench",
+            ".pigeon",
+            "-c",
+            "8",
+            "--rate",
+            "50",
+            "--duration",
+            "30",
+        ])
+        .unwrap();
+
+        let Some(CliCommand::Bench(args)) = cli.command else {
+            panic!("expected bench command");
+        };
+        assert_eq!(
+            args.load.options(),
+            BenchOptions {
+                concurrency: 8,
+                rate: Some(50.0),
+                limit: Limit::Duration(Duration::from_secs(30)),
+            }
+        );
+        assert!(Cli::try_parse_from([
+            "carrier-pigeon",
+            "bench",
+            ".pigeon",
+            "--duration",
+            "30",
+            "-n",
+            "10",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn select_by_name() {
+        let request = |name: &str| {
+            Request::builder()
+                .name(name.into())
+                .method(Method::Get)
+                .url("https://pets.test".into())
+                .build()
+        };
+        let coll = Collection {
+            requests: vec![request("Login"), request("Pets"), request("Me")],
+            ..Default::default()
+        };
+
+        assert_eq!(selection(&coll, &[]).unwrap(), vec![0, 1, 2]);
+        assert_eq!(
+            selection(&coll, &["Me".into(), "Login".into()]).unwrap(),
+            vec![0, 2]
+        );
+        assert!(selection(&coll, &["Logout".into()]).is_err());
+    }
+
+    #[test]
+    fn writes_results() {
+        let request = Request::builder()
+            .name("Create Pet".into())
+            .method(Method::Post)
+            .url("https://pets.test/pets".into())
+            .build();
+        let completed = RunResult {
+            index: 0,
+            iteration: 0,
+            name: request.name.clone(),
+            method: Method::Post,
+            outcome: Outcome::Completed(Box::new(Execution {
+                request,
+                response: Response {
+                    status: 500,
+                    duration: Duration::from_millis(42),
+                    ..Default::default()
+                },
+                started: OffsetDateTime::UNIX_EPOCH,
+                tests: vec![TestResult::fail("status == 201", "status was 500")],
+                environment: None,
+                sent: None,
+            })),
+        };
+        let failed = RunResult {
+            index: 1,
+            iteration: 0,
+            name: "Me".into(),
+            method: Method::Get,
+            outcome: Outcome::Failed("connection refused".into()),
+        };
+
+        let mut out = vec![];
+        write_result(&mut out, &completed).unwrap();
+        write_result(&mut out, &failed).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "FAIL POST Create Pet (500, 42ms)\n    FAIL status == 201: status was 500\nFAIL GET Me: connection refused\n"
+        );
+    }
+}
@@ -0,0 +1,635 @@
+use clap::Parser;
+use color_eyre::eyre::{bail, eyre, OptionExt, Result};
+use log::{info, warn};
+use std::{fs, mem, path::PathBuf, str::FromStr};
+
+use carrier_pigeon_core::{
+    compression::Encoding,
+    formats::{har, http_file, insomnia, openapi, postman, Report},
+    runner::{
+        self,
+        report::{ReportFormat, RunReport},
+    },
+    Example,
+};
+
+use crate::cli::LoadArgs;
+use crate::state::{App, Bench, Collection, HarPicker, Modal, Request, Runner};
+use crate::Message;
+
+#[derive(Debug, PartialEq)]
+pub enum Format {
+    Har,
+    Insomnia,
+    OpenApi,
+    Postman,
+}
+
+impl FromStr for Format {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "har" => Ok(Self::Har),
+            "insomnia" => Ok(Self::Insomnia),
+            "openapi" => Ok(Self::OpenApi),
+            "postman" => Ok(Self::Postman),
+            _ => Err(eyre!("Unknown format: {}", s)),
+        }
+    }
+}
+
+/// Arguments of `:bench`, which load tests the selected request.
+#[derive(Debug, PartialEq, Parser)]
+#[command(name = "bench")]
+pub struct BenchCommand {
+    /// Send the requests selected in the runner as a sequence instead
+    #[arg(long)]
+    pub sequence: bool,
+    #[command(flatten)]
+    pub load: LoadArgs,
+}
+
+/// Commands entered on the `:` command line.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Bench(BenchCommand),
+    /// Sets the encoding the selected request's body is compressed with, `None` sends it as is.
+    Compress(Option<Encoding>),
+    /// Turns decoding of compressed response bodies on or off for the collection.
+    Decompress(bool),
+    /// Compares the named example of the selected request, or its first, with its response.
+    Diff(Option<String>),
+    /// Opens the selected request's body in `$VISUAL` or `$EDITOR`.
+    EditBody,
+    Environment(String),
+    /// Saves the selected request's response as an example under a name, replacing any
+    /// example of the same name.
+    Example(String),
+    Export(Format, PathBuf),
+    ExportEnvironment(Format, String, PathBuf),
+    /// Browses the requests sent from this collection.
+    History,
+    Import(Format, PathBuf),
+    ImportEnvironment(Format, PathBuf),
+    /// Loads another collection, `force` discards unsaved changes to the current one.
+    Open(PathBuf, bool),
+    /// Turns estimating connection timings on a probe connection on or off for the collection.
+    Probe(bool),
+    Quit,
+    /// Writes the last collection run as a report, the format is picked from the extension.
+    Report(PathBuf),
+    /// Opens the runner, optionally loading iteration data from a CSV or JSON file.
+    Run(Option<PathBuf>),
+    /// Writes the response body of the selected request to a file, exactly as received.
+    Save(PathBuf),
+    Write(Option<PathBuf>),
+}
+
+impl FromStr for Command {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let args: Vec<&str> = s.split_whitespace().collect();
+        match args.as_slice() {
+            ["q"] | ["quit"] => Ok(Self::Quit),
+            ["history"] => Ok(Self::History),
+            ["report", path] => Ok(Self::Report(path.into())),
+            ["bench", ..] => Ok(Self::Bench(BenchCommand::try_parse_from(&args).map_err(
+                |err| eyre!("{}", err.to_string().lines().next().unwrap_or_default()),
+            )?)),
+            ["run"] => Ok(Self::Run(None)),
+            ["run", path] => Ok(Self::Run(Some(path.into()))),
+            ["save", path] => Ok(Self::Save(path.into())),
+            ["compress", "off"] => Ok(Self::Compress(None)),
+            ["compress", encoding] => Ok(Self::Compress(Some(encoding.parse()?))),
+            ["decompress", "on"] => Ok(Self::Decompress(true)),
+            ["decompress", "off"] => Ok(Self::Decompress(false)),
+            ["edit-body"] => Ok(Self::EditBody),
+            ["example", name] => Ok(Self::Example(name.to_string())),
+            ["diff"] => Ok(Self::Diff(None)),
+            ["diff", name] => Ok(Self::Diff(Some(name.to_string()))),
+            ["probe", "on"] => Ok(Self::Probe(true)),
+            ["probe", "off"] => Ok(Self::Probe(false)),
+            ["w"] | ["write"] => Ok(Self::Write(None)),
+            ["w", path] | ["write", path] => Ok(Self::Write(Some(path.into()))),
+            ["e", path] | ["open", path] => Ok(Self::Open(path.into(), false)),
+            ["e!", path] | ["open!", path] => Ok(Self::Open(path.into(), true)),
+            ["env", name] => Ok(Self::Environment(name.to_string())),
+            ["import", format, path] => Ok(Self::Import(format.parse()?, path.into())),
+            ["export", format, path] => Ok(Self::Export(format.parse()?, path.into())),
+            ["import-env", format, path] => {
+                Ok(Self::ImportEnvironment(format.parse()?, path.into()))
+            }
+            ["export-env", format, name, path] => Ok(Self::ExportEnvironment(
+                format.parse()?,
+                name.to_string(),
+                path.into(),
+            )),
+            [] => bail!("No command entered"),
+            _ => bail!("Unknown command: {}", s),
+        }
+    }
+}
+
+pub fn execute(app: &mut App, cmd: Command) -> Result<Option<Message>> {
+    match cmd {
+        Command::Quit => Ok(Some(Message::Quit)),
+        Command::Run(data) => {
+            let Some(coll) = &app.collection else {
+                bail!("There is no collection to run");
+            };
+            let data = match data {
+                Some(path) => {
+                    let rows = runner::data::parse(&path, &fs::read_to_string(&path)?)?;
+                    info!("Loaded {} iterations from: {}", rows.len(), path.display());
+                    Some(rows)
+                }
+                None => None,
+            };
+            // Reopening keeps the previous selection and results unless requests changed.
+            let runner = match app.runner.take() {
+                Some(runner) if runner.selected.len() == coll.requests.len() => runner,
+                _ => Runner::new(coll.requests.len()),
+            };
+            let runner = app.runner.insert(runner);
+            if let Some(data) = data {
+                runner.options.data = data;
+            }
+            app.window_state.modal = Modal::Runner;
+            Ok(None)
+        }
+        Command::Bench(cmd) => {
+            let Some(coll) = &app.collection else {
+                bail!("There is no collection to benchmark");
+            };
+            if app.bench.as_ref().is_some_and(Bench::running) {
+                bail!("A benchmark is already running");
+            }
+            let (target, selection) = if cmd.sequence {
+                let selection = match &app.runner {
+                    Some(runner) if runner.selected.len() == coll.requests.len() => {
+                        runner.selection()
+                    }
+                    _ => (0..coll.requests.len()).collect(),
+                };
+                (
+                    format!("{} requests in sequence", selection.len()),
+                    selection,
+                )
+            } else if app.scratch.is_some() {
+                bail!("Only requests in the collection can be benchmarked");
+            } else {
+                let idx = app.window_state.select_list_state.selected().unwrap_or(0);
+                let req = coll.requests.get(idx).ok_or_eyre("No request selected")?;
+                (format!("{} {}", req.method, req.name), vec![idx])
+            };
+            app.bench = Some(Bench {
+                target,
+                selection,
+                options: cmd.load.options(),
+                stats: None,
+                stop: Default::default(),
+            });
+            app.window_state.modal = Modal::Bench;
+            Ok(None)
+        }
+        Command::History => {
+            if app.history.is_empty() {
+                bail!("No requests have been sent yet");
+            }
+            app.window_state.history_list_state.select_first();
+            app.window_state.modal = Modal::History;
+            Ok(None)
+        }
+        Command::Report(path) => {
+            let (Some(coll), Some(runner)) = (&app.collection, &app.runner) else {
+                bail!("The collection has not been run");
+            };
+            let Some(summary) = &runner.summary else {
+                bail!("The collection has not been run");
+            };
+            let format = ReportFormat::from_path(&path)?;
+            let report = RunReport::new(
+                collection_name(app),
+                &runner.results,
+                summary,
+                coll,
+                app.active_env.as_deref(),
+            );
+            info!("Writing {:?} report to: {}", format, path.display());
+            fs::write(&path, report.render(format)?)?;
+            Ok(None)
+        }
+        Command::Save(path) => {
+            let Some(idx) = app.selected_execution() else {
+                bail!("The request has no response to save");
+            };
+            let res = &app.history[idx].response;
+            info!(
+                "Saving {} byte response body to: {}",
+                res.body_len(),
+                path.display()
+            );
+            match &res.spilled {
+                Some(spilled) if spilled.is_paged() => {
+                    fs::copy(&spilled.path, &path)?;
+                }
+                Some(_) => bail!("Only the start of this response body was kept in history"),
+                None => fs::write(&path, &res.body)?,
+            }
+            Ok(None)
+        }
+        Command::Example(name) => {
+            let Some(idx) = app.selected_execution() else {
+                bail!("The request has no response to save");
+            };
+            let response = app.history[idx].response.clone();
+            if response.spilled.is_some() {
+                bail!("Response bodies written to disk are too large to save as examples");
+            }
+            let req = app
+                .selected_request_mut()
+                .ok_or_eyre("No request selected")?;
+            info!("Saving the response of {} as example {}", req.name, name);
+            req.examples.retain(|example| example.name != name);
+            req.examples.push(Example { name, response });
+            Ok(Some(Message::SaveCollection))
+        }
+        Command::Diff(name) => Ok(Some(Message::DiffExample(name))),
+        Command::Compress(encoding) => {
+            let req = app
+                .selected_request_mut()
+                .ok_or_eyre("No request selected")?;
+            match encoding {
+                Some(encoding) => info!("Compressing the body of {} with {}", req.name, encoding),
+                None => info!("Sending the body of {} uncompressed", req.name),
+            }
+            req.compression = encoding;
+            Ok(Some(Message::SaveCollection))
+        }
+        Command::Decompress(decompress) => {
+            let coll = app.collection.get_or_insert_with(Collection::default);
+            info!("Decoding compressed response bodies: {}", decompress);
+            coll.settings.executor.decompress = decompress;
+            Ok(Some(Message::SaveCollection))
+        }
+        Command::Probe(probe) => {
+            let coll = app.collection.get_or_insert_with(Collection::default);
+            info!("Estimating connection timings: {}", probe);
+            coll.settings.executor.probe_connection = probe;
+            Ok(Some(Message::SaveCollection))
+        }
+        Command::EditBody => {
+            if app.selected_request().is_none() {
+                bail!("No request selected");
+            }
+            Ok(Some(Message::EditBody))
+        }
+        Command::Open(path, force) => {
+            if app.dirty && !force {
+                bail!(
+                    "The collection has unsaved changes, :w to save them or :open! to discard them"
+                );
+            }
+            Ok(Some(Message::LoadCollection(path)))
+        }
+        Command::Write(path) => {
+            if let Some(path) = path {
+                app.collection
+                    .get_or_insert_with(Collection::default)
+                    .save_location = Some(path);
+            }
+            Ok(Some(Message::SaveCollection))
+        }
+        Command::Environment(name) => {
+            if !app
+                .collection
+                .as_ref()
+                .is_some_and(|coll| coll.environments.iter().any(|env| env.name == name))
+            {
+                bail!("No environment named: {}", name);
+            }
+            info!("Using environment: {}", name);
+            app.active_env = Some(name);
+            Ok(None)
+        }
+        Command::Import(Format::Har, path) => {
+            info!("Reading HAR file: {}", path.display());
+            let har = har::parse(&fs::read_to_string(path)?)?;
+            app.har_picker = Some(HarPicker::new(har));
+            app.window_state.modal = Modal::HarImport;
+            Ok(None)
+        }
+        Command::Export(Format::Har, path) => {
+            info!(
+                "Exporting {} executions as HAR to: {}",
+                app.history.len(),
+                path.display()
+            );
+            fs::write(&path, har::export(&app.history)?)?;
+            Ok(None)
+        }
+        Command::Import(Format::Postman, path) => {
+            info!("Importing Postman collection from: {}", path.display());
+            let import = postman::import_collection(&fs::read_to_string(path)?)?;
+            log_report(&import.name, &import.report);
+            let coll = app.collection.get_or_insert_with(Collection::default);
+            merge_collection(coll, import.collection);
+            Ok(Some(Message::SaveCollection))
+        }
+        Command::Import(Format::Insomnia, path) => {
+            info!("Importing Insomnia export from: {}", path.display());
+            insomnia::import_export(&fs::read_to_string(path)?)?
+                .into_iter()
+                .for_each(|import| {
+                    log_report(&import.name, &import.report);
+                    let coll = app.collection.get_or_insert_with(Collection::default);
+                    merge_collection(coll, import.collection);
+                });
+            Ok(Some(Message::SaveCollection))
+        }
+        Command::Import(Format::OpenApi, path) => {
+            info!("Importing OpenAPI specification from: {}", path.display());
+            let import = openapi::import_spec(&fs::read_to_string(path)?)?;
+            log_report(&import.name, &import.report);
+            match &mut app.collection {
+                Some(coll) => openapi::update(coll, import.collection),
+                None => app.collection = Some(import.collection),
+            }
+            Ok(Some(Message::SaveCollection))
+        }
+        Command::ImportEnvironment(Format::Postman, path) => {
+            info!("Importing Postman environment from: {}", path.display());
+            let env = postman::import_environment(&fs::read_to_string(path)?)?;
+            let coll = app.collection.get_or_insert_with(Collection::default);
+            coll.environments
+                .retain(|existing| existing.name != env.name);
+            coll.environments.push(env);
+            Ok(Some(Message::SaveCollection))
+        }
+        Command::Export(Format::Postman, path) => {
+            let Some(coll) = &app.collection else {
+                bail!("There is no collection to export");
+            };
+            info!("Exporting Postman collection to: {}", path.display());
+            fs::write(
+                &path,
+                postman::export_collection(&collection_name(app), coll)?,
+            )?;
+            Ok(None)
+        }
+        Command::ExportEnvironment(Format::Postman, name, path) => {
+            let env = app
+                .collection
+                .as_ref()
+                .and_then(|coll| coll.environments.iter().find(|env| env.name == name))
+                .ok_or_else(|| eyre!("No environment named: {}", name))?;
+            info!("Exporting Postman environment to: {}", path.display());
+            fs::write(&path, postman::export_environment(env)?)?;
+            Ok(None)
+        }
+        Command::Export(format, _)
+        | Command::ExportEnvironment(format, _, _)
+        | Command::ImportEnvironment(format, _) => {
+            bail!("{:?} does not support this operation", format)
+        }
+    }
+}
+
+fn log_report(source: &str, report: &Report) {
+    report
+        .entries
+        .iter()
+        .for_each(|entry| warn!("Import of '{}' - {}", source, entry));
+}
+
+/// Adds an import to the collection. Importing an updated export again replaces the requests with
+/// the same folder and name, keeping what the import formats don't carry, like assertions and
+/// scripts.
+fn merge_collection(coll: &mut Collection, imported: Collection) {
+    let existing = coll.requests.len();
+    for req in imported.requests {
+        match coll.requests[..existing]
+            .iter_mut()
+            .find(|current| current.folder == req.folder && current.name == req.name)
+        {
+            Some(current) => {
+                info!("Import replaced request: {}", req.name);
+                *current = Request {
+                    assertions: mem::take(&mut current.assertions),
+                    extractions: mem::take(&mut current.extractions),
+                    pre_request_script: current.pre_request_script.take(),
+                    response_filter: current.response_filter.take(),
+                    compression: current.compression,
+                    examples: mem::take(&mut current.examples),
+                    ..req
+                };
+            }
+            None => coll.requests.push(req),
+        }
+    }
+    imported.environments.into_iter().for_each(|env| {
+        coll.environments
+            .retain(|existing| existing.name != env.name);
+        coll.environments.push(env);
+    });
+    coll.variables.extend(imported.variables);
+}
+
+fn collection_name(app: &App) -> String {
+    let location = app
+        .collection
+        .as_ref()
+        .and_then(|coll| coll.save_location.as_ref())
+        .unwrap_or(&app.work_dir);
+    let name = if http_file::is_http_file(location) {
+        location.file_stem()
+    } else {
+        location.parent().and_then(|dir| dir.file_name())
+    };
+    name.map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| String::from("Carrier Pigeon"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_import() {
+        let cmd: Command = "import postman ./pets.json".parse().unwrap();
+        assert_eq!(cmd, Command::Import(Format::Postman, "./pets.json".into()));
+    }
+
+    #[test]
+    fn parse_import_insomnia() {
+        let cmd: Command = "import insomnia Insomnia.json".parse().unwrap();
+        assert_eq!(
+            cmd,
+            Command::Import(Format::Insomnia, "Insomnia.json".into())
+        );
+    }
+
+    #[test]
+    fn parse_export_environment() {
+        let cmd: Command = "export-env postman Staging staging.json".parse().unwrap();
+        assert_eq!(
+            cmd,
+            Command::ExportEnvironment(Format::Postman, "Staging".into(), "staging.json".into())
+        );
+    }
+
+    #[test]
+    fn parse_write() {
+        assert_eq!("w".parse::<Command>().unwrap(), Command::Write(None));
+        assert_eq!(
+            "w requests.http".parse::<Command>().unwrap(),
+            Command::Write(Some("requests.http".into()))
+        );
+    }
+
+    #[test]
+    fn parse_open() {
+        assert_eq!(
+            "e api".parse::<Command>().unwrap(),
+            Command::Open("api".into(), false)
+        );
+        assert_eq!(
+            "open! requests.http".parse::<Command>().unwrap(),
+            Command::Open("requests.http".into(), true)
+        );
+    }
+
+    #[test]
+    fn parse_run() {
+        assert_eq!("run".parse::<Command>().unwrap(), Command::Run(None));
+        assert_eq!(
+            "run accounts.csv".parse::<Command>().unwrap(),
+            Command::Run(Some("accounts.csv".into()))
+        );
+        assert_eq!(
+            "report results.xml".parse::<Command>().unwrap(),
+            Command::Report("results.xml".into())
+        );
+    }
+
+    #[test]
+    fn parse_save() {
+        assert_eq!(
+            "save logo.png".parse::<Command>().unwrap(),
+            Command::Save("logo.png".into())
+        );
+        assert!("save".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn merge_replaces_reimported_requests() {
+        let request = |name: &str, url: &str| {
+            let mut req = Request::builder()
+                .name(name.into())
+                .method(carrier_pigeon_core::Method::Get)
+                .url(url.into())
+                .build();
+            req.folder = Some("Pets".into());
+            req
+        };
+        let mut coll = Collection::default();
+        coll.requests
+            .push(request("List", "https://pets.test/pets"));
+        coll.requests[0].response_filter = Some("$.pets".into());
+        coll.requests[0].pre_request_script = Some("vars.page = 2;".into());
+        let imported = Collection {
+            requests: vec![
+                request("List", "https://pets.test/v2/pets"),
+                request("Get", "https://pets.test/pets/1"),
+            ],
+            ..Default::default()
+        };
+
+        merge_collection(&mut coll, imported);
+
+        assert_eq!(coll.requests.len(), 2);
+        assert_eq!(coll.requests[0].url, "https://pets.test/v2/pets");
+        assert_eq!(coll.requests[0].response_filter.as_deref(), Some("$.pets"));
+        assert_eq!(
+            coll.requests[0].pre_request_script.as_deref(),
+            Some("vars.page = 2;")
+        );
+        assert_eq!(coll.requests[1].name, "Get");
+    }
+
+    #[test]
+    fn parse_examples() {
+        assert_eq!(
+            "example before-deploy".parse::<Command>().unwrap(),
+            Command::Example("before-deploy".into())
+        );
+        assert_eq!("diff".parse::<Command>().unwrap(), Command::Diff(None));
+        assert_eq!(
+            "diff before-deploy".parse::<Command>().unwrap(),
+            Command::Diff(Some("before-deploy".into()))
+        );
+        assert!("example".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn parse_compression() {
+        assert_eq!(
+            "compress br".parse::<Command>().unwrap(),
+            Command::Compress(Some(Encoding::Brotli))
+        );
+        assert_eq!(
+            "compress off".parse::<Command>().unwrap(),
+            Command::Compress(None)
+        );
+        assert_eq!(
+            "decompress off".parse::<Command>().unwrap(),
+            Command::Decompress(false)
+        );
+        assert!("compress lz4".parse::<Command>().is_err());
+        assert!("decompress maybe".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn parse_probe() {
+        assert_eq!("probe on".parse::<Command>().unwrap(), Command::Probe(true));
+        assert!("probe".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn parse_edit_body() {
+        assert_eq!("edit-body".parse::<Command>().unwrap(), Command::EditBody);
+        assert!("edit-body body.json".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn parse_bench() {
+        let Command::Bench(cmd) = "bench --sequence -c 4 -n 200".parse().unwrap() else {
+            panic!("expected bench command");
+        };
+        assert!(cmd.sequence);
+        assert_eq!(cmd.load.concurrency, 4);
+        assert_eq!(cmd.load.count, Some(200));
+        assert!("bench --frobnicate".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn parse_history() {
+        assert_eq!("history".parse::<Command>().unwrap(), Command::History);
+        assert!("history all".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn parse_environment() {
+        let cmd: Command = "env Staging".parse().unwrap();
+        assert_eq!(cmd, Command::Environment("Staging".into()));
+    }
+
+    #[test]
+    fn parse_unknown() {
+        assert!("import soap x.wsdl".parse::<Command>().is_err());
+        assert!("frobnicate".parse::<Command>().is_err());
+        assert!("".parse::<Command>().is_err());
+    }
+}
@@ -1,48 +1,123 @@
-use color_eyre::eyre::{bail, OptionExt, Result};
-use crossterm::event::{self, Event, KeyCode, KeyEvent};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::Parser;
+use color_eyre::eyre::{bail, eyre, OptionExt, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
 #[allow(unused_imports)]
 use reqwest::header::HeaderMap;
 use simplelog::{ColorChoice, CombinedLogger, LevelFilter, TermLogger, TerminalMode};
 use state::GlobalState;
-use std::{collections::HashMap, env, fs, path::PathBuf, time::Duration};
-use tokio::sync::mpsc;
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use time::{macros::format_description, OffsetDateTime};
+use tokio::sync::{mpsc, oneshot};
 
+mod cli;
+mod commands;
 mod errors;
 mod state;
 mod tui;
 mod ui;
 
+use carrier_pigeon_core::{
+    bench::{self, BenchStats},
+    body::BodyFormat,
+    collection::SerializedCollection,
+    diff::{Change, ResponseDiff},
+    executor::{self, Progress},
+    extract, filter,
+    formats::{http_file, Report},
+    history::{self, Retention},
+    runner::{self, Outcome, RunResult, RunSummary},
+    script,
+};
+
 use crate::state::{
-    App, Collection, Environment, EnvironmentValues, Mode, Pane, Secret, SerializedCollection,
+    App, Collection, Column, DiffView, EditTarget, Environment, EnvironmentValue,
+    EnvironmentValues, Execution, FilterKey, FilterOutput, FilterRun, HarPicker, Modal, Mode, Pane,
+    Request, RequestTab, ResponseTab, Runner, Secret,
 };
 
-#[allow(dead_code)]
+const PRE_REQUEST_SCRIPT_FILE: &str = "pre-request.rhai";
+
+/// Most text the response filter may output before it is stopped.
+const FILTER_OUTPUT_LIMIT: usize = 16 * 1024 * 1024;
+
 static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
 
 #[derive(Debug)]
 enum Message {
+    AddRow,
+    AdjustDelay(i64),
+    Backspace,
+    BenchFailed(String),
+    BenchProgress(Box<BenchStats>),
+    CloseModal,
+    Confirm,
+    CopyPath,
+    CopySelection,
     Crash(String),
+    CutSelection,
+    CycleBodyFormat,
+    DiffExample(Option<String>),
+    DiffHistory,
+    DownloadProgress(Progress),
+    Edit(KeyEvent),
+    EditBody,
+    EditFilter,
+    FilterFinished(FilterKey, Result<String, String>),
     LoadCollection(PathBuf),
     Input(char),
     ModeRequest(Mode),
     NewCollection,
+    NextField,
+    NextHunk,
+    NextTab,
+    Paste(String),
+    PreviousField,
+    PreviousHunk,
+    PreviousTab,
     Quit,
     RawKeyEvent(KeyEvent),
+    RequestFailed(String),
     RequestPane(Pane),
+    ResendHistory,
+    ResponseReceived(Box<Execution>),
+    RunFinished(Box<RunSummary>, Box<Collection>, Box<Collection>),
+    RunProgress(Box<RunResult>),
     SaveCollection,
     SaveGlobal,
     SelectDown,
     SelectLeft,
     SelectRight,
     SelectUp,
+    SendRequest,
     Start,
+    StartEdit(Column),
+    SubmitCommand,
+    SubmitFilter,
     ToggleDebug,
+    ToggleRawBody,
+    ToggleSelection,
+    ToggleStopOnFailure,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    if let Some(command) = cli::Cli::parse().command {
+        return run_headless(command).await;
+    }
+
     errors::install_hooks()?;
     let config = simplelog::ConfigBuilder::new()
         .set_time_format_custom(simplelog::format_description!(
@@ -80,7 +155,9 @@ async fn main() -> Result<()> {
     event_tx.send(Some(Message::Start)).await?;
 
     let event_thread_tx = event_tx.clone();
-    let event_thread = tokio::task::spawn_blocking(|| start_event_thread(event_thread_tx));
+    let (pause_tx, pause_rx) = std::sync::mpsc::channel::<Pause>();
+    let event_thread =
+        tokio::task::spawn_blocking(|| start_event_thread(event_thread_tx, pause_rx));
 
     while app.running {
         if event_thread.is_finished() {
@@ -94,10 +171,14 @@ async fn main() -> Result<()> {
             .unwrap_or_else(|| Some(Message::Crash(String::from("Event channel closed"))))
         {
             debug!("Processing Message: {:?}", msg);
-            if let Some(msg) = update(&mut app, msg)? {
+            if let Message::EditBody = msg {
+                // Needs the terminal itself, which `update` has no access to.
+                edit_body_externally(&mut app, &mut tui, &pause_tx).await?;
+            } else if let Some(msg) = update(&mut app, msg, &event_tx)? {
                 debug!("Update produced new message: {:?}", msg);
                 event_tx.send(Some(msg)).await?;
             }
+            refresh_filter(&mut app, &event_tx);
         }
     }
 
@@ -106,6 +187,25 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// Runs a collection without touching the terminal, so it works in CI where there is no TTY.
+async fn run_headless(command: cli::CliCommand) -> Result<()> {
+    color_eyre::install()?;
+    let _logger = simplelog::WriteLogger::init(
+        LevelFilter::Warn,
+        simplelog::Config::default(),
+        std::io::stderr(),
+    );
+
+    let passed = match command {
+        cli::CliCommand::Bench(args) => cli::bench(args).await?,
+        cli::CliCommand::Run(args) => cli::run(args).await?,
+    };
+    if !passed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
 fn load_global_state() -> Result<GlobalState> {
     use env_home::env_home_dir;
     let app_dir = env_home_dir().ok_or_eyre("Failed to open Home directory")?;
@@ -139,29 +239,502 @@ fn save_global_state(state: &GlobalState) -> Result<()> {
     Ok(())
 }
 
-fn start_event_thread(tx: mpsc::Sender<Option<Message>>) -> Result<()> {
+/// Sets the system clipboard with OSC 52, which also works over SSH.
+fn copy_to_clipboard(text: &str) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Writes a file that must not exist yet, readable only by the user, so nobody else sharing
+/// the temp directory can plant a symlink in its place or read it.
+fn write_new_file(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(content)
+}
+
+/// Hands the terminal to `$VISUAL` or `$EDITOR` to edit the selected request's body in a temp
+/// file. Only failing to take the terminal back is fatal, editor errors end up in the status.
+async fn edit_body_externally(
+    app: &mut App<'_>,
+    tui: &mut tui::Tui,
+    pause: &std::sync::mpsc::Sender<Pause>,
+) -> Result<()> {
+    let Some(req) = app.selected_request() else {
+        return Ok(());
+    };
+    let body = req.body.clone().unwrap_or_default();
+    let content_type = req
+        .headers
+        .iter()
+        .find(|header| header.name.eq_ignore_ascii_case("content-type"))
+        .map(|header| &*header.value);
+    let format = BodyFormat::detect(content_type, body.as_bytes());
+    let name: String = std::iter::repeat_with(fastrand::alphanumeric)
+        .take(16)
+        .collect();
+    let path = env::temp_dir().join(format!("carrier-pigeon-{}.{}", name, format.extension()));
+
+    // Pausing the event thread keeps it from reading the keys meant for the editor.
+    let (paused_tx, paused) = oneshot::channel();
+    let (resume, resume_rx) = std::sync::mpsc::channel();
+    pause
+        .send(Pause {
+            paused: paused_tx,
+            resume: resume_rx,
+        })
+        .map_err(|_| eyre!("Event thread stopped"))?;
+    paused.await.map_err(|_| eyre!("Event thread stopped"))?;
+    tui::restore()?;
+    // A file that was already there isn't ours to remove.
+    let edited = match write_new_file(&path, body.as_bytes()) {
+        Ok(()) => {
+            let edited = run_editor(&path).and_then(|()| Ok(fs::read_to_string(&path)?));
+            let _ = fs::remove_file(&path);
+            edited
+        }
+        Err(err) => Err(err.into()),
+    };
+    tui::resume(tui)?;
+    drop(resume);
+
+    let mut edited = match edited {
+        Ok(edited) => edited,
+        Err(err) => {
+            error!("Editing the body failed: {}", err);
+            app.status = Some(format!("Editing the body failed: {}", err));
+            return Ok(());
+        }
+    };
+    // Editors end files with a newline the body did not have.
+    if !body.ends_with('\n') && edited.ends_with('\n') {
+        edited.pop();
+        if edited.ends_with('\r') {
+            edited.pop();
+        }
+    }
+    let edited = Some(edited).filter(|edited| !edited.is_empty());
+    if let Some(req) = app.selected_request_mut() {
+        if req.body != edited {
+            info!("Updated the body of {}", req.name);
+            req.body = edited;
+            app.dirty = true;
+        }
+    }
+    Ok(())
+}
+
+/// Runs `$VISUAL`, `$EDITOR` or vi on `path` and waits for it to exit.
+fn run_editor(path: &Path) -> Result<()> {
+    let editor = ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| String::from("vi"));
+    let mut args = editor.split_whitespace();
+    let program = args.next().expect("Editor is not blank");
+    let status = std::process::Command::new(program)
+        .args(args)
+        .arg(path)
+        .status()
+        .map_err(|err| eyre!("Failed to start {}: {}", program, err))?;
+    if !status.success() {
+        bail!("{} exited with {}", program, status);
+    }
+    Ok(())
+}
+
+/// Starts the response filter on a thread of its own when the filter or the shown execution
+/// changed, cancelling the run it replaces.
+fn refresh_filter(app: &mut App, tx: &mpsc::Sender<Option<Message>>) {
+    let key = app.filter_key();
+    if app.filter.as_ref().map(|run| &run.key) == key.as_ref() {
+        return;
+    }
+    if let Some(run) = app.filter.take() {
+        run.cancel.store(true, Ordering::Relaxed);
+    }
+    let (Some(key), Some(idx)) = (key, app.selected_execution()) else {
+        return;
+    };
+
+    let body = app.history[idx].response.body.clone();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let (run_key, run_cancel, tx) = (key.clone(), cancel.clone(), tx.clone());
+    std::thread::spawn(move || {
+        let output = filter::output(&body, &run_key.1, FILTER_OUTPUT_LIMIT, &run_cancel)
+            .map_err(|err| err.to_string());
+        if !run_cancel.load(Ordering::Relaxed) {
+            let _ = tx.blocking_send(Some(Message::FilterFinished(run_key, output)));
+        }
+    });
+    app.filter = Some(FilterRun {
+        key,
+        output: FilterOutput::Running,
+        cancel,
+    });
+}
+
+/// Names a history entry by request and time, for display.
+fn history_label(execution: &Execution) -> Result<String> {
+    let started = execution
+        .started
+        .format(format_description!("[hour]:[minute]:[second]"))?;
+    Ok(format!("{} @ {}", execution.request.name, started))
+}
+
+/// Opens the diff modal, or reports why the responses couldn't be compared.
+fn show_diff(app: &mut App, title: String, diff: Result<ResponseDiff>) {
+    let diff = match diff {
+        Ok(diff) => diff,
+        Err(err) => {
+            app.status = Some(format!("Can't compare the responses: {}", err));
+            return;
+        }
+    };
+    info!(
+        "Comparing responses: {} header and {} body line changes",
+        diff.headers.len(),
+        diff.body
+            .iter()
+            .filter(|line| line.change != Change::Equal)
+            .count()
+    );
+    app.diff = Some(DiffView {
+        title,
+        diff,
+        scroll: 0,
+    });
+    app.window_state.modal = Modal::Diff {
+        from_history: app.window_state.modal == Modal::History,
+    };
+}
+
+/// Sends an already resolved request, reporting the result as a message.
+fn send_request(
+    app: &App,
+    req: Request,
+    env_name: Option<String>,
+    tx: &mpsc::Sender<Option<Message>>,
+) {
+    info!("Sending {} {}", req.method, req.url);
+    let client = app.client.clone();
+    let options = app
+        .collection
+        .as_ref()
+        .map(|coll| coll.settings.executor.clone())
+        .unwrap_or_default();
+    let tx = tx.clone();
+    tokio::spawn(async move {
+        let progress = tx.clone();
+        let on_progress = |received| {
+            let _ = progress.try_send(Some(Message::DownloadProgress(received)));
+        };
+        let msg = match executor::execute_with(&client, req, &options, on_progress).await {
+            Ok(mut execution) => {
+                execution.environment = env_name;
+                Message::ResponseReceived(Box::new(execution))
+            }
+            Err(err) => Message::RequestFailed(err.to_string()),
+        };
+        let _ = tx.send(Some(msg)).await;
+    });
+}
+
+/// The history file of the open collection, `.http` collections keep theirs in the work dir.
+fn history_path(app: &App) -> PathBuf {
+    let dir = app
+        .collection
+        .as_ref()
+        .and_then(|coll| coll.save_location.as_ref())
+        .filter(|location| !http_file::is_http_file(location))
+        .unwrap_or(&app.work_dir);
+    dir.join(history::HISTORY_FILE)
+}
+
+fn retention(app: &App) -> Retention {
+    app.collection
+        .as_ref()
+        .map(|coll| coll.settings.history.clone())
+        .unwrap_or_default()
+}
+
+/// Replaces the in-memory history with the collection's saved one, pruned to its retention.
+fn load_history(app: &mut App) -> Result<()> {
+    let path = history_path(app);
+    let Ok(content) = fs::read_to_string(&path) else {
+        app.history.clear();
+        return Ok(());
+    };
+    app.history = history::parse(&content);
+    let removed = retention(app).apply(&mut app.history, OffsetDateTime::now_utc());
+    if removed > 0 {
+        debug!("Removed {} old history entries", removed);
+        fs::write(
+            &path,
+            history::write(&app.history, app.collection.as_ref())?,
+        )?;
+    }
+    Ok(())
+}
+
+/// Adds an execution to the history and appends it to the history file.
+fn record_execution(app: &mut App, execution: Execution) -> Result<()> {
+    let path = history_path(app);
+    app.history.push(execution);
+    let retention = retention(app);
+    if retention.exceeded(&app.history, OffsetDateTime::now_utc()) {
+        retention.apply(&mut app.history, OffsetDateTime::now_utc());
+        fs::write(
+            &path,
+            history::write(&app.history, app.collection.as_ref())?,
+        )?;
+    } else if let Some(dir) = path.parent().filter(|dir| dir.is_dir()) {
+        let line = history::line(
+            app.history.last().expect("execution was just pushed"),
+            app.collection.as_ref(),
+        )?;
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(history::HISTORY_FILE))?
+            .write_all(line.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Loads a collection directory, or a single `.http` file.
+fn load_collection(path: PathBuf) -> Result<Collection> {
+    if http_file::is_http_file(&path) {
+        return load_http_file(path);
+    }
+
+    let request_dir = path.join("requests");
+    let requests = if let Ok(files) = fs::read_dir(request_dir) {
+        read_files(files)?
+    } else {
+        bail!("Failed to load requests from collection directory");
+    };
+
+    let env_dir = path.join("environments");
+    let environments = if let Ok(files) = fs::read_dir(env_dir) {
+        read_files(files)?
+    } else {
+        bail!("Failed to load environments from collection directory");
+    };
+
+    let order = fs::read(path.join("order")).ok().map(Vec::into_boxed_slice);
+    let variables = fs::read(path.join("variables"))
+        .ok()
+        .map(Vec::into_boxed_slice);
+    let pre_request_script = fs::read(path.join(PRE_REQUEST_SCRIPT_FILE))
+        .ok()
+        .map(Vec::into_boxed_slice);
+    let settings = fs::read(path.join("settings"))
+        .ok()
+        .map(Vec::into_boxed_slice);
+
+    Ok(Collection::deserialize(
+        path,
+        SerializedCollection {
+            requests,
+            order,
+            environments,
+            variables,
+            pre_request_script,
+            settings,
+        },
+    ))
+}
+
+/// Reads every file of a collection directory, keyed by file name.
+fn read_files(files: fs::ReadDir) -> Result<HashMap<Box<str>, Box<[u8]>>> {
+    files
+        .filter_map(|dir_entry| dir_entry.ok())
+        .map(|file| {
+            let path = file.path();
+            let data = fs::read(&path)
+                .map_err(|err| eyre!("Failed to read {}: {}", path.display(), err))?;
+            let name = file
+                .file_name()
+                .into_string()
+                .map_err(|_| eyre!("{} is not a UTF-8 file name", path.display()))?;
+            Ok((name.into_boxed_str(), data.into_boxed_slice()))
+        })
+        .collect()
+}
+
+fn load_http_file(path: PathBuf) -> Result<Collection> {
+    let (mut coll, report) = http_file::parse(&fs::read_to_string(&path)?);
+    report
+        .entries
+        .iter()
+        .for_each(|entry| warn!("Loading {} - {}", path.display(), entry));
+
+    let dir = path.parent().unwrap_or(&path);
+    let (environments, shared) = http_file::parse_environments(
+        fs::read_to_string(dir.join(http_file::PUBLIC_ENV_FILE))
+            .ok()
+            .as_deref(),
+        fs::read_to_string(dir.join(http_file::PRIVATE_ENV_FILE))
+            .ok()
+            .as_deref(),
+    )?;
+    coll.environments = environments;
+    coll.variables.extend(shared);
+    coll.save_location = Some(path);
+    Ok(coll)
+}
+
+/// Writes the collection to its save location, the working directory when it has none.
+/// Removes the files in `dir` that aren't in `files`, left behind by renamed or deleted requests
+/// and environments, which would otherwise be loaded again.
+fn remove_stale_files(dir: &Path, files: &HashMap<Box<str>, Box<[u8]>>) -> Result<()> {
+    for file in fs::read_dir(dir)? {
+        let file = file?;
+        let stale = file
+            .file_name()
+            .to_str()
+            .is_none_or(|name| !files.contains_key(name));
+        if stale && file.file_type()?.is_file() {
+            debug!("Removing stale collection file {}", file.path().display());
+            fs::remove_file(file.path())?;
+        }
+    }
+    Ok(())
+}
+
+fn save_collection(coll: &Collection, work_dir: &Path) -> Result<()> {
+    if let Some(path) = coll
+        .save_location
+        .as_deref()
+        .filter(|path| http_file::is_http_file(path))
+    {
+        info!("Saving current collection as .http file");
+        return save_http_file(coll, path);
+    }
+    info!("Saving current collection");
+    let ser_collection = coll.serialize();
+    let path = coll.save_location.as_deref().unwrap_or(work_dir);
+    if !path.exists() {
+        fs::create_dir_all(path)?;
+    }
+
+    let req_dir = path.join("requests");
+    if !req_dir.exists() {
+        fs::create_dir(&req_dir)?;
+    }
+    for (name, request) in &ser_collection.requests {
+        fs::write(req_dir.join(&**name), request)?;
+    }
+    remove_stale_files(&req_dir, &ser_collection.requests)?;
+
+    let env_dir = path.join("environments");
+    if !env_dir.exists() {
+        fs::create_dir(&env_dir)?;
+    }
+    for (name, environment) in &ser_collection.environments {
+        fs::write(env_dir.join(&**name), environment)?;
+    }
+    remove_stale_files(&env_dir, &ser_collection.environments)?;
+
+    if let Some(order) = &ser_collection.order {
+        fs::write(path.join("order"), order)?;
+    }
+    if let Some(variables) = &ser_collection.variables {
+        fs::write(path.join("variables"), variables)?;
+    }
+    if let Some(script) = &ser_collection.pre_request_script {
+        fs::write(path.join(PRE_REQUEST_SCRIPT_FILE), script)?;
+    }
+    if let Some(settings) = &ser_collection.settings {
+        fs::write(path.join("settings"), settings)?;
+    }
+    Ok(())
+}
+
+fn save_http_file(coll: &Collection, path: &Path) -> Result<()> {
+    let (text, report) = http_file::write(coll);
+    report
+        .entries
+        .iter()
+        .for_each(|entry| warn!("Saving {} - {}", path.display(), entry));
+    fs::write(path, text)?;
+
+    let dir = path.parent().unwrap_or(path);
+    let (public, private) = http_file::write_environments(coll)?;
+    if let Some(public) = public {
+        fs::write(dir.join(http_file::PUBLIC_ENV_FILE), public)?;
+    }
+    if let Some(private) = private {
+        fs::write(dir.join(http_file::PRIVATE_ENV_FILE), private)?;
+    }
+    Ok(())
+}
+
+/// Asks the event thread to stop reading terminal input, e.g. while an external editor runs.
+/// The thread answers on `paused` once it stopped and reads again when `resume`'s sender is
+/// dropped.
+struct Pause {
+    paused: oneshot::Sender<()>,
+    resume: std::sync::mpsc::Receiver<()>,
+}
+
+/// Reads terminal events, unless paused, and sends an empty message every couple of seconds so
+/// the UI redraws.
+fn start_event_thread(
+    tx: mpsc::Sender<Option<Message>>,
+    pauses: std::sync::mpsc::Receiver<Pause>,
+) -> Result<()> {
+    let mut last_sent = Instant::now();
     loop {
+        if let Ok(pause) = pauses.try_recv() {
+            debug!("Pausing the event thread");
+            let _ = pause.paused.send(());
+            let _ = pause.resume.recv();
+            continue;
+        }
         trace!("Polling for Event");
-        let msg = if event::poll(Duration::from_millis(2000))? {
+        // Polls briefly so a pause takes effect soon after an external editor asks for it.
+        let event = if event::poll(Duration::from_millis(100))? {
             trace!("Event Ready");
-            match event::read()? {
-                Event::Key(key_event) => Some(Message::RawKeyEvent(key_event)),
-                _ => None,
-            }
+            Some(event::read()?)
         } else {
             None
         };
+        let msg = match event {
+            Some(Event::Key(key_event)) => Some(Message::RawKeyEvent(key_event)),
+            Some(Event::Paste(text)) => Some(Message::Paste(text)),
+            Some(_) => None,
+            None if last_sent.elapsed() >= Duration::from_millis(2000) => None,
+            None => continue,
+        };
+        last_sent = Instant::now();
         trace!("Sending Message from Event thread: {:?}", msg);
         tx.blocking_send(msg)?;
     }
 }
 
-fn update(app: &mut App, msg: Message) -> Result<Option<Message>> {
+fn update(
+    app: &mut App,
+    msg: Message,
+    tx: &mpsc::Sender<Option<Message>>,
+) -> Result<Option<Message>> {
     trace!("Start update app state: {:?}", &app);
     let msg = if let Message::RawKeyEvent(event) = msg {
-        let msg = match app.mode {
-            Mode::Insert => handle_insert_key(event),
-            Mode::Normal => handle_normal_key(event),
+        let msg = if app.window_state.modal != Modal::None {
+            handle_modal_key(event)
+        } else {
+            match app.mode {
+                Mode::Insert => handle_insert_key(event),
+                Mode::Normal => handle_normal_key(event),
+                Mode::Command => handle_command_key(event),
+                Mode::Filter => handle_filter_key(event),
+            }
         };
         if msg.is_none() {
             return Ok(None);
@@ -172,74 +745,377 @@ fn update(app: &mut App, msg: Message) -> Result<Option<Message>> {
     };
 
     let msg = match msg {
-        Message::Crash(message) => {
-            bail!("{}", message);
+        Message::AddRow => {
+            let tab = app.window_state.req_state.selected_tab.clone();
+            if app.window_state.focused_pane != Pane::Request || !tab.is_table() {
+                return Ok(None);
+            }
+            let Some(len) = app
+                .selected_request()
+                .map(|req| state::table_rows(req, &tab).len())
+            else {
+                return Ok(None);
+            };
+            app.start_edit(EditTarget::Cell(tab, len, Column::Name));
+            None
         }
-        Message::Input(char) => {
-            trace!("Input character recieved: '{}'", char);
+        Message::Backspace => {
+            if let Mode::Command | Mode::Filter = app.mode {
+                app.input_buf.pop();
+            }
             None
         }
-        Message::LoadCollection(path) => {
-            info!("Loading collection at: {}", &path.display().to_string());
-
-            let request_dir = path.join("requests");
-            let requests: HashMap<Box<str>, Box<[u8]>> =
-                if let Ok(files) = fs::read_dir(request_dir) {
-                    files.filter_map(|dir_entry| dir_entry.ok()).fold(
-                        HashMap::new(),
-                        |mut reqs, file| {
-                            let data = fs::read(file.path()).unwrap();
-                            reqs.insert(
-                                file.file_name().into_string().unwrap().into_boxed_str(),
-                                data.into_boxed_slice(),
-                            );
-                            reqs
-                        },
-                    )
+        Message::BenchFailed(err) => {
+            error!("Benchmark failed: {}", err);
+            app.status = Some(format!("Benchmark failed: {}", err));
+            if let Some(bench) = &mut app.bench {
+                bench.stats = None;
+            }
+            None
+        }
+        Message::BenchProgress(stats) => {
+            if stats.finished {
+                info!(
+                    "Benchmark finished: {} requests, {:.1} req/s, {} errors",
+                    stats.requests,
+                    stats.requests_per_second(),
+                    stats.error_count()
+                );
+            }
+            if let Some(bench) = &mut app.bench {
+                bench.stats = Some(*stats);
+            }
+            None
+        }
+        Message::CloseModal => {
+            if let (Modal::Bench, Some(bench)) = (&app.window_state.modal, &app.bench) {
+                bench.stop.store(true, Ordering::Relaxed);
+            }
+            // Closing a diff opened from the history goes back to it.
+            app.window_state.modal = match app.window_state.modal {
+                Modal::Diff { from_history: true } => Modal::History,
+                _ => Modal::None,
+            };
+            app.har_picker = None;
+            app.diff = None;
+            None
+        }
+        Message::Confirm => match app.window_state.modal {
+            Modal::HarImport => {
+                app.window_state.modal = Modal::None;
+                if let Some(picker) = app.har_picker.take() {
+                    let mut report = Report::default();
+                    let requests: Vec<_> = picker
+                        .har
+                        .log
+                        .entries
+                        .iter()
+                        .zip(picker.keep)
+                        .filter(|(_, keep)| *keep)
+                        .filter_map(|(entry, _)| entry.to_request(&mut report))
+                        .collect();
+                    report
+                        .entries
+                        .iter()
+                        .for_each(|entry| warn!("HAR import - {}", entry));
+                    info!("Importing {} requests from HAR", requests.len());
+                    app.collection
+                        .get_or_insert_with(Collection::default)
+                        .requests
+                        .extend(requests);
+                    Some(Message::SaveCollection)
                 } else {
-                    bail!("Failed to load requests from collection directory");
+                    None
+                }
+            }
+            Modal::Runner => {
+                let (Some(runner), Some(coll)) = (&mut app.runner, &app.collection) else {
+                    return Ok(None);
                 };
+                if runner.running {
+                    return Ok(None);
+                }
+                let selection = runner.selection();
+                info!(
+                    "Running {} requests over {} iterations",
+                    selection.len(),
+                    runner.options.data.len().max(1)
+                );
+                runner.results.clear();
+                runner.summary = None;
+                runner.running = true;
 
-            let env_dir = path.join("environments");
-            let environments: HashMap<Box<str>, Box<[u8]>> =
-                if let Ok(files) = fs::read_dir(env_dir) {
-                    files.filter_map(|dir_entry| dir_entry.ok()).fold(
-                        HashMap::new(),
-                        |mut envs, file| {
-                            let data = fs::read(file.path()).unwrap();
-                            envs.insert(
-                                file.file_name().into_string().unwrap().into_boxed_str(),
-                                data.into_boxed_slice(),
-                            );
-                            envs
+                let start = coll.clone();
+                let mut coll = coll.clone();
+                let options = runner.options.clone();
+                let env = app.active_env.clone();
+                let client = app.client.clone();
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    // Results are queued without bound and forwarded in order, so a full UI
+                    // channel slows the results view down instead of losing entries.
+                    let (results_tx, mut results_rx) = mpsc::unbounded_channel();
+                    let progress = tx.clone();
+                    let forward = tokio::spawn(async move {
+                        while let Some(result) = results_rx.recv().await {
+                            let msg = Message::RunProgress(Box::new(result));
+                            if progress.send(Some(msg)).await.is_err() {
+                                break;
+                            }
+                        }
+                    });
+                    let summary = runner::run(
+                        &client,
+                        &mut coll,
+                        &selection,
+                        env.as_deref(),
+                        &options,
+                        |result| {
+                            let _ = results_tx.send(result.clone());
                         },
                     )
-                } else {
-                    bail!("Failed to load environments from collection directory");
+                    .await;
+                    drop(results_tx);
+                    let _ = forward.await;
+                    let _ = tx
+                        .send(Some(Message::RunFinished(
+                            Box::new(summary),
+                            Box::new(start),
+                            Box::new(coll),
+                        )))
+                        .await;
+                });
+                None
+            }
+            Modal::History => {
+                let Some(execution) = app.selected_history() else {
+                    return Ok(None);
                 };
+                // The stored request is the resolved one, with secrets in it, so it is opened as
+                // a scratch request and never saved.
+                let mut req = execution.request.clone();
+                req.name = history_label(execution)?;
+                info!("Opening {} from history", req.name);
+                app.scratch = Some(req);
+                app.window_state.select_list_state.select(None);
+                app.window_state.modal = Modal::None;
+                None
+            }
+            Modal::Bench => {
+                let (Some(bench), Some(coll)) = (&mut app.bench, &app.collection) else {
+                    return Ok(None);
+                };
+                if bench.running() {
+                    return Ok(None);
+                }
+                info!(
+                    "Benchmarking {} with {} workers",
+                    bench.target, bench.options.concurrency
+                );
+                bench.stats = Some(BenchStats::default());
+                bench.stop = Default::default();
 
-            app.collection = Some(Collection::deserialize(
-                path,
-                SerializedCollection {
-                    requests,
-                    environments,
-                },
-            ));
-            app.window_state.select_list_state.select_first();
+                let coll = coll.clone();
+                let selection = bench.selection.clone();
+                let options = bench.options.clone();
+                let stop = bench.stop.clone();
+                let env = app.active_env.clone();
+                let client = app.client.clone();
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    let progress = tx.clone();
+                    let result = bench::bench(
+                        &client,
+                        &coll,
+                        &selection,
+                        env.as_deref(),
+                        &options,
+                        stop,
+                        |stats| {
+                            let msg = Message::BenchProgress(Box::new(stats.clone()));
+                            let _ = progress.try_send(Some(msg));
+                        },
+                    )
+                    .await;
+                    let msg = match result {
+                        Ok(stats) => Message::BenchProgress(Box::new(stats)),
+                        Err(err) => Message::BenchFailed(err.to_string()),
+                    };
+                    let _ = tx.send(Some(msg)).await;
+                });
+                None
+            }
+            _ => None,
+        },
+        Message::CopyPath => {
+            let Some(path) = app
+                .json_focused()
+                .then(|| app.window_state.res_state.json.selected_path())
+                .flatten()
+            else {
+                return Ok(None);
+            };
+            copy_to_clipboard(&path)?;
+            info!("Copied {}", path);
+            None
+        }
+        Message::CopySelection => {
+            if let Some(text) = app
+                .editor
+                .as_ref()
+                .and_then(|editor| editor.text.selected_text())
+            {
+                copy_to_clipboard(&text)?;
+            }
+            None
+        }
+        Message::CutSelection => {
+            if let Some(text) = app
+                .editor
+                .as_mut()
+                .and_then(|editor| editor.text.delete_selection())
+            {
+                copy_to_clipboard(&text)?;
+            }
+            None
+        }
+        Message::Crash(message) => {
+            bail!("{}", message);
+        }
+        Message::CycleBodyFormat => {
+            let state = &app.window_state;
+            if state.focused_pane != Pane::Response
+                || state.res_state.selected_tab != ResponseTab::Body
+            {
+                return Ok(None);
+            }
+            if let Some(format) = app.body_format() {
+                debug!("Showing response body as {}", format.next());
+                app.window_state.res_state.body_format = Some(format.next());
+            }
+            None
+        }
+        Message::DiffExample(name) => {
+            let Some(req) = app.selected_request() else {
+                return Ok(None);
+            };
+            let example = match &name {
+                Some(name) => req.examples.iter().find(|example| &example.name == name),
+                None => req.examples.first(),
+            };
+            let current = app.selected_execution().map(|idx| &app.history[idx]);
+            let (example, current) = match (example, current) {
+                (Some(example), Some(current)) => (example, current),
+                (None, _) => {
+                    app.status = Some(match name {
+                        Some(name) => format!("{} has no example named {}", req.name, name),
+                        None => format!("{} has no saved examples", req.name),
+                    });
+                    return Ok(None);
+                }
+                (_, None) => {
+                    app.status = Some(String::from("The request has no response to compare"));
+                    return Ok(None);
+                }
+            };
+            let title = format!("example {} → current response", example.name);
+            let diff = ResponseDiff::new(&example.response, &current.response);
+            show_diff(app, title, diff);
+            None
+        }
+        Message::DiffHistory => {
+            let (Modal::History, Some(selected)) =
+                (&app.window_state.modal, app.selected_history())
+            else {
+                return Ok(None);
+            };
+            // Without a marked entry the selected one is compared with the response shown for the
+            // selected request.
+            let current = app.selected_execution().map(|idx| &app.history[idx]);
+            let (old, new, new_label) = match (&app.history_mark, current) {
+                (Some(mark), _) => (mark, selected, history_label(selected)?),
+                (None, Some(current)) => (selected, current, String::from("current response")),
+                (None, None) => {
+                    app.status = Some(String::from("The request has no response to compare"));
+                    return Ok(None);
+                }
+            };
+            let title = format!("{} → {}", history_label(old)?, new_label);
+            let diff = ResponseDiff::new(&old.response, &new.response);
+            show_diff(app, title, diff);
+            None
+        }
+        Message::DownloadProgress(progress) => {
+            app.download = Some(progress);
+            None
+        }
+        Message::Edit(key) => {
+            let Some(editor) = &mut app.editor else {
+                return Ok(None);
+            };
+            // Enter isn't an edit in single line fields, it finishes them.
+            if !editor.text.input(key) && key.code == KeyCode::Enter {
+                return Ok(Some(Message::ModeRequest(Mode::Normal)));
+            }
+            None
+        }
+        Message::EditFilter => {
+            let Some(req) = app.selected_request() else {
+                return Ok(None);
+            };
+            app.input_buf = req.response_filter.clone().unwrap_or_default();
+            app.mode = Mode::Filter;
+            app.window_state.focused_pane = Pane::Response;
+            app.window_state.res_state.selected_tab = ResponseTab::Body;
+            None
+        }
+        Message::FilterFinished(key, output) => {
+            if let Some(run) = app.filter.as_mut().filter(|run| run.key == key) {
+                run.output = FilterOutput::Done(output);
+            }
+            None
+        }
+        Message::Input(char) => {
+            trace!("Input character recieved: '{}'", char);
+            if let Mode::Command | Mode::Filter = app.mode {
+                app.input_buf.push(char);
+            }
+            None
+        }
+        Message::LoadCollection(path) => {
+            info!("Loading collection at: {}", path.display());
+            match load_collection(path.clone()) {
+                Ok(coll) => {
+                    app.collection = Some(coll);
+                    app.scratch = None;
+                    app.dirty = false;
+                    app.window_state.select_list_state.select_first();
+                    if let Err(err) = load_history(app) {
+                        error!("Loading the history failed: {}", err);
+                        app.status = Some(format!("Loading the history failed: {}", err));
+                    }
+                }
+                Err(err) => {
+                    error!("Loading {} failed: {}", path.display(), err);
+                    app.status = Some(format!("Loading {} failed: {}", path.display(), err));
+                }
+            }
             None
         }
         Message::ModeRequest(mode) => {
             trace!("Mode Reqeust: {:?}", mode);
+            app.commit_edit();
+            app.input_buf.clear();
             app.mode = mode;
             None
         }
         Message::NewCollection => {
             debug!("Creating new collection");
-            let mut coll = crate::state::Collection::default();
+            let mut coll = Collection::default();
             let mut env_vals: EnvironmentValues = HashMap::new();
             env_vals.insert(
                 "TestValue".into(),
-                state::EnvironmentValue::Value("Some value".into()),
+                EnvironmentValue::Value("Some value".into()),
             );
             coll.environments.push(Environment {
                 name: "TestEnvironment".into(),
@@ -252,57 +1128,134 @@ fn update(app: &mut App, msg: Message) -> Result<Option<Message>> {
         Message::Quit => {
             info!("Quitting...");
             app.running = false;
-            update(app, Message::SaveCollection)?;
-            update(app, Message::SaveGlobal)?;
+            update(app, Message::SaveCollection, tx)?;
+            update(app, Message::SaveGlobal, tx)?;
+            None
+        }
+        Message::RequestFailed(err) => {
+            error!("Request failed: {}", err);
+            app.download = None;
+            app.status = Some(format!("Request failed: {}", err));
+            None
+        }
+        Message::RunProgress(result) => {
+            match &result.outcome {
+                Outcome::Completed(execution) => {
+                    info!(
+                        "Run: {} {} responded {} in {}ms",
+                        result.method,
+                        result.name,
+                        execution.response.status,
+                        execution.response.duration.as_millis()
+                    );
+                    if let Err(err) = record_execution(app, *execution.clone()) {
+                        error!("Saving the history failed: {}", err);
+                        app.status = Some(format!("Saving the history failed: {}", err));
+                    }
+                }
+                Outcome::Failed(err) => error!("Run: {} failed: {}", result.name, err),
+            }
+            if let Some(runner) = &mut app.runner {
+                runner.results.push(*result);
+            }
+            None
+        }
+        Message::RunFinished(summary, start, coll) => {
+            info!(
+                "Run finished: {} passed, {} failed in {}ms",
+                summary.passed,
+                summary.failed,
+                summary.duration.as_millis()
+            );
+            if let Some(runner) = &mut app.runner {
+                runner.running = false;
+                runner.summary = Some(*summary);
+            }
+            // Keep the variables the run's scripts and extraction rules changed, without undoing
+            // what was changed while it ran.
+            let changed = app
+                .collection
+                .as_mut()
+                .is_some_and(|existing| existing.merge_variables(&start, &coll));
+            if changed {
+                app.dirty = true;
+            }
+            changed.then_some(Message::SaveCollection)
+        }
+        Message::NextField => {
+            app.edit_next_field(true);
+            None
+        }
+        Message::PreviousField => {
+            app.edit_next_field(false);
+            None
+        }
+        Message::Paste(text) => {
+            match (&app.mode, &mut app.editor) {
+                (Mode::Insert, Some(editor)) => editor.text.insert_str(&text),
+                (Mode::Command | Mode::Filter, _) => {
+                    app.input_buf.push_str(&text.replace(['\r', '\n'], " "))
+                }
+                _ => {}
+            }
             None
         }
         Message::RequestPane(pane) => {
             app.window_state.focused_pane = pane;
             None
         }
+        Message::ResponseReceived(execution) => {
+            info!(
+                "{} {} responded {} in {}ms",
+                execution.request.method,
+                execution.request.url,
+                execution.response.status,
+                execution.response.duration.as_millis()
+            );
+            execution
+                .tests
+                .iter()
+                .filter(|result| !result.passed)
+                .for_each(|result| warn!("{}", result));
+            let mut extracted = false;
+            if let Some(coll) = &mut app.collection {
+                let (variables, environments) = (coll.variables.clone(), coll.environments.clone());
+                extract::apply(coll, app.active_env.as_deref(), &execution)
+                    .iter()
+                    .for_each(|err| warn!("{}", err));
+                extracted = coll.variables != variables || coll.environments != environments;
+            }
+            if let Some(spilled) = &execution.response.spilled {
+                info!(
+                    "Response body of {} bytes written to: {}",
+                    spilled.len,
+                    spilled.path.display()
+                );
+            }
+            app.download = None;
+            app.window_state.res_state.hex_scroll = 0;
+            app.window_state.res_state.spill_offset = 0;
+            if let Err(err) = record_execution(app, *execution) {
+                error!("Saving the history failed: {}", err);
+                app.status = Some(format!("Saving the history failed: {}", err));
+            }
+            // Extracted values are saved right away, the collection stays dirty if that fails.
+            if extracted {
+                app.dirty = true;
+            }
+            extracted.then_some(Message::SaveCollection)
+        }
         Message::SaveCollection => {
-            info!("Saving current collection");
-            let (ser_collection, path) = if let Some(coll) = &app.collection {
-                let save_path = match &coll.save_location {
-                    Some(local) => local,
-                    None => &app.work_dir,
-                };
-                (coll.serialize(), save_path)
-            } else {
+            let Some(coll) = &app.collection else {
                 bail!("Attempted to serialize a none collection");
             };
-            if !path.exists() {
-                fs::create_dir_all(path)?;
-            }
-
-            let req_dir = path.join("requests");
-            if !req_dir.exists() {
-                fs::create_dir(&req_dir)?;
+            match save_collection(coll, &app.work_dir) {
+                Ok(()) => app.dirty = false,
+                Err(err) => {
+                    error!("Saving the collection failed: {}", err);
+                    app.status = Some(format!("Saving the collection failed: {}", err));
+                }
             }
-            ser_collection.requests.keys().for_each(|req_key| {
-                let _ = fs::write(
-                    req_dir.join(req_key.clone().into_string()),
-                    ser_collection
-                        .requests
-                        .get(req_key)
-                        .expect("Failed retrieveing from map inside iterator"),
-                );
-            });
-
-            let env_dir = path.join("environments");
-            if !env_dir.exists() {
-                fs::create_dir(&env_dir)?;
-            }
-            ser_collection.environments.keys().for_each(|env_key| {
-                let _ = fs::write(
-                    env_dir.join(env_key.clone().into_string()),
-                    ser_collection
-                        .environments
-                        .get(env_key)
-                        .expect("Failed retrieving from map inside iterator"),
-                );
-            });
-
             None
         }
         Message::SaveGlobal => {
@@ -312,9 +1265,27 @@ fn update(app: &mut App, msg: Message) -> Result<Option<Message>> {
         }
         Message::SelectDown => {
             trace!("Select Down");
+            if let (Modal::Diff { .. }, Some(view)) = (&app.window_state.modal, &mut app.diff) {
+                view.scroll_by(1);
+                return Ok(None);
+            }
+            if let Some(list_state) = app.modal_list_state() {
+                list_state.select_next();
+                return Ok(None);
+            }
             match app.window_state.focused_pane {
-                Pane::Select => app.window_state.select_list_state.select_next(),
-                Pane::Request => {}
+                Pane::Select => {
+                    app.scratch = None;
+                    app.window_state.select_list_state.select_next();
+                    app.window_state.req_state.table_state.select(None);
+                    app.window_state.res_state.body_format = None;
+                    app.window_state.res_state.hex_scroll = 0;
+                    app.window_state.res_state.spill_offset = 0;
+                }
+                Pane::Request => app.select_row(1),
+                Pane::Response if app.json_focused() => app.window_state.res_state.json.move_by(1),
+                Pane::Response if app.hex_focused() => app.scroll_hex(1),
+                Pane::Response if app.spill_focused() => app.scroll_spill(1)?,
                 Pane::Response => {}
                 Pane::Url => {}
             }
@@ -322,6 +1293,13 @@ fn update(app: &mut App, msg: Message) -> Result<Option<Message>> {
         }
         Message::SelectLeft => {
             trace!("Select Left");
+            if app.json_focused() {
+                app.window_state.res_state.json.fold();
+                return Ok(None);
+            }
+            Some(Message::PreviousTab)
+        }
+        Message::PreviousTab => {
             match app.window_state.focused_pane {
                 Pane::Select => {}
                 Pane::Request => {
@@ -338,6 +1316,13 @@ fn update(app: &mut App, msg: Message) -> Result<Option<Message>> {
         }
         Message::SelectRight => {
             trace!("Select Right");
+            if app.json_focused() {
+                app.window_state.res_state.json.unfold();
+                return Ok(None);
+            }
+            Some(Message::NextTab)
+        }
+        Message::NextTab => {
             match app.window_state.focused_pane {
                 Pane::Select => {}
                 Pane::Request => {
@@ -354,20 +1339,185 @@ fn update(app: &mut App, msg: Message) -> Result<Option<Message>> {
         }
         Message::SelectUp => {
             trace!("Select Up");
+            if let (Modal::Diff { .. }, Some(view)) = (&app.window_state.modal, &mut app.diff) {
+                view.scroll_by(-1);
+                return Ok(None);
+            }
+            if let Some(list_state) = app.modal_list_state() {
+                list_state.select_previous();
+                return Ok(None);
+            }
             match app.window_state.focused_pane {
-                Pane::Select => app.window_state.select_list_state.select_previous(),
-                Pane::Request => {}
+                Pane::Select => {
+                    app.scratch = None;
+                    app.window_state.select_list_state.select_previous();
+                    app.window_state.req_state.table_state.select(None);
+                    app.window_state.res_state.body_format = None;
+                    app.window_state.res_state.hex_scroll = 0;
+                    app.window_state.res_state.spill_offset = 0;
+                }
+                Pane::Request => app.select_row(-1),
+                Pane::Response if app.json_focused() => app.window_state.res_state.json.move_by(-1),
+                Pane::Response if app.hex_focused() => app.scroll_hex(-1),
+                Pane::Response if app.spill_focused() => app.scroll_spill(-1)?,
                 Pane::Response => {}
                 Pane::Url => {}
             }
             None
         }
+        Message::SendRequest => {
+            let (prepared, scripted) = match (app.selected_request().cloned(), &mut app.collection)
+            {
+                (Some(req), Some(coll)) => {
+                    let (variables, environments) =
+                        (coll.variables.clone(), coll.environments.clone());
+                    let prepared =
+                        script::prepare(coll, &req, app.active_env.as_deref(), &HashMap::new());
+                    let scripted = coll.variables != variables || coll.environments != environments;
+                    (prepared, scripted)
+                }
+                _ => return Ok(None),
+            };
+            match prepared {
+                Ok(prepared) => {
+                    prepared
+                        .output
+                        .iter()
+                        .for_each(|line| info!("Script: {}", line));
+                    app.status = None;
+
+                    send_request(app, prepared.request, app.active_env.clone(), tx);
+                }
+                Err(err) => {
+                    error!("{}", err);
+                    app.status = Some(err.to_string());
+                }
+            }
+            // Variables set by the scripts are saved like extracted ones.
+            if scripted {
+                app.dirty = true;
+            }
+            scripted.then_some(Message::SaveCollection)
+        }
         Message::Start => load_application(app)?,
+        Message::StartEdit(column) => {
+            let state = &app.window_state;
+            let target = match (&state.focused_pane, &state.req_state.selected_tab) {
+                (Pane::Url, _) => EditTarget::Url,
+                (Pane::Request, RequestTab::Body) => EditTarget::Body,
+                (Pane::Request, tab) if tab.is_table() => {
+                    let len = app
+                        .selected_request()
+                        .map_or(0, |req| state::table_rows(req, tab).len());
+                    let row = state.req_state.table_state.selected().unwrap_or(0).min(len);
+                    // An empty table starts with the name of its first row.
+                    let column = if row == len { Column::Name } else { column };
+                    EditTarget::Cell(tab.clone(), row, column)
+                }
+                _ => return Ok(None),
+            };
+            if !app.start_edit(target) {
+                debug!("No request to edit");
+            }
+            None
+        }
+        Message::SubmitCommand => {
+            let input = std::mem::take(&mut app.input_buf);
+            app.mode = Mode::Normal;
+            debug!("Running command: {}", input);
+            match input
+                .parse::<commands::Command>()
+                .and_then(|cmd| commands::execute(app, cmd))
+            {
+                Ok(msg) => msg,
+                Err(err) => {
+                    error!("Command '{}' failed: {}", input, err);
+                    app.status = Some(err.to_string());
+                    None
+                }
+            }
+        }
+        Message::NextHunk => {
+            if let (Modal::Diff { .. }, Some(view)) = (&app.window_state.modal, &mut app.diff) {
+                view.next_hunk();
+            }
+            None
+        }
+        Message::PreviousHunk => {
+            if let (Modal::Diff { .. }, Some(view)) = (&app.window_state.modal, &mut app.diff) {
+                view.previous_hunk();
+            }
+            None
+        }
+        Message::ResendHistory => {
+            let (Modal::History, Some(execution)) =
+                (&app.window_state.modal, app.selected_history())
+            else {
+                return Ok(None);
+            };
+            // The stored request is already resolved, so it is sent as it is, without scripts.
+            let req = execution.request.clone();
+            let env = execution.environment.clone();
+            app.window_state.modal = Modal::None;
+            send_request(app, req, env, tx);
+            None
+        }
+        Message::SubmitFilter => {
+            let input = std::mem::take(&mut app.input_buf);
+            app.mode = Mode::Normal;
+            let Some(req) = app.selected_request_mut() else {
+                return Ok(None);
+            };
+            let filter = Some(input.trim().to_string()).filter(|filter| !filter.is_empty());
+            if req.response_filter == filter {
+                return Ok(None);
+            }
+            info!("Response filter of {}: {:?}", req.name, filter);
+            req.response_filter = filter;
+            Some(Message::SaveCollection)
+        }
         Message::ToggleDebug => {
             trace!("Debug Toggle");
             app.show_debug = !app.show_debug;
             None
         }
+        Message::ToggleRawBody => {
+            let state = &mut app.window_state;
+            if state.focused_pane == Pane::Response
+                && state.res_state.selected_tab == ResponseTab::Body
+            {
+                state.res_state.raw = !state.res_state.raw;
+            }
+            None
+        }
+        Message::ToggleSelection => {
+            match app.window_state.modal {
+                Modal::HarImport => app.har_picker.as_mut().map(HarPicker::toggle_selected),
+                Modal::Runner => app.runner.as_mut().map(Runner::toggle_selected),
+                Modal::History => {
+                    let selected = app.selected_history().cloned();
+                    app.history_mark = match (app.history_mark.take(), selected) {
+                        (Some(mark), Some(selected)) if mark == selected => None,
+                        (_, selected) => selected,
+                    };
+                    None
+                }
+                _ => None,
+            };
+            None
+        }
+        Message::ToggleStopOnFailure => {
+            if let (Modal::Runner, Some(runner)) = (&app.window_state.modal, &mut app.runner) {
+                runner.options.stop_on_failure = !runner.options.stop_on_failure;
+            }
+            None
+        }
+        Message::AdjustDelay(millis) => {
+            if let (Modal::Runner, Some(runner)) = (&app.window_state.modal, &mut app.runner) {
+                runner.adjust_delay(millis);
+            }
+            None
+        }
         _ => None,
     };
     trace!("End Update app state: {:?}", &app);
@@ -381,13 +1531,23 @@ fn handle_normal_key(key_event: KeyEvent) -> Option<Message> {
             KeyCode::Char('2') => Some(Message::RequestPane(Pane::Url)),
             KeyCode::Char('3') => Some(Message::RequestPane(Pane::Request)),
             KeyCode::Char('4') => Some(Message::RequestPane(Pane::Response)),
-            KeyCode::Char('i') => Some(Message::ModeRequest(Mode::Insert)),
+            KeyCode::Char('i') => Some(Message::StartEdit(Column::Value)),
+            KeyCode::Char('I') => Some(Message::StartEdit(Column::Name)),
+            KeyCode::Char('o') => Some(Message::AddRow),
+            KeyCode::Char(':') => Some(Message::ModeRequest(Mode::Command)),
+            KeyCode::Char('/') => Some(Message::EditFilter),
             KeyCode::Char('h') => Some(Message::SelectLeft),
             KeyCode::Char('j') => Some(Message::SelectDown),
             KeyCode::Char('k') => Some(Message::SelectUp),
             KeyCode::Char('l') => Some(Message::SelectRight),
+            KeyCode::Char('y') => Some(Message::CopyPath),
+            KeyCode::Char('f') => Some(Message::CycleBodyFormat),
+            KeyCode::Char('r') => Some(Message::ToggleRawBody),
             KeyCode::Char('q') => Some(Message::Quit),
             KeyCode::Char('Q') => Some(Message::Quit),
+            KeyCode::Tab => Some(Message::NextTab),
+            KeyCode::BackTab => Some(Message::PreviousTab),
+            KeyCode::Enter => Some(Message::SendRequest),
             KeyCode::F(12) => Some(Message::ToggleDebug),
             _ => None,
         }
@@ -400,8 +1560,66 @@ fn handle_insert_key(key_event: KeyEvent) -> Option<Message> {
     if key_event.kind == event::KeyEventKind::Press {
         match key_event.code {
             KeyCode::Esc => Some(Message::ModeRequest(Mode::Normal)),
-            KeyCode::Char(char) => Some(Message::Input(char)),
+            KeyCode::Tab => Some(Message::NextField),
+            KeyCode::BackTab => Some(Message::PreviousField),
+            KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                Some(Message::CopySelection)
+            }
+            KeyCode::Char('x') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                Some(Message::CutSelection)
+            }
             KeyCode::F(12) => Some(Message::ToggleDebug),
+            _ => Some(Message::Edit(key_event)),
+        }
+    } else {
+        None
+    }
+}
+
+fn handle_modal_key(key_event: KeyEvent) -> Option<Message> {
+    if key_event.kind == event::KeyEventKind::Press {
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Char('q') => Some(Message::CloseModal),
+            KeyCode::Enter => Some(Message::Confirm),
+            KeyCode::Char(' ') => Some(Message::ToggleSelection),
+            KeyCode::Char('d') => Some(Message::DiffHistory),
+            KeyCode::Char('n') => Some(Message::NextHunk),
+            KeyCode::Char('N') => Some(Message::PreviousHunk),
+            KeyCode::Char('r') => Some(Message::ResendHistory),
+            KeyCode::Char('s') => Some(Message::ToggleStopOnFailure),
+            KeyCode::Char('+') | KeyCode::Char('=') => Some(Message::AdjustDelay(100)),
+            KeyCode::Char('-') => Some(Message::AdjustDelay(-100)),
+            KeyCode::Char('j') | KeyCode::Down => Some(Message::SelectDown),
+            KeyCode::Char('k') | KeyCode::Up => Some(Message::SelectUp),
+            KeyCode::F(12) => Some(Message::ToggleDebug),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+fn handle_filter_key(key_event: KeyEvent) -> Option<Message> {
+    if key_event.kind == event::KeyEventKind::Press {
+        match key_event.code {
+            KeyCode::Esc => Some(Message::ModeRequest(Mode::Normal)),
+            KeyCode::Enter => Some(Message::SubmitFilter),
+            KeyCode::Backspace => Some(Message::Backspace),
+            KeyCode::Char(char) => Some(Message::Input(char)),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+fn handle_command_key(key_event: KeyEvent) -> Option<Message> {
+    if key_event.kind == event::KeyEventKind::Press {
+        match key_event.code {
+            KeyCode::Esc => Some(Message::ModeRequest(Mode::Normal)),
+            KeyCode::Enter => Some(Message::SubmitCommand),
+            KeyCode::Backspace => Some(Message::Backspace),
+            KeyCode::Char(char) => Some(Message::Input(char)),
             _ => None,
         }
     } else {
@@ -417,3 +1635,39 @@ fn load_application(app: &mut App) -> Result<Option<Message>> {
         Ok(Some(Message::NewCollection))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use carrier_pigeon_core::Method;
+
+    #[test]
+    fn saving_removes_renamed_requests() {
+        let name: String = std::iter::repeat_with(fastrand::alphanumeric)
+            .take(16)
+            .collect();
+        let dir = env::temp_dir().join(format!("carrier-pigeon-test-{}", name));
+        let mut coll = Collection {
+            requests: vec![Request::builder()
+                .name("List pets".into())
+                .method(Method::Get)
+                .url("https://pets.test/pets".into())
+                .build()],
+            save_location: Some(dir.clone()),
+            ..Default::default()
+        };
+        save_collection(&coll, &dir).unwrap();
+        coll.requests[0].name = "All pets".into();
+        save_collection(&coll, &dir).unwrap();
+
+        let loaded = load_collection(dir.clone());
+        fs::remove_dir_all(&dir).unwrap();
+        let names: Vec<_> = loaded
+            .unwrap()
+            .requests
+            .into_iter()
+            .map(|req| req.name)
+            .collect();
+        assert_eq!(names, vec!["All pets"]);
+    }
+}
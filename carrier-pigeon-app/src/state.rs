@@ -1,19 +1,45 @@
 use crate::ui;
-use carrier_pigeon_core::Request;
+use crate::ui::json_view::JsonViewState;
+use crate::ui::text_edit::TextEditState;
 
 use carrier_pigeon_macros::{DisplayEnum, ListEnum, OrderedEnum};
-use ratatui::widgets::ListState;
+use ratatui::{
+    text::Line,
+    widgets::{ListState, TableState},
+};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     path::PathBuf,
-    sync::{Arc, Mutex},
+    sync::{atomic::AtomicBool, Arc, Mutex},
+};
+use time::OffsetDateTime;
+
+use carrier_pigeon_core::{
+    bench::{BenchOptions, BenchStats},
+    body::{self, BodyFormat},
+    diff::ResponseDiff,
+    executor::{HttpClient, Progress},
+    formats::har::Har,
+    runner::{RunOptions, RunResult, RunSummary},
+    Header,
+};
+pub use carrier_pigeon_core::{
+    Collection, Environment, EnvironmentValue, EnvironmentValues, Execution, Request,
 };
 
 #[derive(Debug, Default, Deserialize, Serialize, PartialEq)]
 pub enum Modal {
     LoadCollection,
     Environment,
+    HarImport,
+    Runner,
+    Bench,
+    History,
+    /// A response diff, opened from the history modal or from the main view.
+    Diff {
+        from_history: bool,
+    },
     #[default]
     None,
 }
@@ -30,6 +56,9 @@ pub enum Pane {
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
 pub struct RequestDetailsState {
     pub selected_tab: RequestTab,
+    /// Selected row of the headers and params tables.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub table_state: TableState,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, DisplayEnum, ListEnum, OrderedEnum)]
@@ -39,9 +68,19 @@ pub enum RequestTab {
     Headers,
     PathParams,
     QueryParams,
+    Script,
+    Raw,
 }
 
 impl RequestTab {
+    /// Whether the tab shows a table of names and values.
+    pub fn is_table(&self) -> bool {
+        matches!(
+            self,
+            RequestTab::Headers | RequestTab::PathParams | RequestTab::QueryParams
+        )
+    }
+
     pub fn prev_tab(self) -> Self {
         let idx: usize = usize::from(self);
         if idx == 0 {
@@ -59,6 +98,45 @@ impl RequestTab {
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
 pub struct ResponseDetailsState {
     pub selected_tab: ResponseTab,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub json: JsonViewState,
+    /// Format picked for the body with `f`, detected from the `Content-Type` when unset.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub body_format: Option<BodyFormat>,
+    /// The body as last drawn, so it is only decoded and highlighted again when it changes.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub body_view: Option<BodyView>,
+    /// Shows the body as received, without pretty printing.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub raw: bool,
+    /// First line of the hex dump shown for binary bodies.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub hex_scroll: usize,
+    /// Offset of the first line shown of a body spilled to disk.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub spill_offset: u64,
+}
+
+/// What a [`BodyView`] was made from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BodyKey {
+    /// When the execution the body belongs to started.
+    pub started: OffsetDateTime,
+    pub format: Option<BodyFormat>,
+    pub raw: bool,
+    /// Expression whose output is shown in place of the body.
+    pub filter: Option<String>,
+}
+
+/// A response body decoded and highlighted for display.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BodyView {
+    pub key: BodyKey,
+    /// The format picked with `f`, or else the detected one.
+    pub format: BodyFormat,
+    /// Whether the body is shown in the JSON viewer rather than as `lines`.
+    pub json: bool,
+    pub lines: Vec<Line<'static>>,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, DisplayEnum, ListEnum, OrderedEnum)]
@@ -66,6 +144,9 @@ pub enum ResponseTab {
     #[default]
     Body,
     Headers,
+    Tests,
+    Timing,
+    Raw,
 }
 
 impl ResponseTab {
@@ -92,6 +173,9 @@ pub struct WindowState {
     pub res_state: ResponseDetailsState,
     #[serde(skip_serializing, skip_deserializing)]
     pub select_list_state: ListState,
+    /// Selection in the history modal, which lists the newest execution first.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub history_list_state: ListState,
 }
 
 #[derive(Debug, Default, Deserialize, Serialize)]
@@ -99,6 +183,77 @@ pub enum Mode {
     #[default]
     Normal,
     Insert,
+    Command,
+    /// Editing the response filter, which is typed into `input_buf`.
+    Filter,
+}
+
+/// Column of a name and value table.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Column {
+    #[default]
+    Name,
+    Value,
+}
+
+/// A field of the selected request that can be edited in insert mode.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EditTarget {
+    Url,
+    Body,
+    /// A cell of the headers or params table on the tab, a row one past the last adds a row.
+    Cell(RequestTab, usize, Column),
+}
+
+/// The execution, by when it started, and the expression a filter run is for.
+pub type FilterKey = (OffsetDateTime, String);
+
+/// Output of the response filter, which runs on a thread of its own.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FilterOutput {
+    Running,
+    Done(Result<String, String>),
+}
+
+/// The latest response filter run, replaced once the filter or the shown execution changes.
+#[derive(Debug)]
+pub struct FilterRun {
+    pub key: FilterKey,
+    pub output: FilterOutput,
+    /// Set to stop the run once its output is no longer wanted.
+    pub cancel: Arc<AtomicBool>,
+}
+
+/// The field being edited and its text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Editor {
+    pub target: EditTarget,
+    pub text: TextEditState,
+    /// The field's text before editing, to tell whether it changed.
+    pub original: String,
+}
+
+/// Names and values of the table on `tab`, path and query params are sorted by name.
+pub fn table_rows(req: &Request, tab: &RequestTab) -> Vec<(String, String)> {
+    let params = |params: &Option<HashMap<String, String>>| {
+        let mut rows: Vec<_> = params
+            .iter()
+            .flatten()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        rows.sort();
+        rows
+    };
+    match tab {
+        RequestTab::Headers => req
+            .headers
+            .iter()
+            .map(|header| (header.name.to_string(), header.value.to_string()))
+            .collect(),
+        RequestTab::PathParams => params(&req.path_params),
+        RequestTab::QueryParams => params(&req.query_params),
+        _ => vec![],
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -106,105 +261,165 @@ pub enum Secret {
     RawValue,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub enum EnvironmentValue {
-    Secret(String),
-    Value(String),
+/// Entries of a HAR file waiting to be picked for import.
+pub struct HarPicker {
+    pub har: Har,
+    pub keep: Vec<bool>,
+    pub list_state: ListState,
 }
 
-pub type EnvironmentValues = HashMap<String, EnvironmentValue>;
+impl HarPicker {
+    pub fn new(har: Har) -> Self {
+        let keep = vec![true; har.log.entries.len()];
+        let mut list_state = ListState::default();
+        list_state.select_first();
+        HarPicker {
+            har,
+            keep,
+            list_state,
+        }
+    }
 
-#[derive(Debug, Default, Deserialize, Serialize)]
-pub struct Environment {
-    pub name: String,
-    pub values: EnvironmentValues,
+    pub fn toggle_selected(&mut self) {
+        if let Some(keep) = self
+            .list_state
+            .selected()
+            .and_then(|idx| self.keep.get_mut(idx))
+        {
+            *keep = !*keep;
+        }
+    }
 }
 
-#[derive(Debug, Default, Deserialize, Serialize)]
-pub struct Collection {
-    pub requests: Vec<Request>,
-    pub environments: Vec<Environment>,
-    #[serde(skip_serializing)]
-    pub save_location: Option<PathBuf>,
-}
-
-pub struct SerializedCollection {
-    pub requests: HashMap<Box<str>, Box<[u8]>>,
-    pub environments: HashMap<Box<str>, Box<[u8]>>,
-}
-
-impl Collection {
-    pub fn serialize(&self) -> SerializedCollection {
-        let requests: HashMap<Box<str>, Box<[u8]>> =
-            self.requests.iter().fold(HashMap::new(), |mut reqs, req| {
-                if let Ok(ser_req) = serde_json::to_vec(req) {
-                    reqs.insert(
-                        req.name.clone().into_boxed_str(),
-                        ser_req.into_boxed_slice(),
-                    );
-                }
-                reqs
-            });
-
-        let environments: HashMap<Box<str>, Box<[u8]>> =
-            self.environments
-                .iter()
-                .fold(HashMap::new(), |mut envs, env| {
-                    if let Ok(ser_env) = serde_json::to_vec(env) {
-                        envs.insert(
-                            env.name.clone().into_boxed_str(),
-                            ser_env.into_boxed_slice(),
-                        );
-                    }
-                    envs
-                });
+/// A load test started with `:bench` and its live statistics.
+pub struct Bench {
+    /// What is being sent, for display.
+    pub target: String,
+    /// Indices of the requests sent as a sequence.
+    pub selection: Vec<usize>,
+    pub options: BenchOptions,
+    pub stats: Option<BenchStats>,
+    /// Set to stop the workers early.
+    pub stop: Arc<AtomicBool>,
+}
+
+impl Bench {
+    pub fn running(&self) -> bool {
+        self.stats.as_ref().is_some_and(|stats| !stats.finished)
+    }
+}
+
+/// Selection, options and live results of the collection runner.
+pub struct Runner {
+    /// Whether each request in the collection is part of the run.
+    pub selected: Vec<bool>,
+    pub options: RunOptions,
+    pub results: Vec<RunResult>,
+    pub summary: Option<RunSummary>,
+    pub running: bool,
+    pub list_state: ListState,
+}
 
-        SerializedCollection {
-            requests,
-            environments,
+impl Runner {
+    pub fn new(request_count: usize) -> Self {
+        let mut list_state = ListState::default();
+        list_state.select_first();
+        Runner {
+            selected: vec![true; request_count],
+            options: RunOptions::default(),
+            results: vec![],
+            summary: None,
+            running: false,
+            list_state,
         }
     }
 
-    pub fn deserialize(save_location: PathBuf, ser_coll: SerializedCollection) -> Self {
-        let requests: Vec<Request> = ser_coll
-            .requests
-            .keys()
-            .filter_map(|key| {
-                serde_json::from_slice(
-                    ser_coll
-                        .requests
-                        .get(key)
-                        .expect("Failed to retrieve from map inside key iterator"),
-                )
-                .ok()
-            })
-            .collect();
-        let environments: Vec<Environment> = ser_coll
-            .environments
-            .keys()
-            .filter_map(|key| {
-                if let Ok(values) = serde_json::from_slice(
-                    ser_coll
-                        .environments
-                        .get(key)
-                        .expect("Failed to retrieve from map inside key iterator"),
-                ) {
-                    Some(Environment {
-                        name: key.to_string(),
-                        values,
-                    })
-                } else {
-                    None
-                }
+    pub fn toggle_selected(&mut self) {
+        if let Some(selected) = self
+            .list_state
+            .selected()
+            .and_then(|idx| self.selected.get_mut(idx))
+        {
+            *selected = !*selected;
+        }
+    }
+
+    /// Indices of the selected requests, in collection order.
+    pub fn selection(&self) -> Vec<usize> {
+        self.selected
+            .iter()
+            .enumerate()
+            .filter(|(_, selected)| **selected)
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    pub fn adjust_delay(&mut self, millis: i64) {
+        let delay = self.options.delay.as_millis() as i64 + millis;
+        self.options.delay = std::time::Duration::from_millis(delay.max(0) as u64);
+    }
+
+    /// The latest result of the request at `index`.
+    pub fn result(&self, index: usize) -> Option<&RunResult> {
+        self.results
+            .iter()
+            .rev()
+            .find(|result| result.index == index)
+    }
+
+    /// How many iterations of the request at `index` passed, and how many ran.
+    pub fn iterations_passed(&self, index: usize) -> (usize, usize) {
+        self.results
+            .iter()
+            .filter(|result| result.index == index)
+            .fold((0, 0), |(passed, ran), result| {
+                (passed + usize::from(result.passed()), ran + 1)
             })
-            .collect();
+    }
+}
+
+/// Two responses being compared, scrolled by body line.
+pub struct DiffView {
+    pub title: String,
+    pub diff: ResponseDiff,
+    pub scroll: usize,
+}
 
-        Collection {
-            requests,
-            environments,
-            save_location: Some(save_location),
+impl DiffView {
+    pub fn scroll_by(&mut self, lines: isize) {
+        let last = self.diff.body.len().saturating_sub(1);
+        self.scroll = self.scroll.saturating_add_signed(lines).min(last);
+    }
+
+    pub fn next_hunk(&mut self) {
+        if let Some(hunk) = self
+            .diff
+            .hunks()
+            .into_iter()
+            .find(|hunk| *hunk > self.scroll)
+        {
+            self.scroll = hunk;
+        }
+    }
+
+    pub fn previous_hunk(&mut self) {
+        if let Some(hunk) = self
+            .diff
+            .hunks()
+            .into_iter()
+            .rev()
+            .find(|hunk| *hunk < self.scroll)
+        {
+            self.scroll = hunk;
         }
     }
+
+    /// The hunk at or above the scroll position, 1-based, and the number of hunks.
+    pub fn hunk_position(&self) -> (usize, usize) {
+        let hunks = self.diff.hunks();
+        let current = hunks.iter().filter(|hunk| **hunk <= self.scroll).count();
+        (current, hunks.len())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -224,7 +439,7 @@ pub struct AppBuilder<L, G, W> {
 }
 
 impl<'a, L, G, W> AppBuilder<L, G, W> {
-    pub fn logs(self, logs: Arc<Mutex<ui::logging::RecordBuff<'a>>>) -> AppBuilder<Logs, G, W> {
+    pub fn logs(self, logs: Arc<Mutex<ui::logging::RecordBuff<'a>>>) -> AppBuilder<Logs<'a>, G, W> {
         AppBuilder::<Logs, G, W> {
             logs: Logs(logs),
             global_state: self.global_state,
@@ -255,10 +470,24 @@ impl<'a> AppBuilder<Logs<'a>, State, WorkDir> {
             window_state: WindowState::default(),
             mode: Mode::default(),
             collection: None,
+            scratch: None,
             running: true,
             work_dir: self.work_dir.0,
             global: self.global_state.0,
             input_buf: String::default(),
+            editor: None,
+            dirty: false,
+            filter: None,
+            client: HttpClient::new(crate::APP_USER_AGENT).expect("Failed to build HTTP client"),
+            active_env: None,
+            history: vec![],
+            history_mark: None,
+            diff: None,
+            har_picker: None,
+            runner: None,
+            bench: None,
+            download: None,
+            status: None,
             debug_logs: self.logs.0,
             show_debug: false,
         }
@@ -269,10 +498,31 @@ pub struct App<'a> {
     pub window_state: WindowState,
     pub mode: Mode,
     pub collection: Option<Collection>,
+    /// Request opened from history, shown in place of the selected one without being added to
+    /// the collection. Selecting a request in the list closes it.
+    pub scratch: Option<Request>,
     pub running: bool,
     pub work_dir: PathBuf,
     pub global: GlobalState,
     pub input_buf: String,
+    /// The request field being edited in insert mode.
+    pub editor: Option<Editor>,
+    /// Whether the collection was edited since it was last saved.
+    pub dirty: bool,
+    pub filter: Option<FilterRun>,
+    pub client: HttpClient,
+    pub active_env: Option<String>,
+    pub history: Vec<Execution>,
+    /// History entry marked in the history modal as the base of a diff.
+    pub history_mark: Option<Execution>,
+    pub diff: Option<DiffView>,
+    pub har_picker: Option<HarPicker>,
+    pub runner: Option<Runner>,
+    pub bench: Option<Bench>,
+    /// How much of the response body being received has arrived.
+    pub download: Option<Progress>,
+    /// Last error worth showing outside of the debug log, e.g. a failing pre-request script.
+    pub status: Option<String>,
     // Debugging
     pub debug_logs: Arc<Mutex<ui::logging::RecordBuff<'a>>>,
     pub show_debug: bool,
@@ -286,6 +536,320 @@ impl<'a> App<'a> {
             work_dir: AbsentValue,
         }
     }
+
+    pub fn selected_request(&self) -> Option<&Request> {
+        if let Some(scratch) = &self.scratch {
+            return Some(scratch);
+        }
+        self.collection.as_ref().and_then(|coll| {
+            coll.requests
+                .get(self.window_state.select_list_state.selected().unwrap_or(0))
+        })
+    }
+
+    /// List state of the open modal, when it shows a list.
+    pub fn modal_list_state(&mut self) -> Option<&mut ListState> {
+        match self.window_state.modal {
+            Modal::HarImport => self
+                .har_picker
+                .as_mut()
+                .map(|picker| &mut picker.list_state),
+            Modal::Runner => self.runner.as_mut().map(|runner| &mut runner.list_state),
+            Modal::History => Some(&mut self.window_state.history_list_state),
+            _ => None,
+        }
+    }
+
+    /// Whether keys go to the JSON viewer of the response body.
+    pub fn json_focused(&self) -> bool {
+        let state = &self.window_state;
+        state.focused_pane == Pane::Response
+            && state.res_state.selected_tab == ResponseTab::Body
+            && state.res_state.json.is_json()
+    }
+
+    /// The format the response body of the selected request is shown in.
+    pub fn body_format(&self) -> Option<BodyFormat> {
+        let res = &self.history[self.selected_execution()?].response;
+        Some(
+            self.window_state
+                .res_state
+                .body_format
+                .unwrap_or_else(|| BodyFormat::detect(res.header("content-type"), &res.body)),
+        )
+    }
+
+    /// Whether the response body of the selected request is shown as a hex dump, which it is
+    /// when binary unless another format was picked.
+    pub fn hex_focused(&self) -> bool {
+        let state = &self.window_state;
+        let Some(idx) = self.selected_execution() else {
+            return false;
+        };
+        let res = &self.history[idx].response;
+        state.focused_pane == Pane::Response
+            && state.res_state.selected_tab == ResponseTab::Body
+            && state.res_state.body_format.is_none()
+            && body::is_binary(res.header("content-type"), &res.body)
+    }
+
+    /// Scrolls the hex dump of the selected response body.
+    pub fn scroll_hex(&mut self, lines: isize) {
+        let Some(idx) = self.selected_execution() else {
+            return;
+        };
+        let len = self.history[idx].response.body_len() as usize;
+        let last = body::hex_lines(len).saturating_sub(1);
+        let scroll = &mut self.window_state.res_state.hex_scroll;
+        *scroll = scroll.saturating_add_signed(lines).min(last);
+    }
+
+    /// Whether the response body of the selected request was spilled to disk and is paged
+    /// through as text.
+    pub fn spill_focused(&self) -> bool {
+        let state = &self.window_state;
+        state.focused_pane == Pane::Response
+            && state.res_state.selected_tab == ResponseTab::Body
+            && !self.hex_focused()
+            && self
+                .selected_execution()
+                .is_some_and(|idx| self.history[idx].response.paged_body().is_some())
+    }
+
+    /// Moves the spilled body text of the selected response by a line.
+    pub fn scroll_spill(&mut self, lines: isize) -> std::io::Result<()> {
+        let Some(spilled) = self
+            .selected_execution()
+            .and_then(|idx| self.history[idx].response.paged_body())
+        else {
+            return Ok(());
+        };
+        let offset = self.window_state.res_state.spill_offset;
+        self.window_state.res_state.spill_offset = if lines < 0 {
+            spilled.previous_line(offset)?
+        } else {
+            spilled.next_line(offset)?
+        };
+        Ok(())
+    }
+
+    /// The execution selected in the history modal.
+    pub fn selected_history(&self) -> Option<&Execution> {
+        let selected = self.window_state.history_list_state.selected()?;
+        self.history.iter().rev().nth(selected)
+    }
+
+    /// Index into `history` of the most recent execution of the selected request. Requests are
+    /// told apart by folder and name, as names only need to be unique within a folder.
+    pub fn selected_execution(&self) -> Option<usize> {
+        let req = self.selected_request()?;
+        self.history.iter().rposition(|execution| {
+            execution.request.folder == req.folder && execution.request.name == req.name
+        })
+    }
+
+    /// The response filter being typed, or else the selected request's.
+    pub fn current_filter(&self) -> Option<&str> {
+        match self.mode {
+            Mode::Filter => Some(self.input_buf.as_str()),
+            _ => self
+                .selected_request()
+                .and_then(|req| req.response_filter.as_deref()),
+        }
+        .filter(|filter| !filter.trim().is_empty())
+    }
+
+    /// What the response filter should run on now, `None` when there is nothing to filter.
+    /// Bodies spilled to disk are only shown a page at a time, so they aren't filtered.
+    pub fn filter_key(&self) -> Option<FilterKey> {
+        let filter = self.current_filter()?;
+        let execution = &self.history[self.selected_execution()?];
+        if execution.response.spilled.is_some() {
+            return None;
+        }
+        Some((execution.started, filter.to_string()))
+    }
+
+    pub fn selected_request_mut(&mut self) -> Option<&mut Request> {
+        if let Some(scratch) = &mut self.scratch {
+            return Some(scratch);
+        }
+        let idx = self.window_state.select_list_state.selected().unwrap_or(0);
+        self.collection
+            .as_mut()
+            .and_then(|coll| coll.requests.get_mut(idx))
+    }
+
+    /// Moves the row selection of the table on the selected request tab.
+    pub fn select_row(&mut self, rows: isize) {
+        let tab = &self.window_state.req_state.selected_tab;
+        let Some(len) = self
+            .selected_request()
+            .map(|req| table_rows(req, tab).len())
+        else {
+            return;
+        };
+        let table_state = &mut self.window_state.req_state.table_state;
+        let row = table_state
+            .selected()
+            .unwrap_or(0)
+            .saturating_add_signed(rows);
+        table_state.select(Some(row.min(len.saturating_sub(1))));
+    }
+
+    /// Starts editing `target` of the selected request in insert mode, returns whether it exists.
+    pub fn start_edit(&mut self, target: EditTarget) -> bool {
+        let Some(req) = self.selected_request() else {
+            return false;
+        };
+        let (text, multiline) = match &target {
+            EditTarget::Url => (req.url.clone(), false),
+            EditTarget::Body => (req.body.clone().unwrap_or_default(), true),
+            EditTarget::Cell(tab, row, column) => {
+                let rows = table_rows(req, tab);
+                match (rows.get(*row), column) {
+                    (Some((name, _)), Column::Name) => (name.clone(), false),
+                    (Some((_, value)), Column::Value) => (value.clone(), false),
+                    (None, _) if *row == rows.len() => (String::new(), false),
+                    (None, _) => return false,
+                }
+            }
+        };
+        if let EditTarget::Cell(_, row, _) = &target {
+            self.window_state.req_state.table_state.select(Some(*row));
+        }
+        self.editor = Some(Editor {
+            target,
+            text: if multiline {
+                TextEditState::multi_line(&text)
+            } else {
+                TextEditState::single_line(&text)
+            },
+            original: text,
+        });
+        self.mode = Mode::Insert;
+        true
+    }
+
+    /// Moves editing to the next, or previous, cell of the table, the cell after the last row
+    /// starts a new one. Forward indents the body instead, and the URL stays as it is.
+    pub fn edit_next_field(&mut self, forward: bool) {
+        match &mut self.editor {
+            Some(Editor {
+                target: EditTarget::Body,
+                text,
+                ..
+            }) if forward => {
+                text.insert_str("  ");
+                return;
+            }
+            Some(Editor {
+                target: EditTarget::Cell(..),
+                ..
+            }) => {}
+            _ => return,
+        }
+        let next = match (self.commit_edit(), forward) {
+            (Some(EditTarget::Cell(tab, row, Column::Name)), true) => {
+                EditTarget::Cell(tab, row, Column::Value)
+            }
+            (Some(EditTarget::Cell(tab, row, Column::Value)), true) => {
+                EditTarget::Cell(tab, row + 1, Column::Name)
+            }
+            (Some(EditTarget::Cell(tab, row, Column::Value)), false) => {
+                EditTarget::Cell(tab, row, Column::Name)
+            }
+            (Some(EditTarget::Cell(tab, row, Column::Name)), false) if row > 0 => {
+                EditTarget::Cell(tab, row - 1, Column::Value)
+            }
+            (Some(target), _) => target,
+            // An emptied row was removed, so there is nothing left to move from.
+            (None, _) => {
+                self.mode = Mode::Normal;
+                return;
+            }
+        };
+        self.start_edit(next);
+    }
+
+    /// Writes the edited text back into the selected request, marking the collection dirty when
+    /// it changed and the request belongs to it. Returns the target with the row it ended up in,
+    /// since renaming a param can move it, or `None` when editing left the row empty and it was
+    /// removed. Renaming a param to the name of another is refused in the status line.
+    pub fn commit_edit(&mut self) -> Option<EditTarget> {
+        let editor = self.editor.take()?;
+        let text = editor.text.text();
+        // Params are keyed by name, so taking another row's name would drop that row.
+        if let EditTarget::Cell(tab, row, Column::Name) = &editor.target {
+            let rows = table_rows(self.selected_request()?, tab);
+            let renamed = rows.get(*row).is_none_or(|(name, _)| *name != text);
+            if *tab != RequestTab::Headers && renamed && rows.iter().any(|(name, _)| *name == text)
+            {
+                self.status = Some(format!("There already is a param named {}", text));
+                return Some(editor.target);
+            }
+        }
+        if text != editor.original && self.scratch.is_none() {
+            self.dirty = true;
+        }
+        let req = self.selected_request_mut()?;
+        match editor.target {
+            EditTarget::Url => {
+                req.url = text;
+                Some(EditTarget::Url)
+            }
+            EditTarget::Body => {
+                req.body = Some(text).filter(|body| !body.is_empty());
+                Some(EditTarget::Body)
+            }
+            EditTarget::Cell(RequestTab::Headers, row, column) => {
+                if row == req.headers.len() {
+                    if text.is_empty() {
+                        return None;
+                    }
+                    req.headers.push(Header {
+                        name: "".into(),
+                        value: "".into(),
+                    });
+                }
+                let header = &mut req.headers[row];
+                match column {
+                    Column::Name => header.name = text.into(),
+                    Column::Value => header.value = text.into(),
+                }
+                if header.name.is_empty() && header.value.is_empty() {
+                    req.headers.remove(row);
+                    return None;
+                }
+                Some(EditTarget::Cell(RequestTab::Headers, row, column))
+            }
+            EditTarget::Cell(tab, row, column) => {
+                let old = table_rows(req, &tab).into_iter().nth(row);
+                let (name, value) = old.clone().unwrap_or_default();
+                let (name, value) = match column {
+                    Column::Name => (text, value),
+                    Column::Value => (name, text),
+                };
+                let params = match tab {
+                    RequestTab::PathParams => &mut req.path_params,
+                    _ => &mut req.query_params,
+                };
+                let map = params.get_or_insert_with(HashMap::new);
+                if let Some((old, _)) = old {
+                    map.remove(&old);
+                }
+                if name.is_empty() && value.is_empty() {
+                    if map.is_empty() {
+                        *params = None;
+                    }
+                    return None;
+                }
+                let row = map.keys().filter(|key| **key < name).count();
+                map.insert(name, value);
+                Some(EditTarget::Cell(tab, row, column))
+            }
+        }
+    }
 }
 
 impl<'a> std::fmt::Debug for App<'a> {
@@ -294,7 +858,193 @@ impl<'a> std::fmt::Debug for App<'a> {
             .field("window_state", &format_args!("{:?}", self.window_state))
             .field("mode", &format_args!("{:?}", self.mode))
             .field("collection", &format_args!("{:?}", self.collection))
+            .field("active_env", &self.active_env)
             .field("running", &self.running)
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use carrier_pigeon_core::Method;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use simplelog::LevelFilter;
+
+    fn app(req: Request) -> App<'static> {
+        let (_, logs) = ui::logging::UILogger::new(LevelFilter::Off, Default::default());
+        let mut app = App::builder()
+            .logs(logs)
+            .global_state(GlobalState {
+                secrets: HashMap::new(),
+            })
+            .work_dir(PathBuf::from(".pigeon"))
+            .build();
+        app.collection = Some(Collection {
+            requests: vec![req],
+            ..Default::default()
+        });
+        app
+    }
+
+    fn type_str(app: &mut App, text: &str) {
+        let editor = app.editor.as_mut().unwrap();
+        editor.text.select_all();
+        editor.text.delete_selection();
+        text.chars().for_each(|char| {
+            editor
+                .text
+                .input(KeyEvent::new(KeyCode::Char(char), KeyModifiers::NONE));
+        });
+    }
+
+    #[test]
+    fn edits_headers_row_by_row() {
+        let mut app = app(Request::builder()
+            .name("Pets".into())
+            .method(Method::Get)
+            .url("https://pets.test".into())
+            .build());
+
+        assert!(app.start_edit(EditTarget::Cell(RequestTab::Headers, 0, Column::Name)));
+        type_str(&mut app, "Accept");
+        app.edit_next_field(true);
+        type_str(&mut app, "application/json");
+        app.edit_next_field(true);
+        assert_eq!(
+            app.editor.as_ref().unwrap().target,
+            EditTarget::Cell(RequestTab::Headers, 1, Column::Name)
+        );
+        // Leaving the new row empty drops it.
+        app.edit_next_field(true);
+
+        assert!(app.editor.is_none());
+        assert!(app.dirty);
+        assert_eq!(
+            table_rows(app.selected_request().unwrap(), &RequestTab::Headers),
+            vec![("Accept".into(), "application/json".into())]
+        );
+    }
+
+    #[test]
+    fn renames_and_removes_params() {
+        let mut app = app(Request::builder()
+            .name("Pets".into())
+            .method(Method::Get)
+            .url("https://pets.test".into())
+            .query_param("limit".into(), "10".into())
+            .query_param("sort".into(), "name".into())
+            .build());
+
+        app.start_edit(EditTarget::Cell(RequestTab::QueryParams, 1, Column::Name));
+        type_str(&mut app, "order");
+        assert_eq!(
+            app.commit_edit(),
+            Some(EditTarget::Cell(RequestTab::QueryParams, 1, Column::Name))
+        );
+        app.start_edit(EditTarget::Cell(RequestTab::QueryParams, 0, Column::Value));
+        type_str(&mut app, "");
+        app.commit_edit();
+        app.start_edit(EditTarget::Cell(RequestTab::QueryParams, 0, Column::Name));
+        type_str(&mut app, "");
+
+        assert_eq!(app.commit_edit(), None);
+        assert_eq!(
+            table_rows(app.selected_request().unwrap(), &RequestTab::QueryParams),
+            vec![("order".into(), "name".into())]
+        );
+    }
+
+    #[test]
+    fn edits_scratch_request_outside_collection() {
+        let mut app = app(Request::builder()
+            .name("Pets".into())
+            .method(Method::Get)
+            .url("https://pets.test".into())
+            .build());
+        app.scratch = Some(
+            Request::builder()
+                .name("Pets @ 12:00:00".into())
+                .method(Method::Get)
+                .url("https://pets.test?key=s3cr3t".into())
+                .build(),
+        );
+
+        app.start_edit(EditTarget::Url);
+        type_str(&mut app, "https://pets.test/1?key=s3cr3t");
+        app.commit_edit();
+
+        assert!(!app.dirty);
+        assert_eq!(
+            app.selected_request().unwrap().url,
+            "https://pets.test/1?key=s3cr3t"
+        );
+        assert_eq!(
+            app.collection.as_ref().unwrap().requests[0].url,
+            "https://pets.test"
+        );
+    }
+
+    #[test]
+    fn refuses_renaming_param_to_existing_name() {
+        let mut app = app(Request::builder()
+            .name("Pets".into())
+            .method(Method::Get)
+            .url("https://pets.test".into())
+            .query_param("limit".into(), "10".into())
+            .query_param("sort".into(), "name".into())
+            .build());
+
+        app.start_edit(EditTarget::Cell(RequestTab::QueryParams, 1, Column::Name));
+        type_str(&mut app, "limit");
+
+        assert_eq!(
+            app.commit_edit(),
+            Some(EditTarget::Cell(RequestTab::QueryParams, 1, Column::Name))
+        );
+        assert!(!app.dirty);
+        assert_eq!(
+            app.status.as_deref(),
+            Some("There already is a param named limit")
+        );
+        assert_eq!(
+            table_rows(app.selected_request().unwrap(), &RequestTab::QueryParams),
+            vec![
+                ("limit".into(), "10".into()),
+                ("sort".into(), "name".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn selects_executions_by_folder_and_name() {
+        let request = |folder: &str| {
+            let mut req = Request::builder()
+                .name("List".into())
+                .method(Method::Get)
+                .url(format!("https://pets.test/{}", folder))
+                .build();
+            req.folder = Some(folder.into());
+            req
+        };
+        let mut app = app(request("pets"));
+        app.collection
+            .as_mut()
+            .unwrap()
+            .requests
+            .push(request("owners"));
+        app.history.push(Execution {
+            request: request("owners"),
+            response: Default::default(),
+            started: time::OffsetDateTime::UNIX_EPOCH,
+            tests: vec![],
+            environment: None,
+            sent: None,
+        });
+
+        app.window_state.select_list_state.select(Some(0));
+        assert_eq!(app.selected_execution(), None);
+        app.window_state.select_list_state.select(Some(1));
+        assert_eq!(app.selected_execution(), Some(0));
+    }
+}
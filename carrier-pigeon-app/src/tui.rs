@@ -1,18 +1,29 @@
 use std::io::{self, stdout, Stdout};
 
-use crossterm::{execute, terminal::*};
+use crossterm::{
+    event::{DisableBracketedPaste, EnableBracketedPaste},
+    execute,
+    terminal::*,
+};
 use ratatui::prelude::*;
 
 pub type Tui = Terminal<CrosstermBackend<Stdout>>;
 
 pub fn init() -> io::Result<Tui> {
-    execute!(stdout(), EnterAlternateScreen)?;
+    execute!(stdout(), EnterAlternateScreen, EnableBracketedPaste)?;
     let _ = enable_raw_mode();
     Terminal::new(CrosstermBackend::new(stdout()))
 }
 
+/// Takes the terminal back after [`restore`], e.g. once an external editor exits.
+pub fn resume(tui: &mut Tui) -> io::Result<()> {
+    execute!(stdout(), EnterAlternateScreen, EnableBracketedPaste)?;
+    let _ = enable_raw_mode();
+    tui.clear()
+}
+
 pub fn restore() -> io::Result<()> {
-    execute!(stdout(), LeaveAlternateScreen)?;
+    execute!(stdout(), DisableBracketedPaste, LeaveAlternateScreen)?;
     let _ = disable_raw_mode();
     Ok(())
 }
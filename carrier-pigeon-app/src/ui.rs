@@ -1,16 +1,26 @@
-use ratatui::style::Color;
+use carrier_pigeon_core::{
+    bench::{BenchStats, Limit},
+    diff::Change,
+    runner::Outcome,
+};
+use ratatui::style::{Color, Modifier, Style, Stylize};
 use ratatui::{
-    layout::Alignment,
+    layout::{Alignment, Constraint, Layout, Margin},
     terminal::Frame,
-    text::Line,
+    text::{Line, Span},
     widgets::{
         block::{Position, Title},
-        Clear, Paragraph, Wrap,
+        Block, Clear, List, ListState, Paragraph, Sparkline, Wrap,
     },
 };
+use std::time::Duration;
+use text_edit::TextEdit;
+use time::macros::format_description;
 use widgets::{RequestDetails, RequestSelect, ResponseDetails};
 
+pub mod json_view;
 pub mod logging;
+pub mod text_edit;
 
 mod layout;
 mod util;
@@ -25,6 +35,14 @@ pub fn draw(app: &mut App, frame: &mut Frame) {
 
     let url_bar = UrlBar::construct(app);
     frame.render_widget(url_bar, layout.url_area);
+    if let Some(Editor {
+        target: EditTarget::Url,
+        text,
+        ..
+    }) = &mut app.editor
+    {
+        frame.render_stateful_widget(TextEdit, layout.url_area.inner(Margin::new(1, 1)), text);
+    }
 
     let req_list =
         RequestSelect::default().requests(app.collection.as_ref().map(|coll| &coll.requests));
@@ -41,11 +59,13 @@ pub fn draw(app: &mut App, frame: &mut Frame) {
 
     let req_details = if let Some(coll) = &app.collection {
         RequestDetails::default()
-            .request(
+            .request(app.scratch.as_ref().or_else(|| {
                 coll.requests
-                    .get(app.window_state.select_list_state.selected().unwrap_or(0)),
-            )
+                    .get(app.window_state.select_list_state.selected().unwrap_or(0))
+            }))
+            .execution(app.selected_execution().map(|idx| &app.history[idx]))
             .focused(Pane::Request == app.window_state.focused_pane)
+            .editor(app.editor.as_mut())
     } else {
         RequestDetails::default()
     };
@@ -55,14 +75,46 @@ pub fn draw(app: &mut App, frame: &mut Frame) {
         &mut app.window_state.req_state,
     );
 
-    let res_details =
-        ResponseDetails::default().focused(Pane::Response == app.window_state.focused_pane);
+    let filter = match app.mode {
+        Mode::Filter => Some(app.input_buf.clone()),
+        _ => app.current_filter().map(str::to_string),
+    };
+    let filter_key = app.filter_key();
+    let res_details = ResponseDetails::default()
+        .execution(app.selected_execution().map(|idx| &app.history[idx]))
+        .download(app.download)
+        .filter(filter.as_deref(), matches!(app.mode, Mode::Filter))
+        .filtered(
+            app.filter
+                .as_ref()
+                .filter(|run| Some(&run.key) == filter_key.as_ref())
+                .map(|run| &run.output),
+        )
+        .focused(Pane::Response == app.window_state.focused_pane);
     frame.render_stateful_widget(
         res_details,
         layout.res_area,
         &mut app.window_state.res_state,
     );
 
+    if let Mode::Command = app.mode {
+        let command_line = Paragraph::new(format!(":{}", app.input_buf));
+        frame.render_widget(command_line, layout.help_area);
+    } else if let Mode::Insert = app.mode {
+        let insert_line = Paragraph::new("-- INSERT --  (tab) next field / (esc) done").dark_gray();
+        frame.render_widget(insert_line, layout.help_area);
+    } else if let Some(status) = &app.status {
+        let status_line = Paragraph::new(status.as_str()).style(Style::new().fg(Color::Red));
+        frame.render_widget(status_line, layout.help_area);
+    }
+
+    if app.dirty {
+        let dirty = Line::from("[+] unsaved changes, :w to save")
+            .yellow()
+            .right_aligned();
+        frame.render_widget(dirty, layout.help_area);
+    }
+
     match &app.window_state.modal {
         Modal::None => {}
         Modal::LoadCollection => {
@@ -78,6 +130,53 @@ pub fn draw(app: &mut App, frame: &mut Frame) {
             frame.render_widget(modal, modal_area);
         }
         Modal::Environment => todo!(),
+        Modal::HarImport => {
+            if let Some(picker) = &mut app.har_picker {
+                let modal = title_block(" Import HAR ".into(), Color::White).title(
+                    Title::from(" (space) toggle / (enter) import / (esc) cancel ")
+                        .position(Position::Bottom)
+                        .alignment(Alignment::Center),
+                );
+                let entries = picker
+                    .har
+                    .log
+                    .entries
+                    .iter()
+                    .zip(&picker.keep)
+                    .map(|(entry, keep)| {
+                        format!("[{}] {}", if *keep { 'x' } else { ' ' }, entry.summary())
+                    })
+                    .collect::<Vec<_>>();
+                let list = List::new(entries)
+                    .block(modal)
+                    .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+                let modal_area = modal_layout(75, 50, frame.size());
+
+                frame.render_widget(Clear, modal_area);
+                frame.render_stateful_widget(list, modal_area, &mut picker.list_state);
+            }
+        }
+        Modal::Runner => {
+            if let (Some(runner), Some(coll)) = (&mut app.runner, &app.collection) {
+                draw_runner(frame, runner, &coll.requests);
+            }
+        }
+        Modal::Bench => {
+            if let Some(bench) = &app.bench {
+                draw_bench(frame, bench);
+            }
+        }
+        Modal::History => draw_history(
+            frame,
+            &app.history,
+            app.history_mark.as_ref(),
+            &mut app.window_state.history_list_state,
+        ),
+        Modal::Diff { .. } => {
+            if let Some(view) = &app.diff {
+                draw_diff(frame, view);
+            }
+        }
     }
 
     if app.show_debug {
@@ -96,11 +195,7 @@ pub fn draw(app: &mut App, frame: &mut Frame) {
             .alignment(Alignment::Left);
 
         let line_count = log_paragraph.line_count(area.width) as u16;
-        let scroll_offset = if line_count > area.height {
-            line_count - area.height
-        } else {
-            0
-        };
+        let scroll_offset = line_count.saturating_sub(area.height);
 
         let log_paragraph = log_paragraph.scroll((scroll_offset, 0));
 
@@ -108,3 +203,316 @@ pub fn draw(app: &mut App, frame: &mut Frame) {
         frame.render_widget(log_paragraph, area);
     }
 }
+
+fn draw_runner(frame: &mut Frame, runner: &mut Runner, requests: &[Request]) {
+    let modal = title_block(" Collection Runner ".into(), Color::White).title(
+        Title::from(format!(
+            " (space) toggle / (s) stop on failure: {} / (+/-) delay: {}ms / (enter) run ",
+            if runner.options.stop_on_failure {
+                "on"
+            } else {
+                "off"
+            },
+            runner.options.delay.as_millis()
+        ))
+        .position(Position::Bottom)
+        .alignment(Alignment::Center),
+    );
+    let modal_area = modal_layout(75, 50, frame.size());
+    let [list_area, summary_area] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)])
+        .areas(modal.inner(modal_area));
+
+    let entries = requests
+        .iter()
+        .zip(&runner.selected)
+        .enumerate()
+        .map(|(idx, (req, selected))| {
+            let mut line = vec![Span::raw(format!(
+                "[{}] {:7} {}  ",
+                if *selected { 'x' } else { ' ' },
+                req.method.to_string(),
+                req.name
+            ))];
+            if let Some(result) = runner.result(idx) {
+                let (passed, ran) = runner.iterations_passed(idx);
+                let style = if passed == ran {
+                    Style::new().green()
+                } else {
+                    Style::new().red()
+                };
+                let mut detail = match &result.outcome {
+                    Outcome::Completed(execution) => format!(
+                        "{} {}ms  tests {}/{}",
+                        execution.response.status,
+                        execution.response.duration.as_millis(),
+                        execution.tests.iter().filter(|test| test.passed).count(),
+                        execution.tests.len()
+                    ),
+                    Outcome::Failed(err) => err.clone(),
+                };
+                if runner.options.data.len() > 1 {
+                    detail.push_str(&format!("  iterations {}/{}", passed, ran));
+                }
+                line.push(Span::styled(
+                    format!("{} {}", if passed == ran { '✓' } else { '✗' }, detail),
+                    style,
+                ));
+            }
+            Line::from(line)
+        })
+        .collect::<Vec<_>>();
+    let list = List::new(entries).highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+
+    let summary = match &runner.summary {
+        _ if runner.running => format!(
+            "Running {}/{}",
+            runner.results.len(),
+            runner.selection().len()
+        ),
+        Some(summary) => format!(
+            "{} passed, {} failed of {}{}  tests {}/{}  {}ms{}",
+            summary.passed,
+            summary.failed,
+            summary.total,
+            if summary.iterations > 1 {
+                format!(" over {} iterations", summary.iterations)
+            } else {
+                String::new()
+            },
+            summary.tests_passed,
+            summary.tests_passed + summary.tests_failed,
+            summary.duration.as_millis(),
+            if summary.stopped {
+                "  (stopped on failure)"
+            } else {
+                ""
+            }
+        ),
+        None if !runner.options.data.is_empty() => format!(
+            "Not run yet, {} iterations of data loaded",
+            runner.options.data.len()
+        ),
+        None => String::from("Not run yet"),
+    };
+
+    frame.render_widget(Clear, modal_area);
+    frame.render_widget(modal, modal_area);
+    frame.render_stateful_widget(list, list_area, &mut runner.list_state);
+    frame.render_widget(Paragraph::new(summary).bold(), summary_area);
+}
+
+fn draw_bench(frame: &mut Frame, bench: &Bench) {
+    let modal = title_block(" Benchmark ".into(), Color::White).title(
+        Title::from(if bench.running() {
+            " (esc) stop "
+        } else {
+            " (enter) start / (esc) close "
+        })
+        .position(Position::Bottom)
+        .alignment(Alignment::Center),
+    );
+    let modal_area = modal_layout(75, 50, frame.size());
+    let [stats_area, sparkline_area] =
+        Layout::vertical([Constraint::Min(1), Constraint::Length(5)])
+            .areas(modal.inner(modal_area));
+
+    let mut lines = vec![Line::from(format!(
+        "{}  concurrency {}  rate {}  limit {}",
+        bench.target,
+        bench.options.concurrency,
+        bench
+            .options
+            .rate
+            .map(|rate| format!("{}/s", rate))
+            .unwrap_or_else(|| String::from("unlimited")),
+        match bench.options.limit {
+            Limit::Count(count) => format!("{} requests", count),
+            Limit::Duration(duration) => format!("{}s", duration.as_secs()),
+        }
+    ))];
+    match &bench.stats {
+        Some(stats) => lines.extend(bench_lines(stats)),
+        None => lines.push(Line::from("Not started yet")),
+    }
+
+    let throughput = bench
+        .stats
+        .as_ref()
+        .map(|stats| {
+            let width = usize::from(sparkline_area.width);
+            &stats.throughput[stats.throughput.len().saturating_sub(width)..]
+        })
+        .unwrap_or_default();
+    let sparkline = Sparkline::default()
+        .block(Block::new().title("Requests per second"))
+        .data(throughput)
+        .style(Style::new().cyan());
+
+    frame.render_widget(Clear, modal_area);
+    frame.render_widget(modal, modal_area);
+    frame.render_widget(Paragraph::new(lines), stats_area);
+    frame.render_widget(sparkline, sparkline_area);
+}
+
+fn draw_history(
+    frame: &mut Frame,
+    history: &[Execution],
+    mark: Option<&Execution>,
+    list_state: &mut ListState,
+) {
+    let modal = title_block(" History ".into(), Color::White).title(
+        Title::from(
+            " (enter) open in editor / (r) re-send / (space) mark / (d) diff with mark or current / (esc) close ",
+        )
+            .position(Position::Bottom)
+            .alignment(Alignment::Center),
+    );
+    let format = format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
+
+    let entries = history
+        .iter()
+        .rev()
+        .map(|execution| {
+            let status = execution.response.status;
+            let style = if status >= 400 {
+                Style::new().red()
+            } else {
+                Style::new().green()
+            };
+            let mut line = vec![
+                Span::raw(format!(
+                    "{} {}  {:7} ",
+                    if mark == Some(execution) { '*' } else { ' ' },
+                    execution.started.format(format).unwrap_or_default(),
+                    execution.request.method.to_string()
+                )),
+                Span::styled(status.to_string(), style),
+                Span::raw(format!(
+                    " {:>6}ms  ",
+                    execution.response.duration.as_millis()
+                )),
+            ];
+            if let Some(env) = &execution.environment {
+                line.push(Span::styled(format!("[{}] ", env), Style::new().cyan()));
+            }
+            line.push(Span::raw(format!(
+                "{}  {}",
+                execution.request.name, execution.request.url
+            )));
+            Line::from(line)
+        })
+        .collect::<Vec<_>>();
+    let list = List::new(entries)
+        .block(modal)
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    let modal_area = modal_layout(75, 50, frame.size());
+
+    frame.render_widget(Clear, modal_area);
+    frame.render_stateful_widget(list, modal_area, list_state);
+}
+
+fn draw_diff(frame: &mut Frame, view: &DiffView) {
+    let diff = &view.diff;
+    let (hunk, hunks) = view.hunk_position();
+    let modal = title_block(format!(" Diff: {} ", view.title), Color::White).title(
+        Title::from(format!(
+            " change {}/{} / (j/k) scroll / (n/N) next/previous change / (esc) back ",
+            hunk, hunks
+        ))
+        .position(Position::Bottom)
+        .alignment(Alignment::Center),
+    );
+    let modal_area = modal_layout(75, 75, frame.size());
+    let [summary_area, body_area] = Layout::vertical([
+        Constraint::Length(diff.headers.len() as u16 + 2),
+        Constraint::Min(1),
+    ])
+    .areas(modal.inner(modal_area));
+
+    let mut summary = vec![if diff.old_status == diff.new_status {
+        Line::from(format!("Status {}", diff.new_status))
+    } else {
+        Line::from(vec![
+            Span::raw("Status "),
+            Span::styled(diff.old_status.to_string(), Style::new().red()),
+            Span::raw(" → "),
+            Span::styled(diff.new_status.to_string(), Style::new().green()),
+        ])
+    }];
+    for change in &diff.headers {
+        let mut line = vec![Span::raw(format!("{}: ", change.name))];
+        if let Some(old) = &change.old {
+            line.push(Span::styled(format!("-{} ", old), Style::new().red()));
+        }
+        if let Some(new) = &change.new {
+            line.push(Span::styled(format!("+{}", new), Style::new().green()));
+        }
+        summary.push(Line::from(line));
+    }
+    summary.push(Line::from(if diff.is_empty() {
+        "No differences".to_string()
+    } else if diff.structural {
+        "Body (JSON, keys sorted)".to_string()
+    } else {
+        "Body".to_string()
+    }));
+
+    let body = diff
+        .body
+        .iter()
+        .skip(view.scroll)
+        .take(body_area.height as usize)
+        .map(|line| match line.change {
+            Change::Equal => Line::from(format!(" {}", line.text)),
+            Change::Removed => Line::styled(format!("-{}", line.text), Style::new().red()),
+            Change::Added => Line::styled(format!("+{}", line.text), Style::new().green()),
+        })
+        .collect::<Vec<_>>();
+
+    frame.render_widget(Clear, modal_area);
+    frame.render_widget(modal, modal_area);
+    frame.render_widget(Paragraph::new(summary), summary_area);
+    frame.render_widget(Paragraph::new(body), body_area);
+}
+
+fn bench_lines(stats: &BenchStats) -> Vec<Line<'static>> {
+    let ms = |duration: Duration| format!("{:.1}ms", duration.as_secs_f64() * 1000.0);
+    let mut lines = vec![
+        Line::from(format!(
+            "{} {:.1}s  {} requests  {:.1} req/s  {} errors",
+            if stats.finished {
+                "Finished"
+            } else {
+                "Running"
+            },
+            stats.elapsed.as_secs_f64(),
+            stats.requests,
+            stats.requests_per_second(),
+            stats.error_count()
+        ))
+        .bold(),
+        Line::from(format!(
+            "Latency  p50 {}  p90 {}  p99 {}  max {}",
+            ms(stats.latency(0.5)),
+            ms(stats.latency(0.9)),
+            ms(stats.latency(0.99)),
+            ms(stats.max_latency())
+        )),
+        Line::from(format!(
+            "Status   {}",
+            stats
+                .statuses
+                .iter()
+                .map(|(status, count)| format!("{}: {}", status, count))
+                .collect::<Vec<_>>()
+                .join("  ")
+        )),
+    ];
+    lines.extend(
+        stats
+            .errors
+            .iter()
+            .map(|(err, count)| Line::from(format!("{} x {}", count, err)).red()),
+    );
+    lines
+}
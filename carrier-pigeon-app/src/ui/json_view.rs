@@ -0,0 +1,429 @@
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Paragraph, StatefulWidget, Widget},
+};
+use serde_json::Value;
+use std::collections::HashSet;
+use time::OffsetDateTime;
+
+/// What a body was taken from, the start of its execution and the filter it went through.
+pub type Source = (OffsetDateTime, Option<String>);
+
+/// A line of the pretty printed tree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    /// JSONPath of the node the row belongs to, closing brackets share their container's path.
+    pub path: String,
+    pub depth: usize,
+    /// Object key, or array index, of the node.
+    pub key: Option<String>,
+    pub kind: RowKind,
+    /// Whether a comma follows the row.
+    pub comma: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RowKind {
+    Open {
+        bracket: char,
+        folded: bool,
+        len: usize,
+    },
+    Close(char),
+    Scalar(Value),
+}
+
+/// Parsed response body, folded nodes and cursor of the JSON viewer.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JsonViewState {
+    /// Where the body the tree was parsed from came from, it is only parsed again when that
+    /// changes.
+    source: Option<Source>,
+    root: Option<Value>,
+    /// Paths of the objects and arrays shown collapsed.
+    folded: HashSet<String>,
+    /// The tree as lines, only built again when a node is folded or unfolded.
+    rows: Vec<Row>,
+    cursor: usize,
+    offset: usize,
+}
+
+impl JsonViewState {
+    /// Parses `body` unless it came from the same `source` as the last one, returns whether it
+    /// is JSON.
+    pub fn load(&mut self, source: Source, body: &[u8]) -> bool {
+        if self.source.as_ref() != Some(&source) {
+            *self = Self {
+                source: Some(source),
+                root: serde_json::from_slice(body).ok(),
+                ..Default::default()
+            };
+            self.build_rows();
+        }
+        self.is_json()
+    }
+
+    /// Drops the tree, for when the body isn't shown as JSON.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn is_json(&self) -> bool {
+        self.root.is_some()
+    }
+
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    fn build_rows(&mut self) {
+        self.rows.clear();
+        if let Some(root) = &self.root {
+            flatten(
+                &self.folded,
+                root,
+                String::from("$"),
+                None,
+                0,
+                false,
+                &mut self.rows,
+            );
+        }
+    }
+
+    /// JSONPath of the node under the cursor.
+    pub fn selected_path(&self) -> Option<String> {
+        self.rows.get(self.cursor).map(|row| row.path.clone())
+    }
+
+    pub fn move_by(&mut self, rows: isize) {
+        let last = self.rows.len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(rows).min(last);
+    }
+
+    /// Collapses the object or array under the cursor. On a collapsed or plain value the cursor
+    /// moves to the parent instead, like closing a directory in a file tree.
+    pub fn fold(&mut self) {
+        let Some(row) = self.rows.get(self.cursor) else {
+            return;
+        };
+        match row.kind {
+            RowKind::Open { folded: false, .. } => {
+                self.folded.insert(row.path.clone());
+            }
+            RowKind::Close(_) => {
+                let path = row.path.clone();
+                self.cursor = self
+                    .rows
+                    .iter()
+                    .position(|open| open.path == path)
+                    .unwrap_or(self.cursor);
+                self.folded.insert(path);
+            }
+            _ => {
+                let depth = row.depth;
+                if let Some(parent) = self.rows[..self.cursor]
+                    .iter()
+                    .rposition(|parent| parent.depth < depth)
+                {
+                    self.cursor = parent;
+                }
+                return;
+            }
+        }
+        self.build_rows();
+    }
+
+    /// Expands the object or array under the cursor.
+    pub fn unfold(&mut self) {
+        let Some(path) = self.selected_path() else {
+            return;
+        };
+        if self.folded.remove(&path) {
+            self.build_rows();
+        }
+    }
+}
+
+fn flatten(
+    folded: &HashSet<String>,
+    value: &Value,
+    path: String,
+    key: Option<String>,
+    depth: usize,
+    comma: bool,
+    rows: &mut Vec<Row>,
+) {
+    let (bracket, close, children): (char, char, Vec<(String, String, &Value)>) = match value {
+        Value::Object(map) => (
+            '{',
+            '}',
+            map.iter()
+                .map(|(key, value)| {
+                    (
+                        key_path(&path, key),
+                        Value::from(key.as_str()).to_string(),
+                        value,
+                    )
+                })
+                .collect(),
+        ),
+        Value::Array(items) => (
+            '[',
+            ']',
+            items
+                .iter()
+                .enumerate()
+                .map(|(idx, value)| (format!("{}[{}]", path, idx), idx.to_string(), value))
+                .collect(),
+        ),
+        value => {
+            rows.push(Row {
+                path,
+                depth,
+                key,
+                kind: RowKind::Scalar(value.clone()),
+                comma,
+            });
+            return;
+        }
+    };
+
+    let is_folded = children.is_empty() || folded.contains(&path);
+    rows.push(Row {
+        path: path.clone(),
+        depth,
+        key,
+        kind: RowKind::Open {
+            bracket,
+            folded: is_folded,
+            len: children.len(),
+        },
+        comma: comma && is_folded,
+    });
+    if is_folded {
+        return;
+    }
+    let last = children.len() - 1;
+    for (idx, (child_path, child_key, child)) in children.into_iter().enumerate() {
+        let child_key = (bracket == '{').then_some(child_key);
+        flatten(
+            folded,
+            child,
+            child_path,
+            child_key,
+            depth + 1,
+            idx < last,
+            rows,
+        );
+    }
+    rows.push(Row {
+        path,
+        depth,
+        key: None,
+        kind: RowKind::Close(close),
+        comma,
+    });
+}
+
+/// Appends an object key to a JSONPath, in bracket notation when it isn't a plain identifier.
+fn key_path(path: &str, key: &str) -> String {
+    let plain = key
+        .chars()
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if plain {
+        format!("{}.{}", path, key)
+    } else {
+        format!(
+            "{}['{}']",
+            path,
+            key.replace('\\', "\\\\").replace('\'', "\\'")
+        )
+    }
+}
+
+/// Pretty printed, collapsible JSON with a breadcrumb of the path under the cursor.
+#[derive(Default)]
+pub struct JsonView {
+    is_focused: bool,
+}
+
+impl JsonView {
+    pub fn focused(mut self, is_focused: bool) -> Self {
+        self.is_focused = is_focused;
+        self
+    }
+}
+
+impl StatefulWidget for JsonView {
+    type State = JsonViewState;
+
+    fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer, state: &mut Self::State) {
+        let [tree_area, path_area] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(area);
+        let height = tree_area.height as usize;
+        if state.cursor < state.offset {
+            state.offset = state.cursor;
+        } else if height > 0 && state.cursor >= state.offset + height {
+            state.offset = state.cursor + 1 - height;
+        }
+
+        let rows = state.rows();
+        let lines = rows
+            .iter()
+            .enumerate()
+            .skip(state.offset)
+            .take(height)
+            .map(|(idx, row)| {
+                let line = row_line(row);
+                if self.is_focused && idx == state.cursor {
+                    line.patch_style(Style::new().add_modifier(Modifier::REVERSED))
+                } else {
+                    line
+                }
+            })
+            .collect::<Vec<_>>();
+        Paragraph::new(lines).render(tree_area, buf);
+
+        if let Some(row) = rows.get(state.cursor) {
+            Paragraph::new(Line::from(vec![
+                Span::raw(row.path.clone()).dark_gray(),
+                Span::raw("  (y) copy path").dark_gray().italic(),
+            ]))
+            .render(path_area, buf);
+        }
+    }
+}
+
+fn row_line(row: &Row) -> Line<'_> {
+    let mut spans = vec![Span::raw("  ".repeat(row.depth))];
+    if let Some(key) = &row.key {
+        spans.push(Span::raw(key.clone()).cyan());
+        spans.push(Span::raw(": "));
+    }
+    match &row.kind {
+        RowKind::Open {
+            bracket,
+            folded: true,
+            len,
+        } => {
+            let close = if *bracket == '{' { '}' } else { ']' };
+            if *len == 0 {
+                spans.push(Span::raw(format!("{}{}", bracket, close)));
+            } else {
+                spans.push(Span::raw(bracket.to_string()));
+                spans.push(
+                    Span::raw(format!(
+                        " … {} {} ",
+                        len,
+                        match (bracket, len) {
+                            ('{', 1) => "key",
+                            ('{', _) => "keys",
+                            (_, 1) => "item",
+                            _ => "items",
+                        }
+                    ))
+                    .dark_gray(),
+                );
+                spans.push(Span::raw(close.to_string()));
+            }
+        }
+        RowKind::Open { bracket, .. } => spans.push(Span::raw(bracket.to_string())),
+        RowKind::Close(bracket) => spans.push(Span::raw(bracket.to_string())),
+        RowKind::Scalar(value) => spans.push(match value {
+            Value::String(_) => Span::raw(value.to_string()).green(),
+            Value::Number(_) => Span::raw(value.to_string()).yellow(),
+            Value::Bool(_) => Span::raw(value.to_string()).magenta(),
+            _ => Span::raw(value.to_string()).dark_gray(),
+        }),
+    }
+    if row.comma {
+        spans.push(Span::raw(","));
+    }
+    Line::from(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(body: &str) -> JsonViewState {
+        let mut state = JsonViewState::default();
+        assert!(state.load((OffsetDateTime::UNIX_EPOCH, None), body.as_bytes()));
+        state
+    }
+
+    fn text(state: &JsonViewState) -> Vec<String> {
+        state
+            .rows()
+            .iter()
+            .map(|row| row_line(row).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn pretty_prints() {
+        let state = state(r#"{"id": 7, "tags": ["a", null], "owner": {}}"#);
+
+        assert_eq!(
+            text(&state),
+            vec![
+                "{",
+                r#"  "id": 7,"#,
+                r#"  "tags": ["#,
+                r#"    "a","#,
+                "    null",
+                "  ],",
+                r#"  "owner": {}"#,
+                "}",
+            ]
+        );
+        let paths: Vec<_> = state.rows().iter().map(|row| &row.path).collect();
+        assert_eq!(paths[3], "$.tags[0]");
+        assert!(!JsonViewState::default().load((OffsetDateTime::UNIX_EPOCH, None), b"not json"));
+    }
+
+    #[test]
+    fn folds_and_moves_to_parent() {
+        let mut state = state(r#"{"pets": [{"name": "Rex"}], "next": null}"#);
+        state.move_by(3);
+        assert_eq!(state.selected_path().unwrap(), "$.pets[0].name");
+
+        state.fold();
+        assert_eq!(state.selected_path().unwrap(), "$.pets[0]");
+        state.fold();
+        state.move_by(-1);
+        state.fold();
+
+        assert_eq!(
+            text(&state),
+            vec!["{", r#"  "pets": [ … 1 item ],"#, r#"  "next": null"#, "}"]
+        );
+        state.unfold();
+        assert_eq!(text(&state)[2], r#"    { … 1 key }"#);
+    }
+
+    #[test]
+    fn parses_again_only_for_a_new_source() {
+        let mut state = state(r#"{"pets": [1, 2]}"#);
+        state.move_by(1);
+        state.fold();
+
+        assert!(state.load((OffsetDateTime::UNIX_EPOCH, None), b"not parsed"));
+        assert_eq!(text(&state)[1], r#"  "pets": [ … 2 items ]"#);
+        let source = (OffsetDateTime::UNIX_EPOCH, Some(String::from("$.pets")));
+        assert!(state.load(source, b"[1, 2]"));
+        assert_eq!(text(&state), vec!["[", "  1,", "  2", "]"]);
+    }
+
+    #[test]
+    fn quotes_keys_in_paths() {
+        assert_eq!(key_path("$", "user_id"), "$.user_id");
+        assert_eq!(key_path("$", "content-type"), "$['content-type']");
+        assert_eq!(key_path("$", "it's"), r"$['it\'s']");
+    }
+}
@@ -13,7 +13,7 @@ pub struct ScreenLayout {
     pub url_area: Rect,
     pub req_area: Rect,
     pub res_area: Rect,
-    pub _help_area: Rect,
+    pub help_area: Rect,
 }
 
 pub fn screen_layout(frame: &Frame) -> ScreenLayout {
@@ -42,7 +42,7 @@ pub fn screen_layout(frame: &Frame) -> ScreenLayout {
         url_area: vert_sects[0],
         req_area: view_panes[0],
         res_area: view_panes[1],
-        _help_area: vert_chunks[1],
+        help_area: vert_chunks[1],
     }
 }
 
@@ -0,0 +1,419 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, StatefulWidget, Widget},
+};
+
+/// Row and column of a character, counted in characters rather than bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub row: usize,
+    pub col: usize,
+}
+
+impl Position {
+    fn new(row: usize, col: usize) -> Self {
+        Self { row, col }
+    }
+}
+
+/// Where the cursor goes, shift extends the selection along the way.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    WordLeft,
+    WordRight,
+    LineStart,
+    LineEnd,
+    Start,
+    End,
+}
+
+/// Text, cursor and selection of a field being edited.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextEditState {
+    lines: Vec<String>,
+    cursor: Position,
+    /// Where the selection started, it runs to the cursor.
+    anchor: Option<Position>,
+    multiline: bool,
+    /// First row and column shown.
+    scroll: Position,
+}
+
+impl TextEditState {
+    /// An editor for a single line, like the URL, with the cursor at the end. Line breaks in the
+    /// text, or pasted into it, become spaces.
+    pub fn single_line(text: &str) -> Self {
+        let line = text.replace("\r\n", " ").replace(['\r', '\n'], " ");
+        let cursor = Position::new(0, line.chars().count());
+        Self {
+            lines: vec![line],
+            cursor,
+            anchor: None,
+            multiline: false,
+            scroll: Position::default(),
+        }
+    }
+
+    /// An editor for text spanning lines, like a request body, with the cursor at the start.
+    pub fn multi_line(text: &str) -> Self {
+        Self {
+            lines: text
+                .split('\n')
+                .map(|line| line.strip_suffix('\r').unwrap_or(line).to_string())
+                .collect(),
+            cursor: Position::default(),
+            anchor: None,
+            multiline: true,
+            scroll: Position::default(),
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    /// Start and end of the selected text, when any is selected.
+    pub fn selection(&self) -> Option<(Position, Position)> {
+        let anchor = self.anchor.filter(|anchor| *anchor != self.cursor)?;
+        Some((anchor.min(self.cursor), anchor.max(self.cursor)))
+    }
+
+    pub fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection()?;
+        Some(self.text_between(start, end))
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(Position::default());
+        self.cursor = self.target(Motion::End);
+    }
+
+    /// Inserts `text` at the cursor, in place of the selection.
+    pub fn insert_str(&mut self, text: &str) {
+        self.delete_selection();
+        self.anchor = None;
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let text = if self.multiline {
+            text
+        } else {
+            text.replace('\n', " ")
+        };
+
+        let Position { mut row, col } = self.cursor;
+        let rest = {
+            let line = &mut self.lines[row];
+            line.split_off(byte_index(line, col))
+        };
+        let mut parts = text.split('\n');
+        self.lines[row].push_str(parts.next().unwrap_or_default());
+        for part in parts {
+            row += 1;
+            self.lines.insert(row, part.to_string());
+        }
+        let col = self.lines[row].chars().count();
+        self.lines[row].push_str(&rest);
+        self.cursor = Position::new(row, col);
+    }
+
+    pub fn insert_char(&mut self, char: char) {
+        self.insert_str(char.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Removes the selected text, returning it.
+    pub fn delete_selection(&mut self) -> Option<String> {
+        let (start, end) = self.selection()?;
+        Some(self.delete_between(start, end))
+    }
+
+    /// Deletes the selection, or from the cursor to where `motion` would move it.
+    pub fn delete(&mut self, motion: Motion) {
+        if self.delete_selection().is_none() {
+            let target = self.target(motion);
+            self.delete_between(self.cursor.min(target), self.cursor.max(target));
+        }
+    }
+
+    /// Moves the cursor, extending the selection when `select` is set and dropping it otherwise.
+    pub fn move_cursor(&mut self, motion: Motion, select: bool) {
+        if !select {
+            self.anchor = None;
+        } else if self.anchor.is_none() {
+            self.anchor = Some(self.cursor);
+        }
+        self.cursor = self.target(motion);
+    }
+
+    /// Applies an editing key, returns whether it was one.
+    pub fn input(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        match key.code {
+            KeyCode::Char('a') if ctrl => self.select_all(),
+            KeyCode::Char('w') if ctrl => self.delete(Motion::WordLeft),
+            KeyCode::Char('u') if ctrl => self.delete(Motion::LineStart),
+            KeyCode::Char('k') if ctrl => self.delete(Motion::LineEnd),
+            KeyCode::Char('b') if alt => self.move_cursor(Motion::WordLeft, shift),
+            KeyCode::Char('f') if alt => self.move_cursor(Motion::WordRight, shift),
+            KeyCode::Char('d') if alt => self.delete(Motion::WordRight),
+            KeyCode::Char(char) if !ctrl && !alt => self.insert_char(char),
+            KeyCode::Enter if self.multiline => self.insert_char('\n'),
+            KeyCode::Backspace if ctrl || alt => self.delete(Motion::WordLeft),
+            KeyCode::Backspace => self.delete(Motion::Left),
+            KeyCode::Delete if ctrl => self.delete(Motion::WordRight),
+            KeyCode::Delete => self.delete(Motion::Right),
+            KeyCode::Left if ctrl => self.move_cursor(Motion::WordLeft, shift),
+            KeyCode::Left => self.move_cursor(Motion::Left, shift),
+            KeyCode::Right if ctrl => self.move_cursor(Motion::WordRight, shift),
+            KeyCode::Right => self.move_cursor(Motion::Right, shift),
+            KeyCode::Up => self.move_cursor(Motion::Up, shift),
+            KeyCode::Down => self.move_cursor(Motion::Down, shift),
+            KeyCode::Home if ctrl => self.move_cursor(Motion::Start, shift),
+            KeyCode::Home => self.move_cursor(Motion::LineStart, shift),
+            KeyCode::End if ctrl => self.move_cursor(Motion::End, shift),
+            KeyCode::End => self.move_cursor(Motion::LineEnd, shift),
+            _ => return false,
+        }
+        true
+    }
+
+    /// The lines shown in an area of `width` by `height`, scrolled to keep the cursor in view.
+    pub fn visible_lines(&mut self, width: u16, height: u16) -> Vec<Line<'static>> {
+        let (width, height) = (usize::from(width.max(1)), usize::from(height.max(1)));
+        let Position { row, col } = self.cursor;
+        if row < self.scroll.row {
+            self.scroll.row = row;
+        } else if row >= self.scroll.row + height {
+            self.scroll.row = row + 1 - height;
+        }
+        if col < self.scroll.col {
+            self.scroll.col = col;
+        } else if col >= self.scroll.col + width {
+            self.scroll.col = col + 1 - width;
+        }
+
+        let selection = self.selection();
+        let cursor_style = Style::new().add_modifier(Modifier::REVERSED);
+        let selected_style = Style::new().bg(Color::DarkGray);
+        self.lines
+            .iter()
+            .enumerate()
+            .skip(self.scroll.row)
+            .take(height)
+            .map(|(row, line)| {
+                let chars: Vec<char> = line.chars().collect();
+                // One column past the end of the line, so the cursor can sit there.
+                let end = (chars.len() + 1).min(self.scroll.col + width);
+                let mut spans: Vec<Span> = vec![];
+                for col in self.scroll.col..end {
+                    let position = Position::new(row, col);
+                    let style = if position == self.cursor {
+                        cursor_style
+                    } else if selection.is_some_and(|(start, end)| {
+                        start <= position && position < end && col < chars.len()
+                    }) {
+                        selected_style
+                    } else {
+                        Style::new()
+                    };
+                    let char = chars.get(col).copied().unwrap_or(' ');
+                    match spans.last_mut() {
+                        Some(span) if span.style == style => span.content.to_mut().push(char),
+                        _ => spans.push(Span::styled(char.to_string(), style)),
+                    }
+                }
+                Line::from(spans)
+            })
+            .collect()
+    }
+
+    fn line_len(&self, row: usize) -> usize {
+        self.lines[row].chars().count()
+    }
+
+    /// Where `motion` moves the cursor.
+    fn target(&self, motion: Motion) -> Position {
+        let Position { row, col } = self.cursor;
+        let last_row = self.lines.len() - 1;
+        let is_word = |char: &char| char.is_alphanumeric() || *char == '_';
+        match motion {
+            Motion::Left if col > 0 => Position::new(row, col - 1),
+            Motion::Left if row > 0 => Position::new(row - 1, self.line_len(row - 1)),
+            Motion::Right if col < self.line_len(row) => Position::new(row, col + 1),
+            Motion::Right if row < last_row => Position::new(row + 1, 0),
+            Motion::Up if row > 0 => Position::new(row - 1, col.min(self.line_len(row - 1))),
+            Motion::Down if row < last_row => {
+                Position::new(row + 1, col.min(self.line_len(row + 1)))
+            }
+            Motion::WordLeft if col == 0 => self.target(Motion::Left),
+            Motion::WordLeft => {
+                let chars: Vec<char> = self.lines[row].chars().take(col).collect();
+                let skipped = chars.iter().rev().take_while(|char| !is_word(char)).count();
+                let word = chars[..col - skipped]
+                    .iter()
+                    .rev()
+                    .take_while(|char| is_word(char))
+                    .count();
+                Position::new(row, col - skipped - word)
+            }
+            Motion::WordRight if col == self.line_len(row) => self.target(Motion::Right),
+            Motion::WordRight => {
+                let chars: Vec<char> = self.lines[row].chars().skip(col).collect();
+                let word = chars.iter().take_while(|char| is_word(char)).count();
+                let skipped = chars[word..]
+                    .iter()
+                    .take_while(|char| !is_word(char))
+                    .count();
+                Position::new(row, col + word + skipped)
+            }
+            Motion::LineStart => Position::new(row, 0),
+            Motion::LineEnd => Position::new(row, self.line_len(row)),
+            Motion::Start => Position::default(),
+            Motion::End => Position::new(last_row, self.line_len(last_row)),
+            _ => self.cursor,
+        }
+    }
+
+    fn text_between(&self, start: Position, end: Position) -> String {
+        let slice = |row: usize, from: usize, to: Option<usize>| {
+            let line = &self.lines[row];
+            let to = to.map_or(line.len(), |to| byte_index(line, to));
+            line[byte_index(line, from)..to].to_string()
+        };
+        if start.row == end.row {
+            return slice(start.row, start.col, Some(end.col));
+        }
+        let mut text = vec![slice(start.row, start.col, None)];
+        text.extend(self.lines[start.row + 1..end.row].iter().cloned());
+        text.push(slice(end.row, 0, Some(end.col)));
+        text.join("\n")
+    }
+
+    fn delete_between(&mut self, start: Position, end: Position) -> String {
+        let removed = self.text_between(start, end);
+        let tail = {
+            let line = &self.lines[end.row];
+            line[byte_index(line, end.col)..].to_string()
+        };
+        let line = &mut self.lines[start.row];
+        line.truncate(byte_index(line, start.col));
+        line.push_str(&tail);
+        self.lines.drain(start.row + 1..=end.row);
+        self.cursor = start;
+        self.anchor = None;
+        removed
+    }
+}
+
+/// Byte offset of the character at `col`, or the end of the line.
+fn byte_index(line: &str, col: usize) -> usize {
+    line.char_indices()
+        .nth(col)
+        .map_or(line.len(), |(idx, _)| idx)
+}
+
+/// Draws a field being edited with its cursor and selection.
+#[derive(Default)]
+pub struct TextEdit;
+
+impl StatefulWidget for TextEdit {
+    type State = TextEditState;
+
+    fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer, state: &mut Self::State) {
+        Clear.render(area, buf);
+        Paragraph::new(state.visible_lines(area.width, area.height)).render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    fn type_str(state: &mut TextEditState, text: &str) {
+        text.chars().for_each(|char| {
+            state.input(key(KeyCode::Char(char), KeyModifiers::NONE));
+        });
+    }
+
+    #[test]
+    fn edits_a_single_line() {
+        let mut state = TextEditState::single_line("https://pets.test/pets");
+        type_str(&mut state, "/7");
+        state.input(key(KeyCode::Home, KeyModifiers::NONE));
+        state.input(key(KeyCode::Delete, KeyModifiers::NONE));
+        state.input(key(KeyCode::Char('H'), KeyModifiers::SHIFT));
+
+        assert_eq!(state.text(), "Https://pets.test/pets/7");
+        assert!(!state.input(key(KeyCode::Enter, KeyModifiers::NONE)));
+        state.insert_str("\n?q=1");
+        assert_eq!(state.text(), "H ?q=1ttps://pets.test/pets/7");
+    }
+
+    #[test]
+    fn moves_and_deletes_by_word() {
+        let mut state = TextEditState::single_line("get my_pets now");
+        state.input(key(KeyCode::Left, KeyModifiers::CONTROL));
+        assert_eq!(state.cursor.col, 12);
+        state.input(key(KeyCode::Left, KeyModifiers::CONTROL));
+        assert_eq!(state.cursor.col, 4);
+        state.input(key(KeyCode::Right, KeyModifiers::CONTROL));
+        assert_eq!(state.cursor.col, 12);
+        state.input(key(KeyCode::Char('w'), KeyModifiers::CONTROL));
+
+        assert_eq!(state.text(), "get now");
+    }
+
+    #[test]
+    fn selects_and_replaces() {
+        let mut state = TextEditState::multi_line("{\n  \"name\": \"Rex\"\n}");
+        state.move_cursor(Motion::Down, false);
+        state.move_cursor(Motion::LineEnd, false);
+        for _ in 0..5 {
+            state.input(key(KeyCode::Left, KeyModifiers::SHIFT));
+        }
+        assert_eq!(state.selected_text().unwrap(), "\"Rex\"");
+
+        type_str(&mut state, "\"Tom\"");
+        assert_eq!(state.text(), "{\n  \"name\": \"Tom\"\n}");
+
+        state.select_all();
+        state.input(key(KeyCode::Backspace, KeyModifiers::NONE));
+        assert_eq!(state.text(), "");
+    }
+
+    #[test]
+    fn joins_and_splits_lines() {
+        let mut state = TextEditState::multi_line("ab\ncd");
+        state.move_cursor(Motion::Down, false);
+        state.input(key(KeyCode::Backspace, KeyModifiers::NONE));
+        assert_eq!(state.text(), "abcd");
+
+        state.input(key(KeyCode::Enter, KeyModifiers::NONE));
+        state.insert_str("ü\r\nx");
+        assert_eq!(state.text(), "ab\nü\nxcd");
+        assert_eq!(state.cursor, Position::new(2, 1));
+        state.move_cursor(Motion::Start, true);
+        assert_eq!(state.selected_text().unwrap(), "ab\nü\nx");
+    }
+
+    #[test]
+    fn scrolls_to_the_cursor() {
+        let mut state = TextEditState::single_line("0123456789");
+        let lines = state.visible_lines(4, 1);
+
+        assert_eq!(lines[0].to_string(), "789 ");
+        state.move_cursor(Motion::LineStart, false);
+        assert_eq!(state.visible_lines(4, 1)[0].to_string(), "0123");
+    }
+}
@@ -12,6 +12,21 @@ pub fn convert_case(str: String) -> String {
     String::from_utf8(return_bytes).expect("If the blows up we have bigger problems")
 }
 
+/// A byte count in the largest binary unit that keeps it at least 1, e.g. `1.5 MiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
 #[cfg(test)]
 pub mod test {
     use super::*;
@@ -27,4 +42,11 @@ pub mod test {
         let converted_string = convert_case("Hello".into());
         assert_eq!("Hello", converted_string);
     }
+
+    #[test]
+    fn format_bytes_units() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(2 * 1024 * 1024 * 1024), "2.0 GiB");
+    }
 }
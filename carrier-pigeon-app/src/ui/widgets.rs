@@ -1,16 +1,29 @@
-use carrier_pigeon_core::{Method, Request};
+use carrier_pigeon_core::{
+    body::{self, BodyFormat, Highlight},
+    executor::Progress,
+    response::Timings,
+    wire, Execution, Method, Request, Response,
+};
 use ratatui::{
     layout::{Constraint, Layout, Rect},
     style::{Color, Modifier, Style, Stylize},
     text::{Line, Span, Text},
-    widgets::{List, ListState, Paragraph, Row, StatefulWidget, Table, Tabs, Widget, Wrap},
+    widgets::{
+        Cell, Gauge, HighlightSpacing, List, ListState, Paragraph, Row, StatefulWidget, Table,
+        Tabs, Widget, Wrap,
+    },
 };
 
+use std::time::Duration;
+
 use crate::state::{
-    App, Pane, RequestDetailsState, RequestTab, ResponseDetailsState, ResponseTab, WindowState,
+    self, App, BodyKey, BodyView, Column, EditTarget, Editor, FilterOutput, Pane,
+    RequestDetailsState, RequestTab, ResponseDetailsState, ResponseTab, WindowState,
 };
 use crate::ui::layout;
 
+use super::json_view::JsonView;
+use super::text_edit::{TextEdit, TextEditState};
 use super::util;
 
 #[derive(Default)]
@@ -20,7 +33,7 @@ pub struct UrlBar<'a> {
 }
 
 impl UrlBar<'_> {
-    pub fn _new(is_focused: bool, url_text: Option<&String>) -> UrlBar {
+    pub fn _new(is_focused: bool, url_text: Option<&String>) -> UrlBar<'_> {
         UrlBar {
             is_focused,
             url_text,
@@ -31,17 +44,7 @@ impl UrlBar<'_> {
 impl<'a> UrlBar<'a> {
     pub fn construct(app: &'a App) -> UrlBar<'a> {
         let sw: &WindowState = &app.window_state;
-        let url_text = if let Some(coll) = &app.collection {
-            coll.requests
-                .get(
-                    sw.select_list_state
-                        .selected()
-                        .expect("Expected there to be a selected Request"),
-                )
-                .map(|req| &req.url)
-        } else {
-            None
-        };
+        let url_text = app.selected_request().map(|req| &req.url);
 
         UrlBar {
             url_text,
@@ -86,9 +89,13 @@ fn request_text(req: &Request) -> Text<'_> {
     let method_style = match req.method {
         Method::Get => Style::new().green().bold(),
         Method::Post => Style::new().magenta().bold(),
+        Method::Put => Style::new().blue().bold(),
+        Method::Patch => Style::new().yellow().bold(),
+        Method::Delete => Style::new().red().bold(),
+        Method::Head | Method::Options => Style::new().cyan().bold(),
     };
     Line::from(vec![
-        Span::styled(format!("{:5}", req.method.to_string()), method_style),
+        Span::styled(format!("{:7}", req.method.to_string()), method_style),
         Span::raw(": "),
         Span::raw(req.name.clone()),
     ])
@@ -165,6 +172,8 @@ impl StatefulWidget for RequestSelect<'_> {
 #[derive(Default)]
 pub struct RequestDetails<'a> {
     request: Option<&'a Request>,
+    execution: Option<&'a Execution>,
+    editor: Option<&'a mut Editor>,
     is_focused: bool,
 }
 
@@ -174,10 +183,22 @@ impl<'a> RequestDetails<'a> {
         self
     }
 
+    /// The last execution of the request, for showing it as sent.
+    pub fn execution(mut self, execution: Option<&'a Execution>) -> Self {
+        self.execution = execution;
+        self
+    }
+
     pub fn focused(mut self, is_focused: bool) -> Self {
         self.is_focused = is_focused;
         self
     }
+
+    /// The field being edited, drawn in place of the request's value.
+    pub fn editor(mut self, editor: Option<&'a mut Editor>) -> Self {
+        self.editor = editor;
+        self
+    }
 }
 
 impl StatefulWidget for RequestDetails<'_> {
@@ -209,46 +230,182 @@ impl StatefulWidget for RequestDetails<'_> {
         if let Some(req) = self.request {
             match state.selected_tab {
                 RequestTab::Body => {
+                    let mut body_area = layout[1];
+                    if let Some(encoding) = req.compression {
+                        let [note_area, rest] =
+                            Layout::vertical([Constraint::Length(1), Constraint::Min(0)])
+                                .areas(body_area);
+                        Line::from(format!("Sent {} compressed", encoding))
+                            .dark_gray()
+                            .render(note_area, buf);
+                        body_area = rest;
+                    }
+                    if let Some(Editor {
+                        target: EditTarget::Body,
+                        text,
+                        ..
+                    }) = self.editor
+                    {
+                        TextEdit.render(body_area, buf, text);
+                        return;
+                    }
                     let body = if let Some(body) = &req.body {
                         Paragraph::new(body.as_str()).wrap(Wrap { trim: true })
                     } else {
                         Paragraph::default()
                     };
-                    body.render(layout[1], buf);
+                    body.render(body_area, buf);
                 }
-                RequestTab::Headers => {
-                    let header_table = Table::default()
-                        .header(Row::new(vec!["Header Name", "Value"]))
-                        .rows(
-                            req.headers
-                                .iter()
-                                .map(|header| {
-                                    Row::new(vec![header.name.as_ref(), header.value.as_ref()])
-                                })
-                                .collect::<Vec<Row>>(),
-                        );
-                    Widget::render(header_table, layout[1], buf);
+                RequestTab::Headers | RequestTab::PathParams | RequestTab::QueryParams => {
+                    let tab = state.selected_tab.clone();
+                    let editing = match self.editor {
+                        Some(Editor {
+                            target: EditTarget::Cell(edit_tab, row, column),
+                            text,
+                            ..
+                        }) if *edit_tab == tab => Some((*row, *column, text)),
+                        _ => None,
+                    };
+                    name_value_table(
+                        state::table_rows(req, &tab),
+                        &tab,
+                        editing,
+                        self.is_focused,
+                        layout[1],
+                        buf,
+                        state,
+                    );
+                }
+                RequestTab::Script => {
+                    let script = if let Some(script) = &req.pre_request_script {
+                        Paragraph::new(script.as_str())
+                    } else {
+                        Paragraph::default()
+                    };
+                    script.render(layout[1], buf);
+                }
+                RequestTab::Raw => {
+                    let raw = match self.execution.and_then(|execution| execution.sent.as_ref()) {
+                        Some(sent) => Paragraph::new(
+                            sent.lines()
+                                .into_iter()
+                                .map(styled_line)
+                                .collect::<Vec<_>>(),
+                        ),
+                        None => Paragraph::new("Send the request to see it as sent").dark_gray(),
+                    };
+                    raw.wrap(Wrap { trim: false }).render(layout[1], buf);
                 }
-                RequestTab::PathParams => {}
-                RequestTab::QueryParams => {}
             };
         }
     }
 }
 
+/// The headers or params of a request, with the cell being edited drawn by its editor.
+fn name_value_table(
+    mut rows: Vec<(String, String)>,
+    tab: &RequestTab,
+    mut editing: Option<(usize, Column, &mut TextEditState)>,
+    is_focused: bool,
+    area: Rect,
+    buf: &mut ratatui::prelude::Buffer,
+    state: &mut RequestDetailsState,
+) {
+    let title = match tab {
+        RequestTab::Headers => "Header Name",
+        RequestTab::PathParams => "Path Param",
+        _ => "Query Param",
+    };
+    let highlight = "> ";
+    let widths = [Constraint::Percentage(30), Constraint::Percentage(70)];
+    // Where the table puts its columns, to scroll the editor to the width of its cell.
+    let [name_area, value_area] = Layout::horizontal(widths).spacing(1).areas(Rect {
+        x: area.x + highlight.len() as u16,
+        width: area.width.saturating_sub(highlight.len() as u16),
+        ..area
+    });
+    if editing
+        .as_ref()
+        .is_some_and(|(row, _, _)| *row == rows.len())
+    {
+        rows.push(Default::default());
+    }
+    let rows = rows
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (name, value))| match &mut editing {
+            Some((row, Column::Name, text)) if *row == idx => Row::new(vec![
+                Cell::from(text.visible_lines(name_area.width, 1).remove(0)),
+                Cell::from(value),
+            ]),
+            Some((row, Column::Value, text)) if *row == idx => Row::new(vec![
+                Cell::from(name),
+                Cell::from(text.visible_lines(value_area.width, 1).remove(0)),
+            ]),
+            _ => Row::new(vec![name, value]),
+        })
+        .collect::<Vec<_>>();
+    let empty = rows.is_empty();
+
+    let table = Table::new(rows, widths)
+        .header(Row::new(vec![title, "Value"]))
+        .highlight_symbol(highlight)
+        .highlight_spacing(HighlightSpacing::Always)
+        .highlight_style(if is_focused {
+            Style::new().add_modifier(Modifier::UNDERLINED)
+        } else {
+            Style::new()
+        });
+    StatefulWidget::render(table, area, buf, &mut state.table_state);
+    if empty && is_focused {
+        let [_, hint_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Length(1)]).areas(area);
+        Line::from("(o) add a row")
+            .dark_gray()
+            .render(hint_area, buf);
+    }
+}
+
 #[derive(Default)]
-pub struct ResponseDetails {
+pub struct ResponseDetails<'a> {
+    execution: Option<&'a Execution>,
+    download: Option<Progress>,
+    filter: Option<&'a str>,
+    editing_filter: bool,
+    filtered: Option<&'a FilterOutput>,
     is_focused: bool,
 }
 
-impl ResponseDetails {
+impl<'a> ResponseDetails<'a> {
+    pub fn execution(mut self, execution: Option<&'a Execution>) -> Self {
+        self.execution = execution;
+        self
+    }
+
+    pub fn download(mut self, download: Option<Progress>) -> Self {
+        self.download = download;
+        self
+    }
+
+    pub fn filter(mut self, filter: Option<&'a str>, editing: bool) -> Self {
+        self.filter = filter;
+        self.editing_filter = editing;
+        self
+    }
+
+    /// Output of the filter for the shown execution, run outside of drawing.
+    pub fn filtered(mut self, filtered: Option<&'a FilterOutput>) -> Self {
+        self.filtered = filtered;
+        self
+    }
+
     pub fn focused(mut self, is_focused: bool) -> Self {
         self.is_focused = is_focused;
         self
     }
 }
 
-impl StatefulWidget for ResponseDetails {
+impl StatefulWidget for ResponseDetails<'_> {
     type State = ResponseDetailsState;
 
     fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer, state: &mut Self::State) {
@@ -276,5 +433,364 @@ impl StatefulWidget for ResponseDetails {
             .highlight_style(Style::default().bg(Color::White).fg(Color::from_u32(40)))
             .select(state.selected_tab.clone().into());
         res_tabs.render(layout[0], buf);
+
+        if let Some(progress) = self.download {
+            let [gauge_area, _] =
+                Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(layout[1]);
+            download_gauge(progress).render(gauge_area, buf);
+            return;
+        }
+
+        if let Some(execution) = self.execution {
+            let res = &execution.response;
+            match state.selected_tab {
+                ResponseTab::Body => {
+                    let [filter_area, mut body_area] =
+                        Layout::vertical([Constraint::Length(1), Constraint::Min(1)])
+                            .areas(layout[1]);
+                    let filter_line = match self.filter {
+                        _ if self.editing_filter => Line::from(vec![
+                            Span::raw("/ ").cyan(),
+                            Span::raw(self.filter.unwrap_or_default()),
+                            Span::raw("█"),
+                        ]),
+                        Some(filter) if !filter.trim().is_empty() => {
+                            Line::from(vec![Span::raw("/ ").cyan(), Span::raw(filter).cyan()])
+                        }
+                        _ => Line::from("(/) filter with JSONPath or jq").dark_gray(),
+                    };
+                    filter_line.render(filter_area, buf);
+
+                    let content_type = res.header("content-type");
+                    let hex =
+                        state.body_format.is_none() && body::is_binary(content_type, &res.body);
+                    let output = self
+                        .filter
+                        .filter(|filter| !filter.trim().is_empty())
+                        .and(self.filtered);
+                    let filtered = match (self.filter, output) {
+                        (Some(filter), Some(FilterOutput::Done(Ok(text)))) => Some((filter, text)),
+                        _ => None,
+                    };
+                    let mode = if hex {
+                        format!(
+                            "{} {}",
+                            body::mime_type(content_type, &res.body),
+                            body_size(res)
+                        )
+                    } else if res.paged_body().is_some() {
+                        format!(
+                            "{} on disk, at {}",
+                            body_size(res),
+                            util::format_bytes(state.spill_offset)
+                        )
+                    } else {
+                        let format = prepare_body(state, execution, filtered).format;
+                        format!(
+                            "{} {}  {}",
+                            format,
+                            if state.raw { "raw" } else { "pretty" },
+                            body_size(res)
+                        )
+                    };
+                    Line::from(mode)
+                        .dark_gray()
+                        .right_aligned()
+                        .render(filter_area, buf);
+
+                    if hex {
+                        state.json.clear();
+                        state.body_view = None;
+                        let offset = state.hex_scroll * body::HEX_WIDTH;
+                        let len = usize::from(body_area.height) * body::HEX_WIDTH;
+                        let bytes = match res.paged_body() {
+                            Some(spilled) => spilled.read_at(offset as u64, len),
+                            None => Ok(res.body.iter().skip(offset).take(len).copied().collect()),
+                        };
+                        let lines = match bytes {
+                            Ok(bytes) => bytes
+                                .chunks(body::HEX_WIDTH)
+                                .enumerate()
+                                .map(|(line, bytes)| {
+                                    styled_line(body::hex_line(
+                                        offset + line * body::HEX_WIDTH,
+                                        bytes,
+                                    ))
+                                })
+                                .collect::<Vec<_>>(),
+                            Err(err) => vec![Line::from(err.to_string()).red()],
+                        };
+                        Paragraph::new(lines).render(body_area, buf);
+                        return;
+                    }
+                    // Spilled bodies are too big to format, so a page of text is shown as it is.
+                    if let Some(spilled) = res.paged_body() {
+                        state.json.clear();
+                        state.body_view = None;
+                        let text = match spilled.read_at(state.spill_offset, SPILL_PAGE) {
+                            Ok(bytes) => Text::from(body::decode(&bytes, content_type)),
+                            Err(err) => Line::from(err.to_string()).red().into(),
+                        };
+                        Paragraph::new(text)
+                            .wrap(Wrap { trim: false })
+                            .render(body_area, buf);
+                        return;
+                    }
+
+                    let note = match output {
+                        Some(FilterOutput::Running) => Some(Line::from("Filtering…").dark_gray()),
+                        Some(FilterOutput::Done(Err(err))) => Some(Line::from(err.as_str()).red()),
+                        _ => res.spilled.as_ref().map(|spilled| {
+                            Line::from(format!(
+                                "Only the first {} of {} were kept",
+                                util::format_bytes(res.body.len() as u64),
+                                util::format_bytes(spilled.len)
+                            ))
+                            .dark_gray()
+                        }),
+                    };
+                    if let Some(note) = note {
+                        let [note_area, rest] =
+                            Layout::vertical([Constraint::Length(1), Constraint::Min(1)])
+                                .areas(body_area);
+                        note.render(note_area, buf);
+                        body_area = rest;
+                    }
+                    let view = prepare_body(state, execution, filtered);
+                    if view.json {
+                        JsonView::default().focused(self.is_focused).render(
+                            body_area,
+                            buf,
+                            &mut state.json,
+                        );
+                    } else {
+                        // Lines only get taller when wrapped, so the ones past the area never show.
+                        let lines = view
+                            .lines
+                            .iter()
+                            .take(body_area.height.into())
+                            .cloned()
+                            .collect::<Vec<_>>();
+                        Paragraph::new(lines)
+                            .wrap(Wrap { trim: false })
+                            .render(body_area, buf);
+                    }
+                }
+                ResponseTab::Headers => {
+                    let header_table = Table::default()
+                        .header(Row::new(vec!["Header Name", "Value"]))
+                        .rows(
+                            res.headers
+                                .iter()
+                                .map(|header| {
+                                    Row::new(vec![header.name.as_ref(), header.value.as_ref()])
+                                })
+                                .collect::<Vec<Row>>(),
+                        );
+                    Widget::render(header_table, layout[1], buf);
+                }
+                ResponseTab::Tests => {
+                    let results = execution
+                        .tests
+                        .iter()
+                        .map(|result| {
+                            let mark = if result.passed {
+                                Span::styled("✓ ", Style::new().green().bold())
+                            } else {
+                                Span::styled("✗ ", Style::new().red().bold())
+                            };
+                            let mut line = vec![mark, Span::raw(result.name.clone())];
+                            if let Some(message) = &result.message {
+                                line.push(Span::raw(format!(" - {}", message)).dark_gray());
+                            }
+                            Line::from(line)
+                        })
+                        .collect::<Vec<_>>();
+                    Widget::render(List::new(results), layout[1], buf);
+                }
+                ResponseTab::Raw => {
+                    let lines = wire::response_lines(res)
+                        .into_iter()
+                        .map(styled_line)
+                        .collect::<Vec<_>>();
+                    Paragraph::new(lines)
+                        .wrap(Wrap { trim: false })
+                        .render(layout[1], buf);
+                }
+                ResponseTab::Timing => {
+                    Paragraph::new(timing_waterfall(&res.timings, layout[1].width))
+                        .render(layout[1], buf);
+                }
+            }
+        }
     }
 }
+
+/// A row per request phase, with a bar starting where the previous phase ended.
+fn timing_waterfall(timings: &Timings, width: u16) -> Text<'static> {
+    let phases = timings.phases();
+    let total: Duration = phases.iter().map(|(_, phase)| *phase).sum();
+    // Room left for bars after the phase name and duration columns.
+    let bar_width = usize::from(width).saturating_sub(22).max(1);
+    let columns = |phase: Duration| {
+        (phase.as_secs_f64() / total.as_secs_f64().max(f64::EPSILON) * bar_width as f64).round()
+            as usize
+    };
+
+    let mut start = Duration::ZERO;
+    let mut lines = phases
+        .into_iter()
+        .map(|(name, phase)| {
+            let offset = columns(start);
+            let bar = columns(start + phase).saturating_sub(offset).max(1);
+            start += phase;
+            let color = match name {
+                "DNS" => Color::Cyan,
+                "Connect" => Color::Yellow,
+                "TLS" => Color::Magenta,
+                "TTFB" => Color::Green,
+                _ => Color::Blue,
+            };
+            Line::from(vec![
+                Span::raw(format!("{:<9}", name)),
+                Span::raw(format!("{:>10.1}ms ", phase.as_secs_f64() * 1000.0)),
+                Span::raw(" ".repeat(offset)),
+                Span::styled("█".repeat(bar), Style::new().fg(color)),
+            ])
+        })
+        .collect::<Vec<_>>();
+    lines.push(Line::from(format!(
+        "{:<9}{:>10.1}ms",
+        "Total",
+        total.as_secs_f64() * 1000.0
+    )));
+    let estimates = timings.estimates();
+    if !estimates.is_empty() {
+        lines.push(Line::default());
+        lines.push(Line::from("Estimated on a separate connection, not in the total").dark_gray());
+        lines.extend(estimates.into_iter().map(|(name, phase)| {
+            Line::from(format!(
+                "{:<9}{:>10.1}ms",
+                name,
+                phase.as_secs_f64() * 1000.0
+            ))
+            .dark_gray()
+        }));
+    } else if timings.dns.is_none() && timings.connect.is_none() {
+        lines.push(
+            Line::from("Connection phases were not measured, :probe on estimates them").dark_gray(),
+        );
+    }
+    lines.into()
+}
+
+/// Bytes of a spilled body read for each screen, enough to fill it unless lines are very long.
+const SPILL_PAGE: usize = 64 * 1024;
+
+/// Size of the response body, followed by the size it was received at when it was decompressed.
+fn body_size(res: &Response) -> String {
+    match (res.compressed_len, res.header("content-encoding")) {
+        (Some(compressed), Some(encoding)) => format!(
+            "{} ({} {})",
+            util::format_bytes(res.body_len()),
+            encoding,
+            util::format_bytes(compressed)
+        ),
+        _ => util::format_bytes(res.body_len()),
+    }
+}
+
+fn download_gauge(progress: Progress) -> Gauge<'static> {
+    let received = util::format_bytes(progress.received);
+    let throughput = util::format_bytes(progress.throughput() as u64);
+    match progress.total {
+        Some(total) if total > 0 => Gauge::default()
+            .gauge_style(Style::new().green())
+            .ratio((progress.received as f64 / total as f64).min(1.0))
+            .label(format!(
+                "{} / {} at {}/s",
+                received,
+                util::format_bytes(total),
+                throughput
+            )),
+        _ => Gauge::default()
+            .gauge_style(Style::new().green())
+            .ratio(0.0)
+            .label(format!("{} at {}/s", received, throughput)),
+    }
+}
+
+/// Decodes and highlights the body, or the filter output shown in its place, unless the view
+/// in `state` was made from the same execution, format, raw toggle and filter.
+fn prepare_body<'s>(
+    state: &'s mut ResponseDetailsState,
+    execution: &Execution,
+    filtered: Option<(&str, &String)>,
+) -> &'s BodyView {
+    let key = BodyKey {
+        started: execution.started,
+        format: state.body_format,
+        raw: state.raw,
+        filter: filtered.map(|(filter, _)| filter.to_string()),
+    };
+    if state.body_view.as_ref().map(|view| &view.key) != Some(&key) {
+        let res = &execution.response;
+        let content_type = res.header("content-type");
+        // Filter output is always JSON, while the body is decoded with its charset.
+        let (text, format) = match filtered {
+            Some((_, output)) => (output.clone(), BodyFormat::Json),
+            None => (
+                body::decode(&res.body, content_type),
+                state
+                    .body_format
+                    .unwrap_or_else(|| BodyFormat::detect(content_type, &res.body)),
+            ),
+        };
+        let source = (execution.started, key.filter.clone());
+        let json =
+            !state.raw && format == BodyFormat::Json && state.json.load(source, text.as_bytes());
+        let lines = if json {
+            vec![]
+        } else {
+            state.json.clear();
+            if state.raw {
+                Text::from(text).lines
+            } else {
+                body_text(format, &text).lines
+            }
+        };
+        state.body_view = Some(BodyView {
+            key,
+            format,
+            json,
+            lines,
+        });
+    }
+    state
+        .body_view
+        .as_ref()
+        .expect("body view was just prepared")
+}
+
+/// Pretty printed body text, coloured like the JSON viewer.
+fn body_text(format: BodyFormat, text: &str) -> Text<'static> {
+    body::pretty(format, text)
+        .into_iter()
+        .map(styled_line)
+        .collect()
+}
+
+fn styled_line(line: body::StyledLine) -> Line<'static> {
+    line.into_iter()
+        .map(|(highlight, text)| match highlight {
+            Highlight::Plain => Span::raw(text),
+            Highlight::Punctuation => Span::raw(text).dark_gray(),
+            Highlight::Tag | Highlight::Key => Span::raw(text).cyan(),
+            Highlight::Attribute => Span::raw(text).blue(),
+            Highlight::String => Span::raw(text).green(),
+            Highlight::Number => Span::raw(text).yellow(),
+            Highlight::Literal => Span::raw(text).magenta(),
+            Highlight::Comment => Span::raw(text).dark_gray().italic(),
+        })
+        .collect()
+}
@@ -0,0 +1,285 @@
+//! Checks run against a response once it has been received.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_json_path::JsonPath;
+use std::fmt;
+
+use crate::{script, vars, Response, Variables};
+
+/// The checks a request's response is expected to pass.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Assertions {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checks: Vec<Check>,
+    /// Rhai script that records results with `check(name, passed)`, see [`script::post_response`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+}
+
+impl Assertions {
+    pub fn is_empty(&self) -> bool {
+        self.checks.is_empty() && self.script.is_none()
+    }
+
+    /// Runs every check and the script against `res`.
+    pub fn run(&self, res: &Response) -> Vec<TestResult> {
+        let mut results: Vec<TestResult> = self.checks.iter().map(|check| check.run(res)).collect();
+        if let Some(script) = &self.script {
+            match script::post_response(script, res) {
+                Ok(script_results) => results.extend(script_results),
+                Err(err) => results.push(TestResult::fail("test script", err.to_string())),
+            }
+        }
+        results
+    }
+
+    pub(crate) fn resolve(&self, vars: &Variables) -> Self {
+        let checks = self
+            .checks
+            .iter()
+            .map(|check| match check {
+                Check::Header {
+                    name,
+                    equals: Some(equals),
+                } => Check::Header {
+                    name: name.clone(),
+                    equals: Some(vars::substitute(equals, vars)),
+                },
+                Check::JsonPath {
+                    path,
+                    equals: Some(Value::String(equals)),
+                } => Check::JsonPath {
+                    path: path.clone(),
+                    equals: Some(Value::String(vars::substitute(equals, vars))),
+                },
+                check => check.clone(),
+            })
+            .collect();
+        Self {
+            checks,
+            script: self.script.clone(),
+        }
+    }
+}
+
+/// A single declarative check.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "check", rename_all = "snake_case")]
+pub enum Check {
+    Status {
+        equals: u16,
+    },
+    /// Passes when the header is present, and has the given value if `equals` is set.
+    Header {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        equals: Option<String>,
+    },
+    /// Passes when the path matches, and its first match is `equals` if set.
+    JsonPath {
+        path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        equals: Option<Value>,
+    },
+    ResponseTime {
+        under_ms: u64,
+    },
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Status { equals } => write!(f, "status == {}", equals),
+            Self::Header { name, equals: None } => write!(f, "header {} exists", name),
+            Self::Header {
+                name,
+                equals: Some(equals),
+            } => write!(f, "header {} == {}", name, equals),
+            Self::JsonPath { path, equals: None } => write!(f, "{} exists", path),
+            Self::JsonPath {
+                path,
+                equals: Some(equals),
+            } => write!(f, "{} == {}", path, equals),
+            Self::ResponseTime { under_ms } => write!(f, "response time < {}ms", under_ms),
+        }
+    }
+}
+
+impl Check {
+    pub fn run(&self, res: &Response) -> TestResult {
+        let name = self.to_string();
+        let failure = match self {
+            Self::Status { equals } => {
+                (res.status != *equals).then(|| format!("status was {}", res.status))
+            }
+            Self::Header { name, equals } => match (res.header(name), equals) {
+                (None, _) => Some(String::from("header is missing")),
+                (Some(value), Some(equals)) if value != equals => {
+                    Some(format!("header was '{}'", value))
+                }
+                _ => None,
+            },
+            Self::JsonPath { path, equals } => json_path_failure(res, path, equals.as_ref()),
+            Self::ResponseTime { under_ms } => {
+                let took = res.duration.as_millis();
+                (took >= u128::from(*under_ms)).then(|| format!("took {}ms", took))
+            }
+        };
+
+        match failure {
+            Some(message) => TestResult::fail(name, message),
+            None => TestResult::pass(name),
+        }
+    }
+}
+
+fn json_path_failure(res: &Response, path: &str, equals: Option<&Value>) -> Option<String> {
+    let path = match JsonPath::parse(path) {
+        Ok(path) => path,
+        Err(err) => return Some(format!("invalid JSONPath: {}", err)),
+    };
+    let body = match res.complete_body() {
+        Ok(body) => body,
+        Err(err) => return Some(err.to_string()),
+    };
+    let body: Value = match serde_json::from_slice(body) {
+        Ok(body) => body,
+        Err(err) => return Some(format!("body is not JSON: {}", err)),
+    };
+    match (path.query(&body).first(), equals) {
+        (None, _) => Some(String::from("no match")),
+        (Some(found), Some(equals)) if found != equals => Some(format!("was {}", found)),
+        _ => None,
+    }
+}
+
+/// The outcome of a check, stored with the execution it ran against.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct TestResult {
+    pub name: String,
+    pub passed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl TestResult {
+    pub fn pass(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            passed: true,
+            message: None,
+        }
+    }
+
+    pub fn fail(name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            passed: false,
+            message: Some(message.into()),
+        }
+    }
+}
+
+impl fmt::Display for TestResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mark = if self.passed { "PASS" } else { "FAIL" };
+        match &self.message {
+            Some(message) => write!(f, "{} {}: {}", mark, self.name, message),
+            None => write!(f, "{} {}", mark, self.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Header;
+    use std::time::Duration;
+
+    fn response() -> Response {
+        Response {
+            status: 201,
+            headers: vec![Header {
+                name: "Content-Type".into(),
+                value: "application/json".into(),
+            }],
+            body: br#"{"pet":{"id":7,"tags":["good"]}}"#.to_vec(),
+            duration: Duration::from_millis(120),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn declarative_checks() {
+        let assertions: Assertions = serde_json::from_str(
+            r#"{ "checks": [
+                { "check": "status", "equals": 201 },
+                { "check": "header", "name": "content-type", "equals": "application/json" },
+                { "check": "header", "name": "ETag" },
+                { "check": "json_path", "path": "$.pet.id", "equals": 7 },
+                { "check": "json_path", "path": "$.pet.name" },
+                { "check": "response_time", "under_ms": 100 }
+            ] }"#,
+        )
+        .unwrap();
+
+        let results: Vec<String> = assertions
+            .run(&response())
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            results,
+            vec![
+                "PASS status == 201",
+                "PASS header content-type == application/json",
+                "FAIL header ETag exists: header is missing",
+                "PASS $.pet.id == 7",
+                "FAIL $.pet.name exists: no match",
+                "FAIL response time < 100ms: took 120ms",
+            ]
+        );
+    }
+
+    #[test]
+    fn script_checks() {
+        let assertions = Assertions {
+            checks: vec![],
+            script: Some(
+                r#"
+                    check("created", response.status == 201);
+                    check("tagged", response.json.pet.tags[0] == "good");
+                    check("fast", response.time_ms < 100, `took ${response.time_ms}ms`);
+                "#
+                .into(),
+            ),
+        };
+
+        let results = assertions.run(&response());
+
+        assert_eq!(
+            results,
+            vec![
+                TestResult::pass("created"),
+                TestResult::pass("tagged"),
+                TestResult::fail("fast", "took 120ms"),
+            ]
+        );
+    }
+
+    #[test]
+    fn script_errors_fail() {
+        let assertions = Assertions {
+            checks: vec![],
+            script: Some("check(".into()),
+        };
+
+        let results = assertions.run(&response());
+
+        assert_eq!(results.len(), 1);
+        assert!(!results[0].passed);
+        assert_eq!(results[0].name, "test script");
+    }
+}
@@ -0,0 +1,415 @@
+//! Load testing: sends a request, or a sequence of them, repeatedly from concurrent workers.
+
+use color_eyre::{eyre::bail, Result};
+use hdrhistogram::Histogram;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{sync::mpsc, time::Instant};
+
+use crate::{
+    executor::HttpClient,
+    runner::{self, Outcome},
+    Collection, Variables,
+};
+
+/// How often progress is reported while a benchmark runs.
+const TICK: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Limit {
+    /// Stop after sending this many requests.
+    Count(usize),
+    /// Stop sending requests once this much time has passed.
+    Duration(Duration),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchOptions {
+    /// Number of workers sending requests at the same time.
+    pub concurrency: usize,
+    /// Target requests per second across all workers, as fast as possible when unset.
+    pub rate: Option<f64>,
+    pub limit: Limit,
+}
+
+/// Statistics of a benchmark, updated as responses arrive.
+#[derive(Clone, Debug)]
+pub struct BenchStats {
+    /// Response latencies in microseconds.
+    latencies: Histogram<u64>,
+    /// Requests that received a response or failed to send.
+    pub requests: usize,
+    pub statuses: BTreeMap<u16, usize>,
+    /// Requests that could not be prepared or sent, by error message.
+    pub errors: BTreeMap<String, usize>,
+    /// Requests completed in each second of the benchmark.
+    pub throughput: Vec<u64>,
+    pub elapsed: Duration,
+    pub finished: bool,
+}
+
+impl Default for BenchStats {
+    fn default() -> Self {
+        Self {
+            latencies: Histogram::new_with_bounds(1, 3_600_000_000, 3)
+                .expect("histogram bounds are valid"),
+            requests: 0,
+            statuses: BTreeMap::new(),
+            errors: BTreeMap::new(),
+            throughput: vec![],
+            elapsed: Duration::ZERO,
+            finished: false,
+        }
+    }
+}
+
+enum Sample {
+    Response { status: u16, latency: Duration },
+    Error(String),
+}
+
+impl BenchStats {
+    fn record(&mut self, at: Duration, sample: Sample) {
+        self.requests += 1;
+        let second = at.as_secs() as usize;
+        if self.throughput.len() <= second {
+            self.throughput.resize(second + 1, 0);
+        }
+        self.throughput[second] += 1;
+
+        match sample {
+            Sample::Response { status, latency } => {
+                self.latencies
+                    .saturating_record(latency.as_micros().max(1) as u64);
+                *self.statuses.entry(status).or_default() += 1;
+            }
+            Sample::Error(err) => *self.errors.entry(err).or_default() += 1,
+        }
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.errors.values().sum()
+    }
+
+    pub fn requests_per_second(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            0.0
+        } else {
+            self.requests as f64 / self.elapsed.as_secs_f64()
+        }
+    }
+
+    /// The latency `quantile` of responses fall under, e.g. `0.99` for p99.
+    pub fn latency(&self, quantile: f64) -> Duration {
+        Duration::from_micros(self.latencies.value_at_quantile(quantile))
+    }
+
+    pub fn max_latency(&self) -> Duration {
+        Duration::from_micros(self.latencies.max())
+    }
+
+    pub fn mean_latency(&self) -> Duration {
+        Duration::from_micros(self.latencies.mean() as u64)
+    }
+
+    pub fn summary(&self) -> BenchSummary {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        BenchSummary {
+            requests: self.requests,
+            errors: self.error_count(),
+            duration_ms: self.elapsed.as_millis(),
+            requests_per_second: self.requests_per_second(),
+            latency_ms: LatencySummary {
+                p50: ms(self.latency(0.5)),
+                p90: ms(self.latency(0.9)),
+                p99: ms(self.latency(0.99)),
+                max: ms(self.max_latency()),
+                mean: ms(self.mean_latency()),
+            },
+            statuses: self
+                .statuses
+                .iter()
+                .map(|(status, count)| (status.to_string(), *count))
+                .collect(),
+            error_messages: self.errors.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BenchSummary {
+    pub requests: usize,
+    pub errors: usize,
+    pub duration_ms: u128,
+    pub requests_per_second: f64,
+    pub latency_ms: LatencySummary,
+    pub statuses: BTreeMap<String, usize>,
+    pub error_messages: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LatencySummary {
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+/// Hands out request slots to workers, pacing them to the target rate.
+struct Schedule {
+    start: Instant,
+    limit: Limit,
+    interval: Option<Duration>,
+    issued: AtomicUsize,
+    stop: Arc<AtomicBool>,
+}
+
+impl Schedule {
+    /// Waits for the next slot, returns false once the benchmark is over.
+    async fn acquire(&self) -> bool {
+        let slot = self.issued.fetch_add(1, Ordering::Relaxed);
+        if let Limit::Count(count) = self.limit {
+            if slot >= count {
+                return false;
+            }
+        }
+        if let Some(interval) = self.interval {
+            tokio::time::sleep_until(self.start + interval.mul_f64(slot as f64)).await;
+        }
+        let expired = match self.limit {
+            Limit::Duration(duration) => self.start.elapsed() >= duration,
+            Limit::Count(_) => false,
+        };
+        !expired && !self.stop.load(Ordering::Relaxed)
+    }
+}
+
+/// Sends the requests at `selection` in order, over and over, from `options.concurrency` workers
+/// until the limit is reached or `stop` is set. `on_tick` is called with the statistics so far
+/// every quarter second and once more when the benchmark finishes.
+///
+/// Each worker has its own copy of the collection, so scripts and extraction rules chain values
+/// through a sequence without the workers affecting each other.
+pub async fn bench(
+    client: &HttpClient,
+    coll: &Collection,
+    selection: &[usize],
+    env_name: Option<&str>,
+    options: &BenchOptions,
+    stop: Arc<AtomicBool>,
+    mut on_tick: impl FnMut(&BenchStats),
+) -> Result<BenchStats> {
+    if selection.is_empty() {
+        bail!("There are no requests to benchmark");
+    }
+    if options.concurrency == 0 {
+        bail!("Concurrency must be at least 1");
+    }
+    let interval = match options.rate {
+        Some(rate) if rate > 0.0 => Some(Duration::from_secs_f64(1.0 / rate)),
+        Some(_) => bail!("Request rate must be above 0"),
+        None => None,
+    };
+
+    let schedule = Arc::new(Schedule {
+        start: Instant::now(),
+        limit: options.limit,
+        interval,
+        issued: AtomicUsize::new(0),
+        stop,
+    });
+    let selection: Arc<[usize]> = selection.into();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    for _ in 0..options.concurrency {
+        tokio::spawn(worker(
+            client.clone(),
+            coll.clone(),
+            selection.clone(),
+            env_name.map(str::to_string),
+            schedule.clone(),
+            tx.clone(),
+        ));
+    }
+    drop(tx);
+
+    let mut stats = BenchStats::default();
+    let mut ticker = tokio::time::interval(TICK);
+    loop {
+        tokio::select! {
+            sample = rx.recv() => match sample {
+                Some((at, sample)) => stats.record(at, sample),
+                None => break,
+            },
+            _ = ticker.tick() => {
+                stats.elapsed = schedule.start.elapsed();
+                on_tick(&stats);
+            }
+        }
+    }
+
+    stats.elapsed = schedule.start.elapsed();
+    stats.finished = true;
+    on_tick(&stats);
+    Ok(stats)
+}
+
+async fn worker(
+    client: HttpClient,
+    mut coll: Collection,
+    selection: Arc<[usize]>,
+    env_name: Option<String>,
+    schedule: Arc<Schedule>,
+    tx: mpsc::UnboundedSender<(Duration, Sample)>,
+) {
+    // Probing every request's connection would double the connections the benchmark opens.
+    coll.settings.executor.probe_connection = false;
+    let no_data = Variables::new();
+    loop {
+        for &index in selection.iter() {
+            let Some(req) = coll.requests.get(index).cloned() else {
+                return;
+            };
+            if !schedule.acquire().await {
+                return;
+            }
+            let sample =
+                match runner::run_request(&client, &mut coll, &req, env_name.as_deref(), &no_data)
+                    .await
+                {
+                    Outcome::Completed(execution) => Sample::Response {
+                        status: execution.response.status,
+                        latency: execution.response.duration,
+                    },
+                    Outcome::Failed(err) => Sample::Error(err),
+                };
+            if tx.send((schedule.start.elapsed(), sample)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util, Method, Request};
+
+    /// Answers every connection with `200 OK`.
+    fn serve() -> String {
+        test_util::serve("", vec!["ok"])
+    }
+
+    fn collection(urls: &[String]) -> Collection {
+        Collection {
+            requests: urls
+                .iter()
+                .map(|url| {
+                    Request::builder()
+                        .name(url.clone())
+                        .method(Method::Get)
+                        .url(url.clone())
+                        .build()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn sends_count_at_rate() {
+        let coll = collection(&[serve()]);
+        let options = BenchOptions {
+            concurrency: 3,
+            rate: Some(100.0),
+            limit: Limit::Count(10),
+        };
+
+        let mut ticks = 0;
+        let stats = bench(
+            &HttpClient::default(),
+            &coll,
+            &[0],
+            None,
+            &options,
+            Arc::default(),
+            |_| ticks += 1,
+        )
+        .await
+        .unwrap();
+
+        assert!(stats.finished);
+        assert_eq!(stats.requests, 10);
+        assert_eq!(stats.statuses.get(&200), Some(&10));
+        assert_eq!(stats.throughput.iter().sum::<u64>(), 10);
+        // The tenth request is scheduled 90ms after the first.
+        assert!(stats.elapsed >= Duration::from_millis(90));
+        assert!(stats.latency(0.5) <= stats.max_latency());
+        assert!(ticks >= 1);
+    }
+
+    #[tokio::test]
+    async fn counts_errors_and_stops() {
+        let base = serve();
+        let coll = collection(&[base, String::from("http://127.0.0.1:9/closed")]);
+        let options = BenchOptions {
+            concurrency: 1,
+            rate: None,
+            limit: Limit::Duration(Duration::from_secs(60)),
+        };
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let stats = bench(
+            &HttpClient::default(),
+            &coll,
+            &[0, 1],
+            None,
+            &options,
+            stop.clone(),
+            |stats| {
+                if stats.requests >= 4 {
+                    stop.store(true, Ordering::Relaxed);
+                }
+            },
+        )
+        .await
+        .unwrap();
+
+        assert!(stats.elapsed < Duration::from_secs(60));
+        assert_eq!(
+            stats.statuses.get(&200).copied(),
+            Some(stats.requests - stats.error_count())
+        );
+        assert!(stats.error_count() >= 2);
+        let summary = serde_json::to_value(stats.summary()).unwrap();
+        assert_eq!(summary["errors"], stats.error_count());
+    }
+
+    #[tokio::test]
+    async fn rejects_empty_selection() {
+        let options = BenchOptions {
+            concurrency: 1,
+            rate: None,
+            limit: Limit::Count(1),
+        };
+
+        assert!(bench(
+            &HttpClient::default(),
+            &Collection::default(),
+            &[],
+            None,
+            &options,
+            Arc::default(),
+            |_| {},
+        )
+        .await
+        .is_err());
+    }
+}
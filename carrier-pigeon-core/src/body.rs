@@ -0,0 +1,774 @@
+//! Response bodies for display: picking a format from the `Content-Type`, decoding the charset
+//! and pretty printing with highlighting, or a hex dump for binary bodies.
+
+use encoding_rs::{Encoding, UTF_8};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BodyFormat {
+    Json,
+    Xml,
+    Html,
+    Yaml,
+    Form,
+    Text,
+}
+
+impl fmt::Display for BodyFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Json => "json",
+            Self::Xml => "xml",
+            Self::Html => "html",
+            Self::Yaml => "yaml",
+            Self::Form => "form",
+            Self::Text => "text",
+        })
+    }
+}
+
+impl BodyFormat {
+    pub const ALL: [Self; 6] = [
+        Self::Json,
+        Self::Xml,
+        Self::Html,
+        Self::Yaml,
+        Self::Form,
+        Self::Text,
+    ];
+
+    /// Picks the format from the media type, looking at the body itself when the type is
+    /// missing or too generic, e.g. `text/plain` or `application/octet-stream`.
+    pub fn detect(content_type: Option<&str>, body: &[u8]) -> Self {
+        match media_type(content_type).as_str() {
+            media_type if media_type.contains("json") => Self::Json,
+            media_type if media_type.contains("html") => Self::Html,
+            media_type if media_type.contains("xml") => Self::Xml,
+            media_type if media_type.contains("yaml") || media_type.contains("yml") => Self::Yaml,
+            "application/x-www-form-urlencoded" => Self::Form,
+            _ => Self::sniff(body),
+        }
+    }
+
+    fn sniff(body: &[u8]) -> Self {
+        let start = String::from_utf8_lossy(&body[..body.len().min(256)])
+            .trim_start()
+            .to_ascii_lowercase();
+        if start.starts_with(['{', '['])
+            && serde_json::from_slice::<serde_json::Value>(body).is_ok()
+        {
+            Self::Json
+        } else if start.starts_with("<!doctype html") || start.starts_with("<html") {
+            Self::Html
+        } else if start.starts_with('<') {
+            Self::Xml
+        } else {
+            Self::Text
+        }
+    }
+
+    /// File extension for a body in this format, so editors pick the right syntax.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Xml => "xml",
+            Self::Html => "html",
+            Self::Yaml => "yaml",
+            Self::Form | Self::Text => "txt",
+        }
+    }
+
+    /// The next format, for cycling through them in the UI.
+    pub fn next(self) -> Self {
+        let idx = Self::ALL
+            .iter()
+            .position(|format| *format == self)
+            .unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+}
+
+/// The lowercase media type of a `Content-Type`, without its parameters.
+fn media_type(content_type: Option<&str>) -> String {
+    content_type
+        .and_then(|value| value.split(';').next())
+        .map(|media_type| media_type.trim().to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
+/// Decodes a body with the `charset` of its `Content-Type`, UTF-8 when there is none or it is
+/// unknown. Invalid sequences are replaced rather than failing.
+pub fn decode(body: &[u8], content_type: Option<&str>) -> String {
+    let encoding = content_type
+        .into_iter()
+        .flat_map(|value| value.split(';').skip(1))
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .and_then(|(_, label)| Encoding::for_label(label.trim().trim_matches('"').as_bytes()))
+        .unwrap_or(UTF_8);
+    encoding.decode(body).0.into_owned()
+}
+
+/// What a piece of pretty printed text is, for colouring it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Highlight {
+    Plain,
+    Punctuation,
+    Tag,
+    Attribute,
+    Key,
+    String,
+    Number,
+    Literal,
+    Comment,
+}
+
+pub type StyledLine = Vec<(Highlight, String)>;
+
+/// Pretty prints `text` as `format`. Text that doesn't parse as the format is shown as it is.
+pub fn pretty(format: BodyFormat, text: &str) -> Vec<StyledLine> {
+    match format {
+        BodyFormat::Json => match serde_json::from_str::<serde_json::Value>(text) {
+            Ok(value) => serde_json::to_string_pretty(&value)
+                .unwrap_or_default()
+                .lines()
+                .map(json_line)
+                .collect(),
+            Err(_) => plain(text),
+        },
+        BodyFormat::Xml => markup(text, false),
+        BodyFormat::Html => markup(text, true),
+        BodyFormat::Yaml => text.lines().map(yaml_line).collect(),
+        BodyFormat::Form => form_urlencoded::parse(text.trim().as_bytes())
+            .map(|(key, value)| {
+                vec![
+                    (Highlight::Key, key.into_owned()),
+                    (Highlight::Punctuation, String::from(" = ")),
+                    (Highlight::String, value.into_owned()),
+                ]
+            })
+            .collect(),
+        BodyFormat::Text => plain(text),
+    }
+}
+
+fn plain(text: &str) -> Vec<StyledLine> {
+    text.lines()
+        .map(|line| vec![(Highlight::Plain, line.to_string())])
+        .collect()
+}
+
+fn json_line(line: &str) -> StyledLine {
+    let mut styled = vec![];
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        let len = match c {
+            '"' => {
+                // Pretty printed JSON keeps strings on one line, so the end quote is on it too.
+                let mut escaped = false;
+                let end = rest[1..]
+                    .char_indices()
+                    .find(|(_, c)| {
+                        let end = *c == '"' && !escaped;
+                        escaped = *c == '\\' && !escaped;
+                        end
+                    })
+                    .map_or(rest.len(), |(idx, _)| idx + 2);
+                let kind = if rest[end..].starts_with(':') {
+                    Highlight::Key
+                } else {
+                    Highlight::String
+                };
+                styled.push((kind, rest[..end].to_string()));
+                end
+            }
+            c if c.is_whitespace() || "{}[]:,".contains(c) => {
+                let kind = if c.is_whitespace() {
+                    Highlight::Plain
+                } else {
+                    Highlight::Punctuation
+                };
+                styled.push((kind, c.to_string()));
+                c.len_utf8()
+            }
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || ",]}".contains(c))
+                    .unwrap_or(rest.len());
+                let kind = if rest.starts_with(['t', 'f', 'n']) {
+                    Highlight::Literal
+                } else {
+                    Highlight::Number
+                };
+                styled.push((kind, rest[..end].to_string()));
+                end
+            }
+        };
+        rest = &rest[len..];
+    }
+    styled
+}
+
+fn yaml_line(line: &str) -> StyledLine {
+    let content = line.trim_start();
+    let mut styled = vec![(
+        Highlight::Plain,
+        line[..line.len() - content.len()].to_string(),
+    )];
+    if content.starts_with('#') {
+        styled.push((Highlight::Comment, content.to_string()));
+        return styled;
+    }
+    if content == "---" || content == "..." {
+        styled.push((Highlight::Punctuation, content.to_string()));
+        return styled;
+    }
+    let mut rest = content;
+    while let Some(item) = rest.strip_prefix("- ") {
+        styled.push((Highlight::Punctuation, String::from("- ")));
+        rest = item;
+    }
+    let (rest, comment) = match rest.find(" #") {
+        Some(idx) => (&rest[..idx], Some(&rest[idx..])),
+        None => (rest, None),
+    };
+    let key_end = rest
+        .find(": ")
+        .or_else(|| rest.ends_with(':').then(|| rest.len() - 1))
+        .filter(|idx| !rest[..*idx].starts_with(['"', '\'', '{', '[']));
+    let value = match key_end {
+        Some(idx) => {
+            styled.push((Highlight::Key, rest[..idx].to_string()));
+            styled.push((Highlight::Punctuation, String::from(":")));
+            &rest[idx + 1..]
+        }
+        None => rest,
+    };
+    if !value.is_empty() {
+        let trimmed = value.trim();
+        let kind = match trimmed {
+            "" => Highlight::Plain,
+            "true" | "false" | "null" | "~" | "yes" | "no" => Highlight::Literal,
+            "|" | ">" | "|-" | ">-" => Highlight::Punctuation,
+            _ if trimmed.parse::<f64>().is_ok() => Highlight::Number,
+            _ => Highlight::String,
+        };
+        styled.push((kind, value.to_string()));
+    }
+    if let Some(comment) = comment {
+        styled.push((Highlight::Comment, comment.to_string()));
+    }
+    styled
+}
+
+#[derive(Debug, PartialEq)]
+enum Node<'a> {
+    Open {
+        name: &'a str,
+        raw: &'a str,
+    },
+    Close {
+        name: &'a str,
+        raw: &'a str,
+    },
+    /// A self closing tag, or an HTML void element like `<br>`.
+    Empty(&'a str),
+    Text(&'a str),
+    /// Comments, CDATA, doctypes and processing instructions.
+    Other(&'a str),
+}
+
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Splits XML or HTML into tags and text. This is meant for display, not validation, so it
+/// accepts anything and falls back to treating stray `<` as text.
+fn lex_markup(text: &str, html: bool) -> Vec<Node<'_>> {
+    let mut nodes = vec![];
+    let mut rest = text;
+    while !rest.is_empty() {
+        let tag_like = rest.starts_with('<')
+            && rest[1..].starts_with(|c: char| c.is_alphabetic() || "/!?".contains(c));
+        if !tag_like {
+            let first = rest.chars().next().map_or(1, char::len_utf8);
+            let end = rest[first..].find('<').map_or(rest.len(), |idx| idx + first);
+            nodes.push(Node::Text(&rest[..end]));
+            rest = &rest[end..];
+            continue;
+        }
+
+        let end_marker = if rest.starts_with("<!--") {
+            "-->"
+        } else if rest.starts_with("<![CDATA[") {
+            "]]>"
+        } else {
+            ">"
+        };
+        let end = find_tag_end(rest, end_marker);
+        let raw = &rest[..end];
+        rest = &rest[end..];
+
+        let node = if raw.starts_with("<!") || raw.starts_with("<?") {
+            Node::Other(raw)
+        } else if raw.starts_with("</") {
+            Node::Close {
+                name: name_of(raw),
+                raw,
+            }
+        } else if raw.ends_with("/>")
+            || (html && VOID_ELEMENTS.contains(&name_of(raw).to_ascii_lowercase().as_str()))
+        {
+            Node::Empty(raw)
+        } else {
+            Node::Open {
+                name: name_of(raw),
+                raw,
+            }
+        };
+
+        // The contents of scripts and styles aren't markup.
+        if let Node::Open { name, .. } = node {
+            if html && (name.eq_ignore_ascii_case("script") || name.eq_ignore_ascii_case("style")) {
+                let close = format!("</{}", name.to_ascii_lowercase());
+                let end = rest.to_ascii_lowercase().find(&close).unwrap_or(rest.len());
+                nodes.push(node);
+                nodes.push(Node::Text(&rest[..end]));
+                rest = &rest[end..];
+                continue;
+            }
+        }
+        nodes.push(node);
+    }
+    nodes
+}
+
+/// The element name of a start or end tag.
+fn name_of(raw: &str) -> &str {
+    raw.trim_start_matches(['<', '/'])
+        .split(|c: char| c.is_whitespace() || c == '/' || c == '>')
+        .next()
+        .unwrap_or_default()
+}
+
+/// Index just past the end of the tag starting `rest`, skipping `>` inside quoted values.
+fn find_tag_end(rest: &str, end_marker: &str) -> usize {
+    if end_marker != ">" {
+        return rest
+            .find(end_marker)
+            .map_or(rest.len(), |idx| idx + end_marker.len());
+    }
+    let mut quote = None;
+    for (idx, c) in rest.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), c) if c == open => quote = None,
+            (None, '>') => return idx + 1,
+            _ => {}
+        }
+    }
+    rest.len()
+}
+
+fn markup(text: &str, html: bool) -> Vec<StyledLine> {
+    let nodes = lex_markup(text, html);
+    let mut lines = vec![];
+    let mut depth = 0;
+    let indent = |depth: usize| (Highlight::Plain, "  ".repeat(depth));
+    let mut idx = 0;
+    while idx < nodes.len() {
+        match &nodes[idx] {
+            Node::Open { name, raw } => {
+                // Elements holding only a line of text, or nothing, stay on one line.
+                let (text, close) = match (nodes.get(idx + 1), nodes.get(idx + 2)) {
+                    (Some(Node::Close { name: close, raw }), _) if close == name => {
+                        (None, Some((raw, 1)))
+                    }
+                    (Some(Node::Text(text)), Some(Node::Close { name: close, raw }))
+                        if close == name && !text.trim().contains('\n') =>
+                    {
+                        (Some(text.trim()), Some((raw, 2)))
+                    }
+                    _ => (None, None),
+                };
+                let mut line = vec![indent(depth)];
+                line.extend(tag(raw));
+                match close {
+                    Some((close, skip)) => {
+                        if let Some(text) = text {
+                            line.push((Highlight::Plain, text.to_string()));
+                        }
+                        line.extend(tag(close));
+                        idx += skip;
+                    }
+                    None => depth += 1,
+                }
+                lines.push(line);
+            }
+            Node::Close { raw, .. } => {
+                depth = depth.saturating_sub(1);
+                let mut line = vec![indent(depth)];
+                line.extend(tag(raw));
+                lines.push(line);
+            }
+            Node::Empty(raw) => {
+                let mut line = vec![indent(depth)];
+                line.extend(tag(raw));
+                lines.push(line);
+            }
+            Node::Text(text) => {
+                for text_line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+                    lines.push(vec![
+                        indent(depth),
+                        (Highlight::Plain, text_line.to_string()),
+                    ]);
+                }
+            }
+            Node::Other(raw) => {
+                for (line_idx, raw_line) in raw.lines().enumerate() {
+                    let raw_line = if line_idx == 0 {
+                        raw_line
+                    } else {
+                        raw_line.trim()
+                    };
+                    lines.push(vec![
+                        indent(depth),
+                        (Highlight::Comment, raw_line.to_string()),
+                    ]);
+                }
+            }
+        }
+        idx += 1;
+    }
+    lines
+}
+
+/// Highlights a start, end or self closing tag.
+fn tag(raw: &str) -> StyledLine {
+    let open = if raw.starts_with("</") { "</" } else { "<" };
+    let close = if raw.ends_with("/>") { "/>" } else { ">" };
+    let inner = raw
+        .strip_prefix(open)
+        .and_then(|inner| inner.strip_suffix(close))
+        .unwrap_or(raw);
+    let name_end = inner.find(char::is_whitespace).unwrap_or(inner.len());
+
+    let mut styled = vec![
+        (Highlight::Punctuation, open.to_string()),
+        (Highlight::Tag, inner[..name_end].to_string()),
+    ];
+    let mut rest = &inner[name_end..];
+    while !rest.is_empty() {
+        let trimmed = rest.trim_start();
+        if trimmed.len() < rest.len() {
+            styled.push((Highlight::Plain, String::from(" ")));
+            rest = trimmed;
+            continue;
+        }
+        let name_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        styled.push((Highlight::Attribute, rest[..name_end].to_string()));
+        rest = &rest[name_end..];
+        if let Some(value) = rest.strip_prefix('=') {
+            styled.push((Highlight::Punctuation, String::from("=")));
+            let value_end = match value.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    value[1..].find(quote).map_or(value.len(), |idx| idx + 2)
+                }
+                _ => value.find(char::is_whitespace).unwrap_or(value.len()),
+            };
+            styled.push((Highlight::String, value[..value_end].to_string()));
+            rest = &value[value_end..];
+        }
+    }
+    styled.push((Highlight::Punctuation, close.to_string()));
+    styled
+}
+
+/// Media types of common binary formats, by the bytes their files start with.
+const MAGIC_NUMBERS: [(&[u8], &str); 9] = [
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\0asm", "application/wasm"),
+    (b"\x7fELF", "application/x-elf"),
+];
+
+/// Whether a body should be shown as a hex dump rather than text. Textual media types never
+/// are, other bodies are when they look like a known binary format or aren't valid UTF-8.
+pub fn is_binary(content_type: Option<&str>, body: &[u8]) -> bool {
+    let media_type = media_type(content_type);
+    let textual = media_type.starts_with("text/")
+        || ["json", "xml", "yaml", "javascript", "x-www-form-urlencoded"]
+            .iter()
+            .any(|text| media_type.contains(text));
+    if textual {
+        return false;
+    }
+    let start = &body[..body.len().min(1024)];
+    sniff_mime(body).is_some()
+        || start.contains(&0)
+        || std::str::from_utf8(start).is_err_and(|err| err.error_len().is_some())
+}
+
+fn sniff_mime(body: &[u8]) -> Option<&'static str> {
+    if body.starts_with(b"RIFF") && body.get(8..12) == Some(b"WEBP") {
+        return Some("image/webp");
+    }
+    MAGIC_NUMBERS
+        .iter()
+        .find(|(magic, _)| body.starts_with(magic))
+        .map(|(_, mime)| *mime)
+}
+
+/// The MIME type of a body, detected from its contents where possible since servers often send
+/// `application/octet-stream` for anything binary.
+pub fn mime_type(content_type: Option<&str>, body: &[u8]) -> String {
+    match (sniff_mime(body), media_type(content_type)) {
+        (Some(mime), _) => mime.to_string(),
+        (None, media_type) if !media_type.is_empty() => media_type,
+        (None, _) => String::from("application/octet-stream"),
+    }
+}
+
+/// Bytes shown on each line of a hex dump.
+pub const HEX_WIDTH: usize = 16;
+
+/// Number of lines in the hex dump of a body of `len` bytes.
+pub fn hex_lines(len: usize) -> usize {
+    len.div_ceil(HEX_WIDTH)
+}
+
+/// A line of a hex dump, like `hexdump -C`: the offset, up to [`HEX_WIDTH`] bytes in hex and the
+/// printable ones as ASCII.
+pub fn hex_line(offset: usize, bytes: &[u8]) -> StyledLine {
+    let hex = (0..HEX_WIDTH)
+        .map(|idx| {
+            let gap = if idx == HEX_WIDTH / 2 { " " } else { "" };
+            match bytes.get(idx) {
+                Some(byte) => format!("{} {:02x}", gap, byte),
+                None => format!("{}   ", gap),
+            }
+        })
+        .collect::<String>();
+    let ascii = bytes
+        .iter()
+        .map(|byte| match byte {
+            b' '..=b'~' => *byte as char,
+            _ => '.',
+        })
+        .collect::<String>();
+    vec![
+        (Highlight::Comment, format!("{:08x}", offset)),
+        (Highlight::Number, format!(" {} ", hex)),
+        (Highlight::Punctuation, String::from("|")),
+        (Highlight::String, ascii),
+        (Highlight::Punctuation, String::from("|")),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(lines: &[StyledLine]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| line.iter().map(|(_, text)| text.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn detects_formats() {
+        let detect = |content_type, body: &str| BodyFormat::detect(content_type, body.as_bytes());
+
+        assert_eq!(
+            detect(Some("application/problem+json"), ""),
+            BodyFormat::Json
+        );
+        assert_eq!(
+            detect(Some("application/soap+xml; charset=utf-8"), ""),
+            BodyFormat::Xml
+        );
+        assert_eq!(detect(Some("text/html"), ""), BodyFormat::Html);
+        assert_eq!(detect(Some("application/x-yaml"), ""), BodyFormat::Yaml);
+        assert_eq!(
+            detect(Some("application/x-www-form-urlencoded"), ""),
+            BodyFormat::Form
+        );
+        assert_eq!(detect(Some("text/plain"), r#"{"a": 1}"#), BodyFormat::Json);
+        assert_eq!(detect(None, "  <!DOCTYPE html><html>"), BodyFormat::Html);
+        assert_eq!(detect(None, "<?xml version=\"1.0\"?>"), BodyFormat::Xml);
+        assert_eq!(detect(None, "{ not json"), BodyFormat::Text);
+        assert_eq!(BodyFormat::Text.next(), BodyFormat::Json);
+    }
+
+    #[test]
+    fn decodes_charsets() {
+        assert_eq!(
+            decode(b"caf\xe9", Some("text/plain; charset=ISO-8859-1")),
+            "café"
+        );
+        assert_eq!(
+            decode(
+                b"\x93\xfa\x96\x7b",
+                Some("text/plain;charset=\"Shift_JIS\"")
+            ),
+            "日本"
+        );
+        assert_eq!(decode("café".as_bytes(), Some("text/plain")), "café");
+        assert_eq!(decode(b"caf\xe9", None), "caf\u{fffd}");
+    }
+
+    #[test]
+    fn detects_binary_bodies() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+        assert!(is_binary(Some("application/octet-stream"), png));
+        assert!(is_binary(None, b"\x08\x96\x01"));
+        assert!(is_binary(
+            Some("application/x-protobuf"),
+            b"\x0a\x03\xff\xfe"
+        ));
+        assert!(!is_binary(
+            Some("text/plain; charset=ISO-8859-1"),
+            b"caf\xe9"
+        ));
+        assert!(!is_binary(None, "{\"name\": \"café\"}".as_bytes()));
+        assert_eq!(
+            mime_type(Some("application/octet-stream"), png),
+            "image/png"
+        );
+        assert_eq!(
+            mime_type(Some("application/x-protobuf"), b"\x08"),
+            "application/x-protobuf"
+        );
+        assert_eq!(mime_type(None, b"\x08"), "application/octet-stream");
+    }
+
+    #[test]
+    fn dumps_hex() {
+        let body = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\x01";
+
+        assert_eq!(hex_lines(body.len()), 2);
+        assert_eq!(
+            text(
+                &body
+                    .chunks(HEX_WIDTH)
+                    .enumerate()
+                    .map(|(line, bytes)| hex_line(line * HEX_WIDTH, bytes))
+                    .collect::<Vec<_>>()
+            ),
+            vec![
+                "00000000  89 50 4e 47 0d 0a 1a 0a  00 00 00 0d 49 48 44 52 |.PNG........IHDR|",
+                "00000010  00 00 01                                         |...|",
+            ]
+        );
+    }
+
+    #[test]
+    fn pretty_prints_json_in_key_order() {
+        let json = r#"{"name":"Rex","id":7,"tags":{"z":1,"a":2}}"#;
+
+        assert_eq!(
+            text(&pretty(BodyFormat::Json, json)),
+            vec![
+                "{",
+                r#"  "name": "Rex","#,
+                r#"  "id": 7,"#,
+                r#"  "tags": {"#,
+                r#"    "z": 1,"#,
+                r#"    "a": 2"#,
+                "  }",
+                "}",
+            ]
+        );
+    }
+
+    #[test]
+    fn pretty_prints_xml() {
+        let xml = r#"<?xml version="1.0"?><soap:Envelope xmlns:soap="urn:x"><soap:Body><Pet id='7'><Name>Rex</Name><Tags/><!-- none --></Pet></soap:Body></soap:Envelope>"#;
+
+        assert_eq!(
+            text(&pretty(BodyFormat::Xml, xml)),
+            vec![
+                r#"<?xml version="1.0"?>"#,
+                r#"<soap:Envelope xmlns:soap="urn:x">"#,
+                "  <soap:Body>",
+                "    <Pet id='7'>",
+                "      <Name>Rex</Name>",
+                "      <Tags/>",
+                "      <!-- none -->",
+                "    </Pet>",
+                "  </soap:Body>",
+                "</soap:Envelope>",
+            ]
+        );
+        let pet = &pretty(BodyFormat::Xml, xml)[3];
+        assert!(pet.contains(&(Highlight::Attribute, "id".into())));
+        assert!(pet.contains(&(Highlight::String, "'7'".into())));
+    }
+
+    #[test]
+    fn pretty_prints_html() {
+        let html = "<html><head><meta charset=utf-8><script>if (a < b) {}</script></head><body><p>Not <b>found</b></p><br></body></html>";
+
+        assert_eq!(
+            text(&pretty(BodyFormat::Html, html)),
+            vec![
+                "<html>",
+                "  <head>",
+                "    <meta charset=utf-8>",
+                "    <script>if (a < b) {}</script>",
+                "  </head>",
+                "  <body>",
+                "    <p>",
+                "      Not",
+                "      <b>found</b>",
+                "    </p>",
+                "    <br>",
+                "  </body>",
+                "</html>",
+            ]
+        );
+    }
+
+    #[test]
+    fn pretty_prints_non_ascii_text() {
+        assert_eq!(
+            text(&pretty(BodyFormat::Html, "<p>¡Hola!</p>")),
+            vec!["<p>¡Hola!</p>"]
+        );
+        assert_eq!(
+            text(&pretty(BodyFormat::Xml, "<a>日本</a>")),
+            vec!["<a>日本</a>"]
+        );
+    }
+
+    #[test]
+    fn highlights_yaml_form_and_json() {
+        let yaml = pretty(
+            BodyFormat::Yaml,
+            "# pets\npets:\n  - name: Rex # good\n    age: 3\n",
+        );
+        assert_eq!(yaml[0][1], (Highlight::Comment, "# pets".into()));
+        assert_eq!(yaml[2][2], (Highlight::Key, "name".into()));
+        assert_eq!(yaml[2][5], (Highlight::Comment, " # good".into()));
+        assert_eq!(yaml[3][3], (Highlight::Number, " 3".into()));
+
+        let form = pretty(BodyFormat::Form, "name=Rex+the+dog&tag=a%26b");
+        assert_eq!(text(&form), vec!["name = Rex the dog", "tag = a&b"]);
+
+        let json = pretty(BodyFormat::Json, r#"{"a":"x\"y","b":[1,true]}"#);
+        assert_eq!(json[1][2], (Highlight::Key, "\"a\"".into()));
+        assert_eq!(json[1][5], (Highlight::String, "\"x\\\"y\"".into()));
+        assert_eq!(text(&json)[3], "    1,");
+        assert!(json[4].contains(&(Highlight::Literal, "true".into())));
+    }
+}
@@ -0,0 +1,451 @@
+use crate::{executor::ExecuteOptions, history::Retention, Request, Variables};
+
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::PathBuf};
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum EnvironmentValue {
+    Secret(String),
+    Value(String),
+}
+
+impl EnvironmentValue {
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Secret(value) | Self::Value(value) => value,
+        }
+    }
+}
+
+pub type EnvironmentValues = HashMap<String, EnvironmentValue>;
+
+/// What secrets are replaced with when a response or report is written out.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Replaces each of `secrets`, as given by [`Collection::secrets`], in `text`.
+pub fn redact(text: &str, secrets: &[String]) -> String {
+    secrets.iter().fold(text.to_string(), |text, secret| {
+        text.replace(secret, REDACTED)
+    })
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Environment {
+    pub name: String,
+    pub values: EnvironmentValues,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Collection {
+    pub requests: Vec<Request>,
+    pub environments: Vec<Environment>,
+    /// Values shared by every environment, environment values take precedence.
+    #[serde(default)]
+    pub variables: EnvironmentValues,
+    /// Rhai script run before every request in the collection, ahead of the request's own.
+    #[serde(default)]
+    pub pre_request_script: Option<String>,
+    #[serde(default)]
+    pub settings: Settings,
+    #[serde(skip_serializing)]
+    pub save_location: Option<PathBuf>,
+}
+
+/// Preferences stored with the collection, in its `settings` file.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Settings {
+    #[serde(default)]
+    pub history: Retention,
+    #[serde(default)]
+    pub executor: ExecuteOptions,
+}
+
+pub struct SerializedCollection {
+    pub requests: HashMap<Box<str>, Box<[u8]>>,
+    /// JSON list of the request keys in collection order.
+    pub order: Option<Box<[u8]>>,
+    pub environments: HashMap<Box<str>, Box<[u8]>>,
+    pub variables: Option<Box<[u8]>>,
+    pub pre_request_script: Option<Box<[u8]>>,
+    pub settings: Option<Box<[u8]>>,
+}
+
+impl Collection {
+    /// Collection variables overlaid with the values of the named environment.
+    pub fn variables_for(&self, env_name: Option<&str>) -> Variables {
+        let env = self
+            .environments
+            .iter()
+            .find(|env| Some(env.name.as_str()) == env_name);
+        self.variables
+            .iter()
+            .chain(env.into_iter().flat_map(|env| env.values.iter()))
+            .map(|(key, value)| (key.clone(), value.value().to_string()))
+            .collect()
+    }
+
+    /// The secret values visible to requests run in the named environment. They are ordered
+    /// longest first, so a secret containing another is not left partly visible by [`redact`].
+    pub fn secrets(&self, env_name: Option<&str>) -> Vec<String> {
+        let env = self
+            .environments
+            .iter()
+            .find(|env| Some(env.name.as_str()) == env_name);
+        let mut secrets: Vec<String> = self
+            .variables
+            .values()
+            .chain(env.into_iter().flat_map(|env| env.values.values()))
+            .filter_map(|value| match value {
+                EnvironmentValue::Secret(secret) if !secret.is_empty() => Some(secret.clone()),
+                _ => None,
+            })
+            .collect();
+        secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+        secrets
+    }
+
+    /// Sets a variable in the named environment, or the collection variables when there is none.
+    /// Secret values stay secret when they are overwritten.
+    pub fn set_variable(&mut self, env_name: Option<&str>, key: String, value: String) {
+        let values = match self
+            .environments
+            .iter_mut()
+            .find(|env| Some(env.name.as_str()) == env_name)
+        {
+            Some(env) => &mut env.values,
+            None => &mut self.variables,
+        };
+        let value = match values.get(&key) {
+            Some(EnvironmentValue::Secret(_)) => EnvironmentValue::Secret(value),
+            _ => EnvironmentValue::Value(value),
+        };
+        values.insert(key, value);
+    }
+
+    /// Applies the variables that changed from `before` to `after`, as when a collection run
+    /// finishes, leaving values the run didn't touch as they are now. Returns whether any
+    /// value changed.
+    pub fn merge_variables(&mut self, before: &Collection, after: &Collection) -> bool {
+        let mut changed = merge_values(&mut self.variables, &before.variables, &after.variables);
+        let none = EnvironmentValues::new();
+        for env in &after.environments {
+            let Some(values) = self
+                .environments
+                .iter_mut()
+                .find(|existing| existing.name == env.name)
+                .map(|existing| &mut existing.values)
+            else {
+                continue;
+            };
+            let start = before
+                .environments
+                .iter()
+                .find(|start| start.name == env.name)
+                .map_or(&none, |start| &start.values);
+            changed |= merge_values(values, start, &env.values);
+        }
+        changed
+    }
+
+    pub fn serialize(&self) -> SerializedCollection {
+        let mut order = vec![];
+        let requests: HashMap<Box<str>, Box<[u8]>> =
+            self.requests.iter().fold(HashMap::new(), |mut reqs, req| {
+                if let Ok(ser_req) = serde_json::to_vec(req) {
+                    let key = file_key(&reqs, &req.name);
+                    order.push(key.clone());
+                    reqs.insert(key, ser_req.into_boxed_slice());
+                }
+                reqs
+            });
+
+        let environments: HashMap<Box<str>, Box<[u8]>> =
+            self.environments
+                .iter()
+                .fold(HashMap::new(), |mut envs, env| {
+                    if let Ok(ser_env) = serde_json::to_vec(&env.values) {
+                        envs.insert(
+                            env.name.clone().into_boxed_str(),
+                            ser_env.into_boxed_slice(),
+                        );
+                    }
+                    envs
+                });
+
+        let variables = if self.variables.is_empty() {
+            None
+        } else {
+            serde_json::to_vec(&self.variables)
+                .ok()
+                .map(Vec::into_boxed_slice)
+        };
+
+        SerializedCollection {
+            requests,
+            order: serde_json::to_vec(&order).ok().map(Vec::into_boxed_slice),
+            environments,
+            variables,
+            pre_request_script: self
+                .pre_request_script
+                .as_ref()
+                .map(|script| script.as_bytes().into()),
+            settings: serde_json::to_vec_pretty(&self.settings)
+                .ok()
+                .map(Vec::into_boxed_slice),
+        }
+    }
+
+    pub fn deserialize(save_location: PathBuf, ser_coll: SerializedCollection) -> Self {
+        // Requests missing from the order, e.g. added by hand, go last in name order.
+        let mut keys: Vec<&Box<str>> = ser_coll.requests.keys().collect();
+        keys.sort();
+        let order: Vec<Box<str>> = ser_coll
+            .order
+            .as_ref()
+            .and_then(|order| serde_json::from_slice(order).ok())
+            .unwrap_or_default();
+        keys.sort_by_key(|key| {
+            order
+                .iter()
+                .position(|ordered| ordered == *key)
+                .unwrap_or(usize::MAX)
+        });
+
+        let requests: Vec<Request> = keys
+            .into_iter()
+            .filter_map(|key| {
+                serde_json::from_slice(
+                    ser_coll
+                        .requests
+                        .get(key)
+                        .expect("Failed to retrieve from map inside key iterator"),
+                )
+                .ok()
+            })
+            .collect();
+        let environments: Vec<Environment> = ser_coll
+            .environments
+            .keys()
+            .filter_map(|key| {
+                if let Ok(values) = serde_json::from_slice(
+                    ser_coll
+                        .environments
+                        .get(key)
+                        .expect("Failed to retrieve from map inside key iterator"),
+                ) {
+                    Some(Environment {
+                        name: key.to_string(),
+                        values,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect();
+        let variables = ser_coll
+            .variables
+            .and_then(|vars| serde_json::from_slice(&vars).ok())
+            .unwrap_or_default();
+
+        let pre_request_script = ser_coll
+            .pre_request_script
+            .map(|script| String::from_utf8_lossy(&script).into_owned());
+
+        let settings = ser_coll
+            .settings
+            .and_then(|settings| serde_json::from_slice(&settings).ok())
+            .unwrap_or_default();
+
+        Collection {
+            requests,
+            environments,
+            variables,
+            pre_request_script,
+            settings,
+            save_location: Some(save_location),
+        }
+    }
+}
+
+/// Applies the changes from `before` to `after` onto `values`, returns whether any was made.
+fn merge_values(
+    values: &mut EnvironmentValues,
+    before: &EnvironmentValues,
+    after: &EnvironmentValues,
+) -> bool {
+    let mut changed = false;
+    for (key, value) in after {
+        if before.get(key) != Some(value) && values.get(key) != Some(value) {
+            values.insert(key.clone(), value.clone());
+            changed = true;
+        }
+    }
+    for key in before.keys().filter(|key| !after.contains_key(*key)) {
+        changed |= values.remove(key).is_some();
+    }
+    changed
+}
+
+/// Request names are used as file names, so path separators are replaced and duplicates numbered.
+fn file_key(existing: &HashMap<Box<str>, Box<[u8]>>, name: &str) -> Box<str> {
+    let base = name.replace(['/', '\\'], "_");
+    let mut key = base.clone();
+    let mut count = 1;
+    while existing.contains_key(key.as_str()) {
+        count += 1;
+        key = format!("{} ({})", base, count);
+    }
+    key.into_boxed_str()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Method;
+
+    #[test]
+    fn serialization_round_trip() {
+        let mut values = EnvironmentValues::new();
+        values.insert("host".into(), EnvironmentValue::Value("localhost".into()));
+        values.insert("token".into(), EnvironmentValue::Secret("abc".into()));
+        let mut variables = EnvironmentValues::new();
+        variables.insert("version".into(), EnvironmentValue::Value("v1".into()));
+
+        let coll = Collection {
+            requests: vec![Request::builder()
+                .name("Ping".into())
+                .method(Method::Get)
+                .url("{{host}}/ping".into())
+                .build()],
+            environments: vec![Environment {
+                name: "Local".into(),
+                values,
+            }],
+            variables,
+            pre_request_script: Some("env.nonce = nonce(8);".into()),
+            settings: Settings {
+                history: Retention {
+                    max_entries: Some(50),
+                    max_age_days: None,
+                },
+                executor: ExecuteOptions {
+                    memory_limit: 1024,
+                    probe_connection: false,
+                    decompress: false,
+                },
+            },
+            save_location: None,
+        };
+
+        let loaded = Collection::deserialize(PathBuf::from(".pigeon"), coll.serialize());
+
+        assert_eq!(loaded.requests, coll.requests);
+        assert_eq!(loaded.environments, coll.environments);
+        assert_eq!(loaded.variables, coll.variables);
+        assert_eq!(loaded.pre_request_script, coll.pre_request_script);
+        assert_eq!(loaded.settings, coll.settings);
+        assert_eq!(loaded.save_location, Some(PathBuf::from(".pigeon")));
+    }
+
+    #[test]
+    fn set_variable_keeps_secrets() {
+        let mut values = EnvironmentValues::new();
+        values.insert("token".into(), EnvironmentValue::Secret("old".into()));
+        let mut coll = Collection {
+            environments: vec![Environment {
+                name: "Local".into(),
+                values,
+            }],
+            ..Default::default()
+        };
+
+        coll.set_variable(Some("Local"), "token".into(), "new".into());
+        coll.set_variable(None, "nonce".into(), "abc".into());
+
+        assert_eq!(
+            coll.environments[0].values.get("token"),
+            Some(&EnvironmentValue::Secret("new".into()))
+        );
+        assert_eq!(
+            coll.variables.get("nonce"),
+            Some(&EnvironmentValue::Value("abc".into()))
+        );
+    }
+
+    #[test]
+    fn request_order_is_kept() {
+        let req = |name: &str| {
+            Request::builder()
+                .name(name.into())
+                .method(Method::Get)
+                .url("/".into())
+                .build()
+        };
+        let coll = Collection {
+            requests: ["Login", "Create Pet", "Get Pet", "Logout"]
+                .into_iter()
+                .map(req)
+                .collect(),
+            ..Default::default()
+        };
+        let mut ser_coll = coll.serialize();
+        ser_coll.requests.insert(
+            "Added".into(),
+            serde_json::to_vec(&req("Added")).unwrap().into(),
+        );
+
+        let loaded = Collection::deserialize(PathBuf::from(".pigeon"), ser_coll);
+
+        let names: Vec<&str> = loaded
+            .requests
+            .iter()
+            .map(|req| req.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["Login", "Create Pet", "Get Pet", "Logout", "Added"]
+        );
+    }
+
+    #[test]
+    fn request_file_names() {
+        let req = |name: &str| {
+            Request::builder()
+                .name(name.into())
+                .method(Method::Get)
+                .url("/".into())
+                .build()
+        };
+        let coll = Collection {
+            requests: vec![req("GET /pets"), req("GET /pets"), req("Ping")],
+            ..Default::default()
+        };
+
+        let mut keys: Vec<Box<str>> = coll.serialize().requests.into_keys().collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec!["GET _pets".into(), "GET _pets (2)".into(), "Ping".into()]
+        );
+    }
+
+    #[test]
+    fn merges_only_changed_variables() {
+        let value = |value: &str| EnvironmentValue::Value(value.into());
+        let mut before = Collection::default();
+        before.variables.insert("token".into(), value("old"));
+        before.variables.insert("user".into(), value("7"));
+        before.variables.insert("nonce".into(), value("abc"));
+        let mut after = before.clone();
+        after.variables.insert("token".into(), value("new"));
+        after.variables.remove("nonce");
+        let mut coll = before.clone();
+        coll.variables.insert("user".into(), value("8"));
+
+        assert!(coll.merge_variables(&before, &after));
+        assert_eq!(coll.variables.get("token"), Some(&value("new")));
+        assert_eq!(coll.variables.get("user"), Some(&value("8")));
+        assert_eq!(coll.variables.get("nonce"), None);
+        assert!(!coll.merge_variables(&before, &after));
+    }
+}
@@ -0,0 +1,168 @@
+//! Content codings for compressing request bodies and decoding response bodies.
+
+use color_eyre::eyre::{eyre, Report};
+use flate2::{
+    bufread::{DeflateDecoder, MultiGzDecoder, ZlibDecoder},
+    write::{GzEncoder, ZlibEncoder},
+    Compression,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Display,
+    io::{self, BufRead, BufReader, Read, Write},
+    str::FromStr,
+};
+
+/// The `Accept-Encoding` sent when response bodies are decoded.
+pub const ACCEPT_ENCODING: &str = "gzip, deflate, br, zstd";
+
+/// A `Content-Encoding` the executor can apply and undo.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    Gzip,
+    Deflate,
+    #[serde(rename = "br")]
+    Brotli,
+    Zstd,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 4] = [
+        Encoding::Gzip,
+        Encoding::Deflate,
+        Encoding::Brotli,
+        Encoding::Zstd,
+    ];
+
+    /// The coding's name as it appears in `Content-Encoding`.
+    pub fn token(&self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+            Encoding::Brotli => "br",
+            Encoding::Zstd => "zstd",
+        }
+    }
+
+    /// The encoding a `Content-Encoding` value names. Identity, unknown codings and several
+    /// codings stacked on top of each other give `None`.
+    pub fn from_header(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(Encoding::Gzip),
+            "deflate" => Some(Encoding::Deflate),
+            "br" => Some(Encoding::Brotli),
+            "zstd" => Some(Encoding::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn compress(&self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(vec![], Compression::default());
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            }
+            Encoding::Deflate => {
+                let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            }
+            Encoding::Brotli => {
+                let mut compressed = vec![];
+                {
+                    let mut encoder = brotli::CompressorWriter::new(&mut compressed, 4096, 9, 22);
+                    encoder.write_all(bytes)?;
+                }
+                Ok(compressed)
+            }
+            Encoding::Zstd => zstd::encode_all(bytes, 0),
+        }
+    }
+
+    /// Wraps `reader` in a decoder for this encoding.
+    pub fn decoder<'a>(&self, reader: impl Read + 'a) -> io::Result<Box<dyn Read + 'a>> {
+        let mut reader = BufReader::new(reader);
+        Ok(match self {
+            Encoding::Gzip => Box::new(MultiGzDecoder::new(reader)),
+            // `deflate` is meant to be zlib wrapped, but some servers send a bare deflate stream.
+            Encoding::Deflate if is_zlib(reader.fill_buf()?) => Box::new(ZlibDecoder::new(reader)),
+            Encoding::Deflate => Box::new(DeflateDecoder::new(reader)),
+            Encoding::Brotli => Box::new(brotli::Decompressor::new(reader, 4096)),
+            Encoding::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(reader)?),
+        })
+    }
+
+    pub fn decompress(&self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        let mut decoded = vec![];
+        self.decoder(bytes)?.read_to_end(&mut decoded)?;
+        Ok(decoded)
+    }
+}
+
+/// Whether `bytes` start with a zlib header, see RFC 1950.
+fn is_zlib(bytes: &[u8]) -> bool {
+    match bytes {
+        [cmf, flg, ..] => cmf & 0x0f == 8 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0,
+        _ => false,
+    }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.token())
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Encoding::from_header(s).ok_or_else(|| {
+            eyre!(
+                "Unknown encoding: {}, expected one of gzip, deflate, br or zstd",
+                s
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_every_encoding() {
+        let body = br#"{"pets":["Rex","Tom","Rex","Tom","Rex","Tom"]}"#;
+
+        for encoding in Encoding::ALL {
+            let compressed = encoding.compress(body).unwrap();
+            assert_ne!(compressed, body, "{}", encoding);
+            assert_eq!(
+                encoding.decompress(&compressed).unwrap(),
+                body,
+                "{}",
+                encoding
+            );
+        }
+    }
+
+    #[test]
+    fn decodes_bare_deflate() {
+        let mut encoder = flate2::write::DeflateEncoder::new(vec![], Compression::default());
+        encoder.write_all(b"hello").unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        assert_eq!(Encoding::Deflate.decompress(&compressed).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn parses_header_values() {
+        assert_eq!(Encoding::from_header(" GZIP"), Some(Encoding::Gzip));
+        assert_eq!(Encoding::from_header("br"), Some(Encoding::Brotli));
+        assert_eq!(Encoding::from_header("identity"), None);
+        assert_eq!(Encoding::from_header("gzip, br"), None);
+        assert!("lz4".parse::<Encoding>().is_err());
+    }
+}
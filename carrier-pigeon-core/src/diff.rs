@@ -0,0 +1,268 @@
+//! Differences between two responses, for comparing an execution against an earlier one or a
+//! saved [`crate::Example`].
+
+use color_eyre::Result;
+use serde_json::Value;
+use similar::{ChangeTag, TextDiff};
+use std::{
+    fmt::Write,
+    time::{Duration, Instant},
+};
+
+use crate::{body, Response};
+
+/// How long the body diff may take. Past it the rest of the bodies is compared more coarsely,
+/// so changes may show as larger than they are rather than the diff taking minutes.
+const DEADLINE: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Change {
+    Equal,
+    Removed,
+    Added,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffLine {
+    pub change: Change,
+    pub text: String,
+}
+
+/// A header that differs, `None` on the side it is missing from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeaderChange {
+    pub name: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResponseDiff {
+    pub old_status: u16,
+    pub new_status: u16,
+    pub headers: Vec<HeaderChange>,
+    /// Whether both bodies are JSON and were compared with their keys sorted.
+    pub structural: bool,
+    pub body: Vec<DiffLine>,
+}
+
+impl ResponseDiff {
+    /// Compares `new` against `old`. JSON bodies are compared after sorting their keys and
+    /// formatting them the same way, so only changes to the values show up. Bodies are decoded
+    /// with their charset first. Fails when either body was spilled to disk.
+    pub fn new(old: &Response, new: &Response) -> Result<Self> {
+        let text = |res: &Response| -> Result<String> {
+            Ok(body::decode(
+                res.complete_body()?,
+                res.header("content-type"),
+            ))
+        };
+        let (old_text, new_text) = (text(old)?, text(new)?);
+        let (old_body, new_body, structural) = match (json_lines(&old_text), json_lines(&new_text))
+        {
+            (Some(old), Some(new)) => (old, new, true),
+            _ => (old_text, new_text, false),
+        };
+        let body = TextDiff::configure()
+            .deadline(Instant::now() + DEADLINE)
+            .diff_lines(&old_body, &new_body)
+            .iter_all_changes()
+            .map(|change| DiffLine {
+                change: match change.tag() {
+                    ChangeTag::Equal => Change::Equal,
+                    ChangeTag::Delete => Change::Removed,
+                    ChangeTag::Insert => Change::Added,
+                },
+                text: change.value().trim_end_matches(['\r', '\n']).to_string(),
+            })
+            .collect();
+
+        Ok(Self {
+            old_status: old.status,
+            new_status: new.status,
+            headers: header_changes(old, new),
+            structural,
+            body,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.old_status == self.new_status
+            && self.headers.is_empty()
+            && self.body.iter().all(|line| line.change == Change::Equal)
+    }
+
+    /// Indices into `body` of the first line of each run of changed lines.
+    pub fn hunks(&self) -> Vec<usize> {
+        let mut hunks = vec![];
+        let mut in_hunk = false;
+        for (idx, line) in self.body.iter().enumerate() {
+            let changed = line.change != Change::Equal;
+            if changed && !in_hunk {
+                hunks.push(idx);
+            }
+            in_hunk = changed;
+        }
+        hunks
+    }
+}
+
+/// Headers are compared by name, ignoring case. Repeated headers are joined with `, `.
+fn header_changes(old: &Response, new: &Response) -> Vec<HeaderChange> {
+    let values = |response: &Response, name: &str| {
+        let values: Vec<&str> = response
+            .headers
+            .iter()
+            .filter(|header| header.name.eq_ignore_ascii_case(name))
+            .map(|header| header.value.as_ref())
+            .collect();
+        (!values.is_empty()).then(|| values.join(", "))
+    };
+    let mut names: Vec<String> = old
+        .headers
+        .iter()
+        .chain(&new.headers)
+        .map(|header| header.name.to_ascii_lowercase())
+        .collect();
+    names.sort();
+    names.dedup();
+
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let (old, new) = (values(old, &name), values(new, &name));
+            (old != new).then_some(HeaderChange { name, old, new })
+        })
+        .collect()
+}
+
+fn json_lines(body: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(body).ok()?;
+    let mut out = String::new();
+    write_sorted(&value, 0, &mut out);
+    out.push('\n');
+    Some(out)
+}
+
+/// Pretty prints `value` with object keys in sorted order, whatever order they were parsed in.
+fn write_sorted(value: &Value, indent: usize, out: &mut String) {
+    let pad = |out: &mut String, indent: usize| out.push_str(&"  ".repeat(indent));
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            out.push('{');
+            for (idx, (key, value)) in entries.into_iter().enumerate() {
+                out.push_str(if idx == 0 { "\n" } else { ",\n" });
+                pad(out, indent + 1);
+                let _ = write!(out, "{}: ", Value::String(key.clone()));
+                write_sorted(value, indent + 1, out);
+            }
+            out.push('\n');
+            pad(out, indent);
+            out.push('}');
+        }
+        Value::Array(items) if !items.is_empty() => {
+            out.push('[');
+            for (idx, item) in items.iter().enumerate() {
+                out.push_str(if idx == 0 { "\n" } else { ",\n" });
+                pad(out, indent + 1);
+                write_sorted(item, indent + 1, out);
+            }
+            out.push('\n');
+            pad(out, indent);
+            out.push(']');
+        }
+        value => {
+            let _ = write!(out, "{}", value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Header;
+
+    fn response(status: u16, headers: &[(&str, &str)], body: &str) -> Response {
+        Response {
+            status,
+            headers: headers
+                .iter()
+                .map(|(name, value)| Header {
+                    name: (*name).into(),
+                    value: (*value).into(),
+                })
+                .collect(),
+            body: body.as_bytes().to_vec(),
+            ..Default::default()
+        }
+    }
+
+    fn changed(diff: &ResponseDiff) -> Vec<(Change, &str)> {
+        diff.body
+            .iter()
+            .filter(|line| line.change != Change::Equal)
+            .map(|line| (line.change, line.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn json_ignores_key_order() {
+        let old = response(200, &[], r#"{"id": 1, "tags": ["a"], "name": "Rex"}"#);
+        let new = response(200, &[], r#"{"name": "Rex", "id": 1, "tags": ["a", "b"]}"#);
+
+        let diff = ResponseDiff::new(&old, &new).unwrap();
+
+        assert!(diff.structural);
+        assert_eq!(
+            changed(&diff),
+            vec![
+                (Change::Removed, r#"    "a""#),
+                (Change::Added, r#"    "a","#),
+                (Change::Added, r#"    "b""#),
+            ]
+        );
+        assert_eq!(diff.hunks().len(), 1);
+        assert!(ResponseDiff::new(&old, &old).unwrap().is_empty());
+    }
+
+    #[test]
+    fn text_status_and_headers() {
+        let old = response(
+            200,
+            &[("Content-Type", "text/plain"), ("X-Old", "1")],
+            "one\ntwo\nthree\nfour\n",
+        );
+        let new = response(
+            500,
+            &[("content-type", "text/html"), ("X-New", "2")],
+            "one\n2\nthree\n4\n",
+        );
+
+        let diff = ResponseDiff::new(&old, &new).unwrap();
+
+        assert!(!diff.structural);
+        assert_eq!((diff.old_status, diff.new_status), (200, 500));
+        let names: Vec<_> = diff.headers.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(names, vec!["content-type", "x-new", "x-old"]);
+        assert_eq!(diff.headers[1].old, None);
+        assert_eq!(diff.hunks(), vec![1, 4]);
+        assert_eq!(changed(&diff)[0], (Change::Removed, "two"));
+    }
+
+    #[test]
+    fn decodes_charsets() {
+        let mut old = response(
+            200,
+            &[("Content-Type", "text/plain; charset=ISO-8859-1")],
+            "",
+        );
+        old.body = b"caf\xe9\n".to_vec();
+        let new = response(200, &[("Content-Type", "text/plain")], "café\n");
+
+        let diff = ResponseDiff::new(&old, &new).unwrap();
+
+        assert!(changed(&diff).is_empty());
+    }
+}
@@ -0,0 +1,554 @@
+use color_eyre::Result;
+use log::warn;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, ACCEPT, ACCEPT_ENCODING, CONTENT_ENCODING, USER_AGENT},
+    Client,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    time::{Duration, Instant},
+};
+use time::OffsetDateTime;
+use tokio::net::{lookup_host, TcpStream};
+
+use crate::{
+    compression::{self, Encoding},
+    response::{SpilledBody, Timings},
+    wire::SentRequest,
+    ApiKeyLocation, Auth, Execution, Header, Request, Response,
+};
+
+/// How requests are sent and their response bodies received.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ExecuteOptions {
+    /// Bodies larger than this many bytes are written to a temp file instead of kept in memory.
+    #[serde(default = "default_memory_limit")]
+    pub memory_limit: usize,
+    /// Estimate DNS, connect and TLS timings on a connection of their own before each request,
+    /// see [`probe_connection`]. Off by default, as every request then opens an extra connection.
+    #[serde(default)]
+    pub probe_connection: bool,
+    /// Ask for gzip, deflate, brotli and zstd bodies and decode them, keeping the size they were
+    /// received at. When off, bodies are kept exactly as they were received.
+    #[serde(default = "default_decompress")]
+    pub decompress: bool,
+}
+
+fn default_memory_limit() -> usize {
+    16 * 1024 * 1024
+}
+
+fn default_decompress() -> bool {
+    true
+}
+
+impl Default for ExecuteOptions {
+    fn default() -> Self {
+        Self {
+            memory_limit: default_memory_limit(),
+            probe_connection: false,
+            decompress: default_decompress(),
+        }
+    }
+}
+
+/// How long a connection probe may take before its phases are left unmeasured.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How much of a response body has arrived.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Progress {
+    pub received: u64,
+    /// From the `Content-Length`, when the server sent one.
+    pub total: Option<u64>,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// Bytes received per second.
+    pub fn throughput(&self) -> f64 {
+        self.received as f64 / self.elapsed.as_secs_f64().max(0.001)
+    }
+}
+
+/// Progress is reported at most this often while a body downloads.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The client requests are sent with, along with the headers it adds to each of them. reqwest
+/// doesn't expose those, but they belong in the [`SentRequest`].
+#[derive(Clone, Debug)]
+pub struct HttpClient {
+    client: Client,
+    default_headers: HeaderMap,
+}
+
+impl HttpClient {
+    /// A client that identifies itself as `user_agent`.
+    pub fn new(user_agent: &str) -> Result<Self> {
+        let mut default_headers = accept_any();
+        default_headers.insert(USER_AGENT, HeaderValue::from_str(user_agent)?);
+        let client = Client::builder()
+            .default_headers(default_headers.clone())
+            .build()?;
+        Ok(Self {
+            client,
+            default_headers,
+        })
+    }
+
+    /// Headers added to requests that don't set them.
+    pub fn default_headers(&self) -> &HeaderMap {
+        &self.default_headers
+    }
+}
+
+impl Default for HttpClient {
+    /// A client with reqwest's defaults, which only add `accept: */*`.
+    fn default() -> Self {
+        Self {
+            client: Client::new(),
+            default_headers: accept_any(),
+        }
+    }
+}
+
+fn accept_any() -> HeaderMap {
+    HeaderMap::from_iter([(ACCEPT, HeaderValue::from_static("*/*"))])
+}
+
+/// Sends a request that has already had its variables resolved, then runs its assertions.
+pub async fn execute(client: &HttpClient, req: Request) -> Result<Execution> {
+    execute_with(client, req, &ExecuteOptions::default(), |_| {}).await
+}
+
+/// Like [`execute`], calling `on_progress` as the body arrives and spilling bodies past the
+/// memory limit to a temp file.
+pub async fn execute_with(
+    client: &HttpClient,
+    req: Request,
+    options: &ExecuteOptions,
+    mut on_progress: impl FnMut(Progress),
+) -> Result<Execution> {
+    // A compressed body gets the `Content-Encoding` of its compression in place of any set by hand.
+    let headers = req
+        .headers
+        .iter()
+        .filter(|header| {
+            req.compression.is_none()
+                || !header.name.eq_ignore_ascii_case(CONTENT_ENCODING.as_str())
+        })
+        .fold(Ok(HeaderMap::new()), Header::fold)?;
+    let asks_encoding = headers.contains_key(ACCEPT_ENCODING);
+    let mut builder = client
+        .client
+        .request(req.method.clone().into(), path_url(&req))
+        .headers(headers);
+
+    if let Some(query) = &req.query_params {
+        let mut query: Vec<(&String, &String)> = query.iter().collect();
+        query.sort();
+        builder = builder.query(&query);
+    }
+
+    builder = match &req.auth {
+        Some(Auth::Basic { username, password }) => builder.basic_auth(username, Some(password)),
+        Some(Auth::Bearer { token }) => builder.bearer_auth(token),
+        Some(Auth::ApiKey {
+            key,
+            value,
+            location: ApiKeyLocation::Header,
+        }) => builder.header(key.as_str(), value.as_str()),
+        Some(Auth::ApiKey {
+            key,
+            value,
+            location: ApiKeyLocation::Query,
+        }) => builder.query(&[(key, value)]),
+        None => builder,
+    };
+
+    if options.decompress && !asks_encoding {
+        builder = builder.header(ACCEPT_ENCODING, compression::ACCEPT_ENCODING);
+    }
+
+    match (&req.body, req.compression) {
+        (Some(body), Some(encoding)) => {
+            builder = builder
+                .header(CONTENT_ENCODING, encoding.token())
+                .body(encoding.compress(body.as_bytes())?);
+        }
+        (Some(body), None) => builder = builder.body(body.clone()),
+        (None, _) => {}
+    }
+
+    let mut timings = Timings::default();
+    if options.probe_connection {
+        if let Ok(url) = reqwest::Url::parse(&path_url(&req)) {
+            let _ = tokio::time::timeout(PROBE_TIMEOUT, probe_connection(&url, &mut timings)).await;
+            timings.estimated = true;
+        }
+    }
+
+    let request = builder.build()?;
+    let mut sent = SentRequest::new(&request, &client.default_headers);
+
+    let started = OffsetDateTime::now_utc();
+    let timer = Instant::now();
+    let mut res = client.client.execute(request).await?;
+    timings.ttfb = timer.elapsed();
+
+    let status = res.status().as_u16();
+    let version = format!("{:?}", res.version());
+    sent.version = version.clone();
+    let encoding = res
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .and_then(Encoding::from_header);
+    let headers = res
+        .headers()
+        .iter()
+        .map(|(name, value)| Header {
+            name: name.as_str().into(),
+            value: String::from_utf8_lossy(value.as_bytes()).into(),
+        })
+        .collect();
+
+    let download = Instant::now();
+    let mut progress = Progress {
+        received: 0,
+        total: res.content_length(),
+        elapsed: Duration::ZERO,
+    };
+    let mut reported = download;
+    let mut body = vec![];
+    let mut spill: Option<(SpilledBody, File)> = None;
+    while let Some(chunk) = res.chunk().await? {
+        progress.received += chunk.len() as u64;
+        match &mut spill {
+            Some((_, file)) => file.write_all(&chunk)?,
+            None if body.len() + chunk.len() > options.memory_limit => {
+                let spilled = SpilledBody::create()?;
+                let mut file = File::create(&spilled.path)?;
+                file.write_all(&body)?;
+                file.write_all(&chunk)?;
+                spill = Some((spilled, file));
+            }
+            None => body.extend_from_slice(&chunk),
+        }
+        if reported.elapsed() >= PROGRESS_INTERVAL {
+            progress.elapsed = download.elapsed();
+            on_progress(progress);
+            reported = Instant::now();
+        }
+    }
+    progress.elapsed = download.elapsed();
+    on_progress(progress);
+    timings.download = progress.elapsed;
+
+    let mut spilled = match spill {
+        Some((mut spilled, mut file)) => {
+            file.flush()?;
+            spilled.len = progress.received;
+            Some(spilled)
+        }
+        None => None,
+    };
+
+    let mut compressed_len = None;
+    if let Some(encoding) = encoding.filter(|_| options.decompress) {
+        match decode_body(encoding, &body, spilled.as_ref(), options.memory_limit) {
+            Ok((decoded, decoded_spill)) => {
+                // Replacing the received body drops it, which removes its file.
+                body = decoded;
+                spilled = decoded_spill;
+                compressed_len = Some(progress.received);
+            }
+            Err(err) => warn!("Failed to decode {} response body: {}", encoding, err),
+        }
+    }
+    let response = Response {
+        status,
+        version,
+        headers,
+        body,
+        spilled,
+        compressed_len,
+        timings,
+        duration: timer.elapsed(),
+    };
+    let tests = req.assertions.run(&response);
+
+    Ok(Execution {
+        request: req,
+        response,
+        started,
+        tests,
+        environment: None,
+        sent: Some(sent),
+    })
+}
+
+/// Undoes `encoding` on a body received in `body`, or in `spilled` when it outgrew memory. The
+/// decoded body is kept to the memory limit the same way, spilling the rest to a new file.
+fn decode_body(
+    encoding: Encoding,
+    body: &[u8],
+    spilled: Option<&SpilledBody>,
+    memory_limit: usize,
+) -> io::Result<(Vec<u8>, Option<SpilledBody>)> {
+    let mut decoder = match spilled {
+        Some(spilled) => encoding.decoder(File::open(&spilled.path)?)?,
+        None => encoding.decoder(body)?,
+    };
+    let mut decoded = vec![];
+    (&mut decoder)
+        .take(memory_limit as u64 + 1)
+        .read_to_end(&mut decoded)?;
+    if decoded.len() <= memory_limit {
+        return Ok((decoded, None));
+    }
+
+    let mut spilled = SpilledBody::create()?;
+    let mut file = File::create(&spilled.path)?;
+    file.write_all(&decoded)?;
+    spilled.len = decoded.len() as u64 + io::copy(&mut decoder, &mut file)?;
+    file.flush()?;
+    decoded.truncate(memory_limit);
+    Ok((decoded, Some(spilled)))
+}
+
+/// Times resolving the host, opening a TCP connection and, for `https`, the TLS handshake, on a
+/// connection of its own. The HTTP client pools its connections and doesn't expose their phases,
+/// so these are estimates of the network path to the server rather than the request's own
+/// connection, and are kept apart from its timeline, see [`Timings::estimated`]. Phases that
+/// fail are left unset, the request itself reports the error.
+pub async fn probe_connection(url: &reqwest::Url, timings: &mut Timings) {
+    let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+        return;
+    };
+    let timer = Instant::now();
+    let Some(addr) = lookup_host((host, port))
+        .await
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+    else {
+        return;
+    };
+    if url.domain().is_some() {
+        timings.dns = Some(timer.elapsed());
+    }
+
+    let timer = Instant::now();
+    let Ok(stream) = TcpStream::connect(addr).await else {
+        return;
+    };
+    timings.connect = Some(timer.elapsed());
+
+    if url.scheme() == "https" {
+        let Ok(connector) = native_tls::TlsConnector::new() else {
+            return;
+        };
+        let timer = Instant::now();
+        if tokio_native_tls::TlsConnector::from(connector)
+            .connect(host, stream)
+            .await
+            .is_ok()
+        {
+            timings.tls = Some(timer.elapsed());
+        }
+    }
+}
+
+/// Fills `{name}` and `:name` path segments from the request's path parameters.
+pub fn path_url(req: &Request) -> String {
+    let Some(params) = &req.path_params else {
+        return req.url.clone();
+    };
+    req.url
+        .split('/')
+        .map(|segment| {
+            params
+                .iter()
+                .fold(segment.to_string(), |segment, (key, value)| {
+                    if segment.strip_prefix(':') == Some(key) {
+                        value.clone()
+                    } else {
+                        segment.replace(&format!("{{{}}}", key), value)
+                    }
+                })
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util::serve, Method};
+
+    #[tokio::test]
+    async fn records_timings() {
+        let req = Request::builder()
+            .name("Ping".into())
+            .method(Method::Get)
+            .url(serve("", vec![b"ok".to_vec()]))
+            .build();
+
+        let execution = execute(&HttpClient::default(), req).await.unwrap();
+        let timings = &execution.response.timings;
+        assert!(!timings.estimated);
+        assert_eq!(timings.connect, None);
+        assert!(timings.ttfb > Duration::ZERO);
+        let names: Vec<_> = timings.phases().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["TTFB", "Download"]);
+    }
+
+    #[tokio::test]
+    async fn probes_connection_when_asked() {
+        let req = Request::builder()
+            .name("Ping".into())
+            .method(Method::Get)
+            .url(serve("", vec![b"ok".to_vec()]))
+            .build();
+        let options = ExecuteOptions {
+            probe_connection: true,
+            ..Default::default()
+        };
+
+        let execution = execute_with(&HttpClient::default(), req, &options, |_| {})
+            .await
+            .unwrap();
+
+        let timings = &execution.response.timings;
+        assert!(timings.estimated);
+        // The server is addressed by IP and over plain HTTP, so there is nothing to resolve or
+        // encrypt.
+        assert_eq!((timings.dns, timings.tls), (None, None));
+        let names: Vec<_> = timings
+            .estimates()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["Connect"]);
+        let names: Vec<_> = timings.phases().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["TTFB", "Download"]);
+    }
+
+    #[tokio::test]
+    async fn spills_large_bodies() {
+        let req = Request::builder()
+            .name("Export".into())
+            .method(Method::Get)
+            .url(serve("", vec![b"0123456789abcdef".to_vec()]))
+            .build();
+        let options = ExecuteOptions {
+            memory_limit: 8,
+            probe_connection: true,
+            decompress: true,
+        };
+        let mut last = None;
+
+        let execution = execute_with(&HttpClient::default(), req, &options, |progress| {
+            last = Some(progress)
+        })
+        .await
+        .unwrap();
+
+        let spilled = execution.response.spilled.as_ref().unwrap();
+        assert_eq!(spilled.len, 16);
+        assert_eq!(execution.response.body_len(), 16);
+        assert_eq!(std::fs::read(&spilled.path).unwrap(), b"0123456789abcdef");
+        assert!(execution.response.body.len() <= 8);
+        let last = last.unwrap();
+        assert_eq!((last.received, last.total), (16, Some(16)));
+
+        let path = spilled.path.clone();
+        drop(execution);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn decodes_compressed_bodies() {
+        let body = b"pets pets pets pets pets pets pets pets".repeat(4);
+        let url = serve(
+            "Content-Encoding: br\r\n",
+            vec![Encoding::Brotli.compress(&body).unwrap()],
+        );
+        let req = Request::builder()
+            .name("Pets".into())
+            .method(Method::Get)
+            .url(url)
+            .build();
+        let mut options = ExecuteOptions {
+            probe_connection: false,
+            ..Default::default()
+        };
+
+        let execution = execute_with(&HttpClient::default(), req.clone(), &options, |_| {})
+            .await
+            .unwrap();
+        let res = &execution.response;
+        assert_eq!(res.body, body);
+        assert!(res
+            .compressed_len
+            .is_some_and(|len| len < body.len() as u64));
+        let sent = execution.sent.unwrap();
+        assert!(sent
+            .headers
+            .iter()
+            .any(|header| &*header.name == "accept-encoding"));
+
+        options.decompress = false;
+        let execution = execute_with(&HttpClient::default(), req, &options, |_| {})
+            .await
+            .unwrap();
+        let res = &execution.response;
+        assert_eq!(Encoding::Brotli.decompress(&res.body).unwrap(), body);
+        assert_eq!(res.compressed_len, None);
+    }
+
+    #[tokio::test]
+    async fn compresses_request_bodies() {
+        let mut req = Request::builder()
+            .name("Ingest".into())
+            .method(Method::Post)
+            .url(serve("", vec![b"ok".to_vec()]))
+            .header(Header {
+                name: "Content-Encoding".into(),
+                value: "identity".into(),
+            })
+            .body(r#"{"events":[]}"#.into())
+            .build();
+        req.compression = Some(Encoding::Gzip);
+
+        let execution = execute(&HttpClient::default(), req).await.unwrap();
+
+        let sent = execution.sent.unwrap();
+        let encodings: Vec<_> = sent
+            .headers
+            .iter()
+            .filter(|header| &*header.name == "content-encoding")
+            .map(|header| header.value.to_string())
+            .collect();
+        assert_eq!(encodings, vec!["gzip"]);
+        assert_eq!(
+            Encoding::Gzip.decompress(&sent.body).unwrap(),
+            br#"{"events":[]}"#
+        );
+    }
+
+    #[test]
+    fn path_url_params() {
+        let req = Request::builder()
+            .name("Get Pet".into())
+            .method(Method::Get)
+            .url("https://pets.test/owners/:owner/pets/{petId}".into())
+            .path_param("owner".into(), "ada".into())
+            .path_param("petId".into(), "7".into())
+            .build();
+
+        assert_eq!(path_url(&req), "https://pets.test/owners/ada/pets/7");
+    }
+}
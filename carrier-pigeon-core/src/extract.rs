@@ -0,0 +1,212 @@
+//! Rules that copy values out of a response into variables, so later requests can use them.
+
+use color_eyre::{
+    eyre::{eyre, OptionExt},
+    Result,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_json_path::JsonPath;
+
+use crate::{Collection, Execution, Response};
+
+/// Stores a value taken from the response in `variable`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Extraction {
+    pub variable: String,
+    #[serde(flatten)]
+    pub source: Source,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "from", rename_all = "snake_case")]
+pub enum Source {
+    /// The first match of the path, strings are stored without quotes.
+    JsonPath {
+        path: String,
+    },
+    /// A capture group of the first match in the body, the first group when there is one.
+    Regex {
+        pattern: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group: Option<usize>,
+    },
+    Header {
+        name: String,
+    },
+    /// The value of a cookie set with `Set-Cookie`.
+    Cookie {
+        name: String,
+    },
+}
+
+impl Extraction {
+    pub fn extract(&self, res: &Response) -> Result<String> {
+        match &self.source {
+            Source::JsonPath { path } => {
+                let path =
+                    JsonPath::parse(path).map_err(|err| eyre!("invalid JSONPath: {}", err))?;
+                let body: Value = serde_json::from_slice(res.complete_body()?)
+                    .map_err(|err| eyre!("body is not JSON: {}", err))?;
+                match path.query(&body).first().ok_or_eyre("no match")? {
+                    Value::String(value) => Ok(value.clone()),
+                    value => Ok(value.to_string()),
+                }
+            }
+            Source::Regex { pattern, group } => {
+                let regex = Regex::new(pattern)?;
+                let body = String::from_utf8_lossy(res.complete_body()?);
+                let captures = regex.captures(&body).ok_or_eyre("no match")?;
+                let group = group.unwrap_or(usize::from(regex.captures_len() > 1));
+                captures
+                    .get(group)
+                    .map(|value| value.as_str().to_string())
+                    .ok_or_else(|| eyre!("group {} did not match", group))
+            }
+            Source::Header { name } => res
+                .header(name)
+                .map(str::to_string)
+                .ok_or_eyre("header is missing"),
+            Source::Cookie { name } => res
+                .headers
+                .iter()
+                .filter(|header| header.name.eq_ignore_ascii_case("set-cookie"))
+                .filter_map(|header| header.value.split(';').next()?.split_once('='))
+                .find(|(cookie, _)| cookie.trim() == name)
+                .map(|(_, value)| value.trim().to_string())
+                .ok_or_eyre("cookie was not set"),
+        }
+    }
+}
+
+/// Runs the extraction rules of a successful execution, writing the values to the named
+/// environment. Returns a message for each rule that failed.
+pub fn apply(coll: &mut Collection, env_name: Option<&str>, execution: &Execution) -> Vec<String> {
+    if !execution.response.is_success() {
+        return vec![];
+    }
+    execution
+        .request
+        .extractions
+        .iter()
+        .filter_map(|extraction| match extraction.extract(&execution.response) {
+            Ok(value) => {
+                coll.set_variable(env_name, extraction.variable.clone(), value);
+                None
+            }
+            Err(err) => Some(format!(
+                "Extracting '{}' from {} failed: {}",
+                extraction.variable, execution.request.name, err
+            )),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EnvironmentValue, Header, Method, Request};
+    use time::OffsetDateTime;
+
+    fn execution(status: u16, extractions: Vec<Extraction>) -> Execution {
+        let mut request = Request::builder()
+            .name("Login".into())
+            .method(Method::Post)
+            .url("https://pets.test/login".into())
+            .build();
+        request.extractions = extractions;
+        Execution {
+            request,
+            response: Response {
+                status,
+                headers: vec![
+                    Header {
+                        name: "X-Request-Id".into(),
+                        value: "req-1".into(),
+                    },
+                    Header {
+                        name: "Set-Cookie".into(),
+                        value: "session=s3cr3t; Path=/; HttpOnly".into(),
+                    },
+                ],
+                body: br#"{"token":"abc","user":{"id":7}}"#.to_vec(),
+                ..Default::default()
+            },
+            started: OffsetDateTime::UNIX_EPOCH,
+            tests: vec![],
+            environment: None,
+            sent: None,
+        }
+    }
+
+    fn rule(variable: &str, json: &str) -> Extraction {
+        let mut rule: Value = serde_json::from_str(json).unwrap();
+        rule["variable"] = variable.into();
+        serde_json::from_value(rule).unwrap()
+    }
+
+    #[test]
+    fn extracts_each_source() {
+        let extractions = vec![
+            rule("token", r#"{ "from": "json_path", "path": "$.token" }"#),
+            rule("userId", r#"{ "from": "json_path", "path": "$.user.id" }"#),
+            rule(
+                "tokenRe",
+                r#"{ "from": "regex", "pattern": "\"token\":\"(\\w+)\"" }"#,
+            ),
+            rule(
+                "requestId",
+                r#"{ "from": "header", "name": "x-request-id" }"#,
+            ),
+            rule("session", r#"{ "from": "cookie", "name": "session" }"#),
+            rule("missing", r#"{ "from": "cookie", "name": "other" }"#),
+        ];
+        let mut coll = Collection::default();
+
+        let errors = apply(&mut coll, None, &execution(200, extractions));
+
+        let value = |key: &str| coll.variables.get(key).map(EnvironmentValue::value);
+        assert_eq!(value("token"), Some("abc"));
+        assert_eq!(value("userId"), Some("7"));
+        assert_eq!(value("tokenRe"), Some("abc"));
+        assert_eq!(value("requestId"), Some("req-1"));
+        assert_eq!(value("session"), Some("s3cr3t"));
+        assert_eq!(
+            errors,
+            vec!["Extracting 'missing' from Login failed: cookie was not set"]
+        );
+    }
+
+    #[test]
+    fn refuses_spilled_bodies() {
+        let mut coll = Collection::default();
+        let extractions = vec![rule(
+            "token",
+            r#"{ "from": "json_path", "path": "$.token" }"#,
+        )];
+        let mut execution = execution(200, extractions);
+        execution.response.spilled =
+            serde_json::from_str(r#"{ "path": "/tmp/missing.body", "len": 64 }"#).unwrap();
+
+        assert_eq!(
+            apply(&mut coll, None, &execution),
+            vec![
+                "Extracting 'token' from Login failed: body of 64 bytes is too large to parse, \
+                 only its start is in memory"
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_failed_responses() {
+        let mut coll = Collection::default();
+        let extractions = vec![rule(
+            "token",
+            r#"{ "from": "json_path", "path": "$.token" }"#,
+        )];
+
+        assert!(apply(&mut coll, None, &execution(401, extractions)).is_empty());
+        assert!(coll.variables.is_empty());
+    }
+}
@@ -0,0 +1,193 @@
+//! Filters over JSON response bodies, written as a JSONPath query or a jq program.
+
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
+use jaq_core::{
+    load::{self, Arena, File, Loader},
+    Compiler, Ctx, RcIter,
+};
+use jaq_json::Val;
+use serde_json::Value;
+use serde_json_path::JsonPath;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Runs `expr` against a JSON body. Expressions starting with `$` are JSONPath queries and
+/// return every matching node, anything else is run as a jq program and returns its outputs.
+pub fn apply(body: &[u8], expr: &str) -> Result<Vec<Value>> {
+    let mut values = vec![];
+    each_value(body, expr, |value| {
+        values.push(value);
+        Ok(())
+    })?;
+    Ok(values)
+}
+
+/// Runs `expr` like [`apply`] and renders its outputs like [`render`], stopping with an error
+/// once the text outgrows `limit` bytes or `cancel` is set, so programs like `repeat(.)` end.
+/// A jq program that loops without producing any output can't be stopped this way, so this is
+/// meant to run on a thread of its own.
+pub fn output(body: &[u8], expr: &str, limit: usize, cancel: &AtomicBool) -> Result<String> {
+    let mut text = String::new();
+    each_value(body, expr, |value| {
+        if cancel.load(Ordering::Relaxed) {
+            bail!("Filter cancelled");
+        }
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&serde_json::to_string_pretty(&value)?);
+        if text.len() > limit {
+            bail!("Filter output is larger than {} bytes", limit);
+        }
+        Ok(())
+    })?;
+    Ok(text)
+}
+
+fn each_value(body: &[u8], expr: &str, on_value: impl FnMut(Value) -> Result<()>) -> Result<()> {
+    let expr = expr.trim();
+    let body: Value =
+        serde_json::from_slice(body).map_err(|err| eyre!("Body is not JSON: {}", err))?;
+    if expr.starts_with('$') {
+        let path = JsonPath::parse(expr).map_err(|err| eyre!("Invalid JSONPath: {}", err))?;
+        path.query(&body)
+            .all()
+            .into_iter()
+            .cloned()
+            .try_for_each(on_value)
+    } else {
+        jq(body, expr, on_value)
+    }
+}
+
+/// Outputs of a filter as text, each value pretty printed on its own like `jq` does.
+pub fn render(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(|value| serde_json::to_string_pretty(value).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn jq(body: Value, code: &str, mut on_value: impl FnMut(Value) -> Result<()>) -> Result<()> {
+    let program = File { code, path: () };
+    let loader = Loader::new(jaq_std::defs().chain(jaq_json::defs()));
+    let arena = Arena::default();
+    let modules = loader.load(&arena, program).map_err(|errs| {
+        let message = errs
+            .into_iter()
+            .flat_map(|(_, err)| load_errors(code, err))
+            .next()
+            .unwrap_or_default();
+        eyre!("Invalid jq filter: {}", message)
+    })?;
+    let filter = Compiler::default()
+        .with_funs(jaq_std::funs().chain(jaq_json::funs()))
+        .compile(modules)
+        .map_err(|errs| {
+            let message = errs
+                .into_iter()
+                .flat_map(|(_, errs)| errs)
+                .map(|(name, undefined)| format!("undefined {} {}", undefined.as_str(), name))
+                .next()
+                .unwrap_or_default();
+            eyre!("Invalid jq filter: {}", message)
+        })?;
+
+    let inputs = RcIter::new(core::iter::empty());
+    for output in filter.run((Ctx::new([], &inputs), Val::from(body))) {
+        match output {
+            Ok(value) => on_value(Value::from(value))?,
+            Err(err) => bail!("jq error: {}", err),
+        }
+    }
+    Ok(())
+}
+
+fn load_errors<'s>(code: &'s str, err: load::Error<&'s str>) -> Vec<String> {
+    let column = |found: &str| load::span(code, found).start + 1;
+    match err {
+        load::Error::Io(errs) => errs.into_iter().map(|(_, err)| err).collect(),
+        load::Error::Lex(errs) => errs
+            .into_iter()
+            .map(|(expect, found)| {
+                format!("expected {} at column {}", expect.as_str(), column(found))
+            })
+            .collect(),
+        load::Error::Parse(errs) => errs
+            .into_iter()
+            .map(|(expect, found)| match found {
+                "" => format!("expected {} at the end", expect.as_str()),
+                found => format!(
+                    "expected {} at column {}, found {}",
+                    expect.as_str(),
+                    column(found),
+                    found
+                ),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const PETS: &[u8] = br#"{"pets": [{"name": "Rex", "age": 3}, {"name": "Tom", "age": 9}]}"#;
+
+    #[test]
+    fn json_path() {
+        assert_eq!(
+            apply(PETS, "$.pets[*].name").unwrap(),
+            vec![json!("Rex"), json!("Tom")]
+        );
+        assert!(apply(PETS, "$.pets[").is_err());
+    }
+
+    #[test]
+    fn jq_programs() {
+        assert_eq!(
+            apply(PETS, ".pets[] | select(.age > 5) | .name").unwrap(),
+            vec![json!("Tom")]
+        );
+        assert_eq!(
+            apply(PETS, ".pets | map(.age) | add").unwrap(),
+            vec![json!(12)]
+        );
+        assert_eq!(render(&apply(PETS, ".pets[0]").unwrap()).lines().count(), 4);
+    }
+
+    #[test]
+    fn reports_errors() {
+        let message = |expr| apply(PETS, expr).unwrap_err().to_string();
+
+        assert!(message(".pets[").starts_with("Invalid jq filter: expected"));
+        assert_eq!(
+            message(".pets | frobnicate"),
+            "Invalid jq filter: undefined filter frobnicate"
+        );
+        assert!(message(".pets + 1").starts_with("jq error:"));
+        assert!(apply(b"<html>", ".").is_err());
+    }
+
+    #[test]
+    fn bounds_output() {
+        let running = AtomicBool::new(false);
+        assert_eq!(
+            output(PETS, "$.pets[*].age", 1024, &running).unwrap(),
+            "3\n9"
+        );
+        assert_eq!(
+            output(PETS, "repeat(.)", 1024, &running)
+                .unwrap_err()
+                .to_string(),
+            "Filter output is larger than 1024 bytes"
+        );
+
+        let cancelled = AtomicBool::new(true);
+        assert!(output(PETS, "range(infinite)", 1024, &cancelled).is_err());
+    }
+}
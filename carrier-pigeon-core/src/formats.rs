@@ -0,0 +1,116 @@
+use std::fmt;
+
+use crate::Header;
+
+pub mod har;
+pub mod http_file;
+pub mod insomnia;
+pub mod openapi;
+pub mod postman;
+
+/// A single part of a document that could not be converted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReportEntry {
+    /// Where in the source document the entry came from, e.g. `Users/Get User`.
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for ReportEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// Everything that was skipped or approximated while converting between formats.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    pub entries: Vec<ReportEntry>,
+}
+
+impl Report {
+    pub fn unsupported(&mut self, location: impl Into<String>, message: impl Into<String>) {
+        self.entries.push(ReportEntry {
+            location: location.into(),
+            message: message.into(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Joins a folder path and a name into the slash separated form used by [`crate::Request::folder`].
+pub(crate) fn join_path(folder: Option<&str>, name: &str) -> String {
+    match folder {
+        Some(folder) if !folder.is_empty() => format!("{}/{}", folder, name),
+        _ => name.to_string(),
+    }
+}
+
+/// Splits a url into the part before the query string and its query parameters.
+pub(crate) fn split_query(url: &str) -> (String, Vec<(String, String)>) {
+    match url.split_once('?') {
+        Some((base, query)) => (
+            base.to_string(),
+            query
+                .split('&')
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| match pair.split_once('=') {
+                    Some((key, value)) => (key.to_string(), value.to_string()),
+                    None => (pair.to_string(), String::new()),
+                })
+                .collect(),
+        ),
+        None => (url.to_string(), vec![]),
+    }
+}
+
+/// Joins `pairs` into an `application/x-www-form-urlencoded` string, for form bodies and query
+/// strings. `{{variable}}` references are kept as they are, so they still resolve when sent.
+pub(crate) fn form_urlencode<K, V>(pairs: impl IntoIterator<Item = (K, V)>) -> String
+where
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    pairs
+        .into_iter()
+        .map(|(key, value)| {
+            format!(
+                "{}={}",
+                encode_component(key.as_ref()),
+                encode_component(value.as_ref())
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn encode_component(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}").map(|end| start + end + 2) else {
+            break;
+        };
+        encoded.extend(form_urlencoded::byte_serialize(&rest.as_bytes()[..start]));
+        encoded.push_str(&rest[start..end]);
+        rest = &rest[end..];
+    }
+    encoded.extend(form_urlencoded::byte_serialize(rest.as_bytes()));
+    encoded
+}
+
+/// Adds a `Content-Type` header unless the request already sets one.
+pub(crate) fn ensure_content_type(headers: &mut Vec<Header>, content_type: &str) {
+    if !headers
+        .iter()
+        .any(|header| header.name.eq_ignore_ascii_case("content-type"))
+    {
+        headers.push(Header {
+            name: "Content-Type".into(),
+            value: content_type.into(),
+        });
+    }
+}
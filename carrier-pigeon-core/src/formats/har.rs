@@ -0,0 +1,646 @@
+//! HTTP Archive (HAR 1.2) model and conversion to and from requests and executions.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, time::Duration};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use super::{form_urlencode, split_query, Report};
+use crate::{executor, response, Execution, Header, Method, Request, Response};
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Har {
+    pub log: Log,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Log {
+    pub version: String,
+    pub creator: Creator,
+    #[serde(default)]
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Creator {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    pub started_date_time: String,
+    /// Total time of the entry in milliseconds.
+    pub time: f64,
+    pub request: HarRequest,
+    pub response: HarResponse,
+    #[serde(default)]
+    pub cache: serde_json::Value,
+    #[serde(default)]
+    pub timings: Timings,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_ip_address: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarRequest {
+    pub method: String,
+    pub url: String,
+    pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<Cookie>,
+    #[serde(default)]
+    pub headers: Vec<NameValue>,
+    #[serde(default)]
+    pub query_string: Vec<NameValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<PostData>,
+    #[serde(default = "unknown_size")]
+    pub headers_size: i64,
+    #[serde(default = "unknown_size")]
+    pub body_size: i64,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub status_text: String,
+    pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<Cookie>,
+    #[serde(default)]
+    pub headers: Vec<NameValue>,
+    pub content: Content,
+    #[serde(rename = "redirectURL", default)]
+    pub redirect_url: String,
+    #[serde(default = "unknown_size")]
+    pub headers_size: i64,
+    #[serde(default = "unknown_size")]
+    pub body_size: i64,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct NameValue {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_only: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secure: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostData {
+    pub mime_type: String,
+    #[serde(default)]
+    pub params: Vec<NameValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Not part of HAR 1.2 but written by browsers for binary request bodies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Content {
+    pub size: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<i64>,
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+/// Phase timings in milliseconds, `-1` marks a phase that does not apply.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Timings {
+    #[serde(default = "not_applicable")]
+    pub blocked: f64,
+    #[serde(default = "not_applicable")]
+    pub dns: f64,
+    #[serde(default = "not_applicable")]
+    pub connect: f64,
+    #[serde(default)]
+    pub send: f64,
+    #[serde(default)]
+    pub wait: f64,
+    #[serde(default)]
+    pub receive: f64,
+    #[serde(default = "not_applicable")]
+    pub ssl: f64,
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Timings {
+            blocked: -1.0,
+            dns: -1.0,
+            connect: -1.0,
+            send: 0.0,
+            wait: 0.0,
+            receive: 0.0,
+            ssl: -1.0,
+        }
+    }
+}
+
+impl Timings {
+    /// The phases of an execution. HAR counts the TLS handshake as part of connecting, estimated
+    /// connection phases are left out as they don't belong to the entry's own timeline.
+    fn from_execution(timings: &response::Timings, millis: f64) -> Self {
+        // Executions recorded before phases were measured only have a total.
+        if timings.ttfb.is_zero() {
+            return Timings {
+                wait: millis,
+                ..Default::default()
+            };
+        }
+        let phase =
+            |phase: Option<Duration>| phase.map_or(-1.0, |phase| phase.as_secs_f64() * 1000.0);
+        let connection = |phase: Option<Duration>| phase.filter(|_| !timings.estimated);
+        let ssl = phase(connection(timings.tls));
+        let connect = match phase(connection(timings.connect)) {
+            connect if connect >= 0.0 => connect + ssl.max(0.0),
+            connect => connect,
+        };
+        Timings {
+            dns: phase(connection(timings.dns)),
+            connect,
+            ssl,
+            wait: phase(Some(timings.ttfb)),
+            receive: phase(Some(timings.download)),
+            ..Default::default()
+        }
+    }
+
+    fn to_execution(&self) -> response::Timings {
+        let phase = |millis: f64| (millis >= 0.0).then(|| Duration::from_secs_f64(millis / 1000.0));
+        response::Timings {
+            dns: phase(self.dns),
+            connect: phase(self.connect - self.ssl.max(0.0)),
+            tls: phase(self.ssl),
+            estimated: false,
+            ttfb: phase(self.wait).unwrap_or_default(),
+            download: phase(self.receive).unwrap_or_default(),
+        }
+    }
+
+    /// Sum of every phase that applies.
+    pub fn total(&self) -> f64 {
+        [
+            self.blocked,
+            self.dns,
+            self.connect,
+            self.send,
+            self.wait,
+            self.receive,
+        ]
+        .iter()
+        .filter(|phase| **phase > 0.0)
+        .sum()
+    }
+}
+
+fn unknown_size() -> i64 {
+    -1
+}
+
+fn not_applicable() -> f64 {
+    -1.0
+}
+
+/// Parses a HAR document.
+pub fn parse(json: &str) -> Result<Har> {
+    Ok(serde_json::from_str(json)?)
+}
+
+impl Entry {
+    /// Short description used when choosing which entries to import.
+    pub fn summary(&self) -> String {
+        format!(
+            "{:7} {} {}",
+            self.request.method, self.response.status, self.request.url
+        )
+    }
+
+    /// Converts the entry into a request that can be saved in a collection.
+    pub fn to_request(&self, report: &mut Report) -> Option<Request> {
+        let location = format!("{} {}", self.request.method, self.request.url);
+        let method: Method = match self.request.method.parse() {
+            Ok(method) => method,
+            Err(err) => {
+                report.unsupported(location, format!("{}, entry skipped", err));
+                return None;
+            }
+        };
+
+        let (url, query) = split_query(&self.request.url);
+        let name = url
+            .split("://")
+            .last()
+            .and_then(|rest| rest.split_once('/'))
+            .map(|(_, path)| format!("{} /{}", method, path))
+            .unwrap_or_else(|| format!("{} /", method));
+        let mut req = Request::builder()
+            .name(name)
+            .method(method)
+            .url(url)
+            .build();
+
+        let query: Vec<(String, String)> = if self.request.query_string.is_empty() {
+            query
+        } else {
+            self.request
+                .query_string
+                .iter()
+                .map(|param| (param.name.clone(), param.value.clone()))
+                .collect()
+        };
+        if !query.is_empty() {
+            req.query_params = Some(query.into_iter().collect());
+        }
+
+        req.headers = self
+            .request
+            .headers
+            .iter()
+            // HTTP/2 pseudo headers and lengths are produced by the client when sending.
+            .filter(|header| {
+                !header.name.starts_with(':') && !header.name.eq_ignore_ascii_case("content-length")
+            })
+            .map(|header| Header {
+                name: header.name.as_str().into(),
+                value: header.value.as_str().into(),
+            })
+            .collect();
+
+        if !self.request.cookies.is_empty()
+            && !req
+                .headers
+                .iter()
+                .any(|header| header.name.eq_ignore_ascii_case("cookie"))
+        {
+            req.headers.push(Header {
+                name: "Cookie".into(),
+                value: cookie_header(&self.request.cookies).into(),
+            });
+        }
+
+        if let Some(post) = &self.request.post_data {
+            req.body = match (&post.text, post.encoding.as_deref()) {
+                (Some(text), Some("base64")) => {
+                    match STANDARD
+                        .decode(text)
+                        .ok()
+                        .and_then(|b| String::from_utf8(b).ok())
+                    {
+                        Some(text) => Some(text),
+                        None => {
+                            report.unsupported(&location, "binary request body skipped");
+                            None
+                        }
+                    }
+                }
+                (Some(text), _) => Some(text.clone()),
+                (None, _) if !post.params.is_empty() => Some(form_urlencode(
+                    post.params.iter().map(|param| (&param.name, &param.value)),
+                )),
+                (None, _) => None,
+            };
+        }
+
+        Some(req)
+    }
+
+    /// Converts the entry into an execution, decoding base64 response content.
+    pub fn to_execution(&self, report: &mut Report) -> Option<Execution> {
+        let request = self.to_request(report)?;
+        let started = OffsetDateTime::parse(&self.started_date_time, &Rfc3339)
+            .unwrap_or(OffsetDateTime::UNIX_EPOCH);
+        let millis = if self.time > 0.0 {
+            self.time
+        } else {
+            self.timings.total()
+        };
+        let content = &self.response.content;
+        let body = match (&content.text, content.encoding.as_deref()) {
+            (Some(text), Some("base64")) => STANDARD.decode(text).unwrap_or_else(|_| {
+                report.unsupported(&self.request.url, "invalid base64 response body");
+                vec![]
+            }),
+            (Some(text), _) => text.clone().into_bytes(),
+            (None, _) => vec![],
+        };
+
+        Some(Execution {
+            request,
+            response: Response {
+                status: self.response.status,
+                version: self.response.http_version.to_uppercase(),
+                headers: self
+                    .response
+                    .headers
+                    .iter()
+                    .map(|header| Header {
+                        name: header.name.as_str().into(),
+                        value: header.value.as_str().into(),
+                    })
+                    .collect(),
+                body,
+                spilled: None,
+                // HAR records how many bytes compression saved rather than the compressed size.
+                compressed_len: content
+                    .compression
+                    .filter(|saved| *saved > 0)
+                    .map(|saved| (content.size - saved).max(0) as u64),
+                timings: self.timings.to_execution(),
+                duration: Duration::from_secs_f64(millis / 1000.0),
+            },
+            started,
+            tests: vec![],
+            environment: None,
+            sent: None,
+        })
+    }
+
+    /// Builds an entry from an execution, binary bodies are written as base64.
+    pub fn from_execution(execution: &Execution) -> Entry {
+        let req = &execution.request;
+        let res = &execution.response;
+        let millis = res.duration.as_secs_f64() * 1000.0;
+
+        let query_string = req
+            .query_params
+            .iter()
+            .flatten()
+            .collect::<BTreeMap<_, _>>()
+            .into_iter()
+            .map(|(name, value)| NameValue {
+                name: name.clone(),
+                value: value.clone(),
+            })
+            .collect::<Vec<_>>();
+        let url = if query_string.is_empty() {
+            executor::path_url(req)
+        } else {
+            format!(
+                "{}?{}",
+                executor::path_url(req),
+                form_urlencode(query_string.iter().map(|param| (&param.name, &param.value)))
+            )
+        };
+
+        let request_type = header_value(&req.headers, "content-type").unwrap_or_default();
+        let response_type = res.header("content-type").unwrap_or_default().to_string();
+        let (text, encoding) = match std::str::from_utf8(&res.body) {
+            Ok(text) => (text.to_string(), None),
+            Err(_) => (STANDARD.encode(&res.body), Some("base64".to_string())),
+        };
+
+        Entry {
+            started_date_time: execution.started.format(&Rfc3339).unwrap_or_default(),
+            time: millis,
+            request: HarRequest {
+                method: req.method.to_string(),
+                url,
+                http_version: res.version.clone(),
+                cookies: parse_cookies(header_value(&req.headers, "cookie").as_deref()),
+                headers: to_name_values(&req.headers),
+                query_string,
+                post_data: req.body.as_ref().map(|body| PostData {
+                    mime_type: request_type,
+                    params: vec![],
+                    text: Some(body.clone()),
+                    encoding: None,
+                }),
+                headers_size: -1,
+                body_size: req.body.as_ref().map_or(0, |body| body.len() as i64),
+            },
+            response: HarResponse {
+                status: res.status,
+                status_text: reqwest::StatusCode::from_u16(res.status)
+                    .ok()
+                    .and_then(|status| status.canonical_reason())
+                    .unwrap_or_default()
+                    .to_string(),
+                http_version: res.version.clone(),
+                cookies: res
+                    .headers
+                    .iter()
+                    .filter(|header| header.name.eq_ignore_ascii_case("set-cookie"))
+                    .filter_map(|header| parse_set_cookie(&header.value))
+                    .collect(),
+                headers: to_name_values(&res.headers),
+                content: Content {
+                    size: res.body.len() as i64,
+                    compression: res
+                        .compressed_len
+                        .map(|compressed| res.body.len() as i64 - compressed as i64),
+                    mime_type: response_type,
+                    text: Some(text),
+                    encoding,
+                },
+                redirect_url: res.header("location").unwrap_or_default().to_string(),
+                headers_size: -1,
+                body_size: res.compressed_len.unwrap_or(res.body.len() as u64) as i64,
+            },
+            cache: serde_json::json!({}),
+            timings: Timings::from_execution(&res.timings, millis),
+            server_ip_address: None,
+        }
+    }
+}
+
+/// Writes executions as a HAR document.
+pub fn export(executions: &[Execution]) -> Result<String> {
+    let har = Har {
+        log: Log {
+            version: "1.2".into(),
+            creator: Creator {
+                name: "carrier-pigeon".into(),
+                version: env!("CARGO_PKG_VERSION").into(),
+            },
+            entries: executions.iter().map(Entry::from_execution).collect(),
+        },
+    };
+    Ok(serde_json::to_string_pretty(&har)?)
+}
+
+fn header_value(headers: &[Header], name: &str) -> Option<String> {
+    headers
+        .iter()
+        .find(|header| header.name.eq_ignore_ascii_case(name))
+        .map(|header| header.value.to_string())
+}
+
+fn to_name_values(headers: &[Header]) -> Vec<NameValue> {
+    headers
+        .iter()
+        .map(|header| NameValue {
+            name: header.name.to_string(),
+            value: header.value.to_string(),
+        })
+        .collect()
+}
+
+fn cookie_header(cookies: &[Cookie]) -> String {
+    cookies
+        .iter()
+        .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn parse_cookies(header: Option<&str>) -> Vec<Cookie> {
+    header
+        .into_iter()
+        .flat_map(|header| header.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .map(|(name, value)| Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn parse_set_cookie(header: &str) -> Option<Cookie> {
+    let mut parts = header.split(';').map(str::trim);
+    let (name, value) = parts.next()?.split_once('=')?;
+    let mut cookie = Cookie {
+        name: name.to_string(),
+        value: value.to_string(),
+        ..Default::default()
+    };
+    for attr in parts {
+        let (key, value) = attr.split_once('=').unwrap_or((attr, ""));
+        match key.to_ascii_lowercase().as_str() {
+            "path" => cookie.path = Some(value.to_string()),
+            "domain" => cookie.domain = Some(value.to_string()),
+            "expires" => cookie.expires = Some(value.to_string()),
+            "httponly" => cookie.http_only = Some(true),
+            "secure" => cookie.secure = Some(true),
+            _ => {}
+        }
+    }
+    Some(cookie)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HAR: &str = r#"{
+        "log": {
+            "version": "1.2",
+            "creator": { "name": "Firefox", "version": "128" },
+            "entries": [
+                {
+                    "startedDateTime": "2024-05-01T10:00:00.000Z",
+                    "time": 125.5,
+                    "request": {
+                        "method": "POST",
+                        "url": "https://pets.test/pets?dry=true",
+                        "httpVersion": "HTTP/2",
+                        "cookies": [{ "name": "session", "value": "abc" }],
+                        "headers": [
+                            { "name": ":authority", "value": "pets.test" },
+                            { "name": "content-type", "value": "application/json" }
+                        ],
+                        "queryString": [{ "name": "dry", "value": "true" }],
+                        "postData": { "mimeType": "application/json", "text": "eyJuYW1lIjoiUmV4In0=", "encoding": "base64" }
+                    },
+                    "response": {
+                        "status": 201,
+                        "statusText": "Created",
+                        "httpVersion": "HTTP/2",
+                        "headers": [{ "name": "content-type", "value": "image/png" }],
+                        "content": { "size": 4, "mimeType": "image/png", "text": "iVBORw==", "encoding": "base64" }
+                    },
+                    "timings": { "dns": 3, "connect": 20, "ssl": 12, "send": 1, "wait": 120, "receive": 4.5 }
+                }
+            ]
+        }
+    }"#;
+
+    #[test]
+    fn entry_to_request() {
+        let har = parse(HAR).unwrap();
+        let mut report = Report::default();
+        let req = har.log.entries[0].to_request(&mut report).unwrap();
+
+        assert_eq!(req.name, "POST /pets");
+        assert_eq!(req.url, "https://pets.test/pets");
+        assert_eq!(req.body.as_deref(), Some(r#"{"name":"Rex"}"#));
+        assert_eq!(
+            req.query_params.as_ref().unwrap().get("dry"),
+            Some(&"true".to_string())
+        );
+        assert_eq!(req.headers.len(), 2);
+        assert_eq!(req.headers[1].value.as_ref(), "session=abc");
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let har = parse(HAR).unwrap();
+        let mut report = Report::default();
+        let execution = har.log.entries[0].to_execution(&mut report).unwrap();
+
+        assert_eq!(execution.response.body, vec![0x89, b'P', b'N', b'G']);
+        assert_eq!(execution.response.duration, Duration::from_micros(125_500));
+        assert_eq!(
+            execution.response.timings.connect,
+            Some(Duration::from_millis(8))
+        );
+        assert_eq!(
+            execution.response.timings.tls,
+            Some(Duration::from_millis(12))
+        );
+
+        let exported = parse(&export(&[execution]).unwrap()).unwrap();
+        let entry = &exported.log.entries[0];
+        assert_eq!(entry.started_date_time, "2024-05-01T10:00:00Z");
+        assert_eq!(entry.request.url, "https://pets.test/pets?dry=true");
+        assert_eq!(entry.request.cookies[0].value, "abc");
+        assert_eq!(entry.response.content.encoding.as_deref(), Some("base64"));
+        assert_eq!(entry.response.content.text.as_deref(), Some("iVBORw=="));
+        assert_eq!(entry.timings.dns, 3.0);
+        assert_eq!(entry.timings.connect, 20.0);
+        assert_eq!(entry.timings.ssl, 12.0);
+        assert_eq!(entry.timings.wait, 120.0);
+        assert_eq!(entry.timings.receive, 4.5);
+    }
+
+    #[test]
+    fn set_cookie_parsing() {
+        let cookie = parse_set_cookie("id=a3f; Path=/; Secure; HttpOnly").unwrap();
+        assert_eq!(cookie.name, "id");
+        assert_eq!(cookie.path.as_deref(), Some("/"));
+        assert_eq!(cookie.secure, Some(true));
+        assert_eq!(cookie.http_only, Some(true));
+    }
+}
@@ -0,0 +1,547 @@
+//! Reading and writing `.http`/`.rest` files as used by the VS Code REST Client and JetBrains
+//! HTTP client, along with the JetBrains `http-client.env.json` environment files.
+
+use color_eyre::Result;
+use serde_json::{Map, Value};
+use std::{collections::BTreeMap, path::Path};
+
+use super::{join_path, split_query, Report};
+use crate::{
+    collection::Settings, executor, ApiKeyLocation, Auth, Collection, Environment,
+    EnvironmentValue, EnvironmentValues, Header, Method, Request,
+};
+
+pub const PUBLIC_ENV_FILE: &str = "http-client.env.json";
+pub const PRIVATE_ENV_FILE: &str = "http-client.private.env.json";
+/// Environment whose values the JetBrains client adds to every other one, used for collection
+/// variables that can't be written into the `.http` file.
+pub const SHARED_ENV: &str = "$shared";
+
+pub fn is_http_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("http") || ext.eq_ignore_ascii_case("rest"))
+}
+
+#[derive(PartialEq)]
+enum Section {
+    Preamble,
+    Headers,
+    Body,
+}
+
+/// Parses the requests and file variables of a `.http` file.
+pub fn parse(text: &str) -> (Collection, Report) {
+    let mut collection = Collection::default();
+    let mut report = Report::default();
+
+    let mut blocks: Vec<(String, Vec<&str>)> = vec![(String::new(), vec![])];
+    for line in text.lines() {
+        match line.strip_prefix("###") {
+            Some(title) => blocks.push((title.trim().to_string(), vec![])),
+            None => blocks
+                .last_mut()
+                .expect("There is always a block")
+                .1
+                .push(line),
+        }
+    }
+
+    for (idx, (title, lines)) in blocks.into_iter().enumerate() {
+        let location = if title.is_empty() {
+            format!("Request {}", idx)
+        } else {
+            title.clone()
+        };
+        if let Some(req) = parse_block(&mut collection, &mut report, &location, &title, &lines) {
+            collection.requests.push(req);
+        }
+    }
+
+    (collection, report)
+}
+
+fn parse_block(
+    collection: &mut Collection,
+    report: &mut Report,
+    location: &str,
+    title: &str,
+    lines: &[&str],
+) -> Option<Request> {
+    let mut section = Section::Preamble;
+    let mut name: Option<String> = None;
+    let mut folder: Option<String> = None;
+    let mut request_line: Option<String> = None;
+    let mut headers: Vec<Header> = vec![];
+    let mut body: Vec<&str> = vec![];
+
+    for line in lines {
+        let trimmed = line.trim();
+        match section {
+            Section::Preamble => {
+                if trimmed.is_empty() {
+                    continue;
+                }
+                if let Some(comment) = trimmed
+                    .strip_prefix('#')
+                    .or_else(|| trimmed.strip_prefix("//"))
+                {
+                    match comment.trim().split_once(char::is_whitespace) {
+                        Some(("@name", value)) => name = Some(value.trim().to_string()),
+                        Some(("@folder", value)) => folder = Some(value.trim().to_string()),
+                        _ if comment.trim().starts_with('@') => report.unsupported(
+                            location,
+                            format!("'{}' annotation skipped", comment.trim()),
+                        ),
+                        _ => {}
+                    }
+                } else if let Some((key, value)) = trimmed
+                    .strip_prefix('@')
+                    .and_then(|definition| definition.split_once('='))
+                {
+                    collection.variables.insert(
+                        key.trim().to_string(),
+                        EnvironmentValue::Value(value.trim().to_string()),
+                    );
+                } else {
+                    // The protocol version is optional and carries nothing we keep.
+                    let line = match trimmed.rsplit_once(char::is_whitespace) {
+                        Some((line, version)) if version.starts_with("HTTP/") => line.trim_end(),
+                        _ => trimmed,
+                    };
+                    request_line = Some(line.to_string());
+                    section = Section::Headers;
+                }
+            }
+            Section::Headers => {
+                if trimmed.is_empty() {
+                    section = Section::Body;
+                } else if trimmed.starts_with('?') || trimmed.starts_with('&') {
+                    if let Some(request_line) = &mut request_line {
+                        request_line.push_str(trimmed);
+                    }
+                } else if trimmed.starts_with('#') || trimmed.starts_with("//") {
+                    continue;
+                } else if let Some((key, value)) = trimmed.split_once(':') {
+                    headers.push(Header {
+                        name: key.trim().into(),
+                        value: value.trim().into(),
+                    });
+                } else {
+                    report.unsupported(location, format!("invalid header '{}' skipped", trimmed));
+                }
+            }
+            Section::Body => body.push(line),
+        }
+    }
+
+    let request_line = request_line?;
+    let mut parts = request_line.split_whitespace();
+    let first = parts.next()?;
+    let (method, url) = match first.parse::<Method>() {
+        Ok(method) => (method, parts.next().unwrap_or_default().to_string()),
+        Err(_) => (Method::Get, first.to_string()),
+    };
+
+    if let Some(start) = body
+        .iter()
+        .position(|line| line.trim_start().starts_with("> {%"))
+    {
+        report.unsupported(location, "response handler script skipped");
+        body.truncate(start);
+    }
+    while body.last().is_some_and(|line| line.trim().is_empty()) {
+        body.pop();
+    }
+    if body
+        .first()
+        .is_some_and(|line| line.trim_start().starts_with("< "))
+    {
+        report.unsupported(location, "body file reference kept as text");
+    }
+
+    let (url, query) = split_query(&url);
+    let name = name
+        .or_else(|| (!title.is_empty()).then(|| title.to_string()))
+        .unwrap_or_else(|| format!("{} {}", method, url));
+    let mut req = Request::builder()
+        .name(name)
+        .method(method)
+        .url(url)
+        .build();
+    req.headers = headers;
+    req.folder = folder;
+    if !query.is_empty() {
+        req.query_params = Some(query.into_iter().collect());
+    }
+    if !body.is_empty() {
+        req.body = Some(body.join("\n"));
+    }
+    Some(req)
+}
+
+/// Writes a collection as a `.http` file, reporting everything the format has no place for.
+/// Secret collection variables are left out, [`write_environments`] puts them in the private
+/// environment file.
+pub fn write(collection: &Collection) -> (String, Report) {
+    let mut out = String::new();
+    let mut report = Report::default();
+    collection
+        .variables
+        .iter()
+        .filter_map(|(key, value)| match value {
+            EnvironmentValue::Value(value) => Some((key, value)),
+            EnvironmentValue::Secret(_) => None,
+        })
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .for_each(|(key, value)| out.push_str(&format!("@{} = {}\n", key, value)));
+    if collection.pre_request_script.is_some() {
+        report.unsupported("Collection", "pre-request script skipped");
+    }
+    if collection.settings != Settings::default() {
+        report.unsupported("Collection", "settings skipped");
+    }
+
+    for req in &collection.requests {
+        report_unsupported(&mut report, req);
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("### {}\n", req.name));
+        out.push_str(&format!("# @name {}\n", req.name));
+        if let Some(folder) = &req.folder {
+            out.push_str(&format!("# @folder {}\n", folder));
+        }
+
+        let mut query: Vec<(String, String)> = req
+            .query_params
+            .iter()
+            .flatten()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        query.sort();
+        let mut headers: Vec<(String, String)> = req
+            .headers
+            .iter()
+            .map(|header| (header.name.to_string(), header.value.to_string()))
+            .collect();
+        match &req.auth {
+            Some(Auth::Basic { username, password }) => headers.push((
+                "Authorization".into(),
+                format!("Basic {}:{}", username, password),
+            )),
+            Some(Auth::Bearer { token }) => {
+                headers.push(("Authorization".into(), format!("Bearer {}", token)))
+            }
+            Some(Auth::ApiKey {
+                key,
+                value,
+                location: ApiKeyLocation::Header,
+            }) => headers.push((key.clone(), value.clone())),
+            Some(Auth::ApiKey {
+                key,
+                value,
+                location: ApiKeyLocation::Query,
+            }) => query.push((key.clone(), value.clone())),
+            None => {}
+        }
+
+        out.push_str(&format!("{} {}", req.method, executor::path_url(req)));
+        query.iter().enumerate().for_each(|(idx, (key, value))| {
+            out.push_str(&format!(
+                "{}{}={}",
+                if idx == 0 { '?' } else { '&' },
+                key,
+                value
+            ))
+        });
+        out.push('\n');
+        headers
+            .iter()
+            .for_each(|(name, value)| out.push_str(&format!("{}: {}\n", name, value)));
+        if let Some(body) = &req.body {
+            out.push('\n');
+            out.push_str(body);
+            out.push('\n');
+        }
+    }
+    (out, report)
+}
+
+fn report_unsupported(report: &mut Report, req: &Request) {
+    let location = join_path(req.folder.as_deref(), &req.name);
+    if req
+        .path_params
+        .as_ref()
+        .is_some_and(|params| !params.is_empty())
+    {
+        report.unsupported(&location, "path parameters written into the url");
+    }
+    if req.pre_request_script.is_some() {
+        report.unsupported(&location, "pre-request script skipped");
+    }
+    if !req.assertions.is_empty() {
+        report.unsupported(&location, "assertions skipped");
+    }
+    if !req.extractions.is_empty() {
+        report.unsupported(&location, "extractions skipped");
+    }
+    if req.response_filter.is_some() {
+        report.unsupported(&location, "response filter skipped");
+    }
+    if req.compression.is_some() {
+        report.unsupported(&location, "body compression skipped");
+    }
+    if !req.examples.is_empty() {
+        report.unsupported(&location, "saved examples skipped");
+    }
+}
+
+/// Reads JetBrains style environment files, values from the private file are kept as secrets.
+/// The values of the [`SHARED_ENV`] are returned separately, as collection variables.
+pub fn parse_environments(
+    public: Option<&str>,
+    private: Option<&str>,
+) -> Result<(Vec<Environment>, EnvironmentValues)> {
+    let mut envs: Vec<Environment> = vec![];
+    let mut shared = EnvironmentValues::new();
+    for (text, secret) in [(public, false), (private, true)] {
+        let Some(text) = text else {
+            continue;
+        };
+        let parsed: Map<String, Value> = serde_json::from_str(text)?;
+        for (name, values) in parsed {
+            let target = if name == SHARED_ENV {
+                &mut shared
+            } else {
+                let idx = match envs.iter().position(|env| env.name == name) {
+                    Some(idx) => idx,
+                    None => {
+                        envs.push(Environment {
+                            name,
+                            values: Default::default(),
+                        });
+                        envs.len() - 1
+                    }
+                };
+                &mut envs[idx].values
+            };
+            values
+                .as_object()
+                .into_iter()
+                .flatten()
+                .for_each(|(key, value)| {
+                    let value = match value {
+                        Value::String(value) => value.clone(),
+                        value => value.to_string(),
+                    };
+                    target.insert(
+                        key.clone(),
+                        if secret {
+                            EnvironmentValue::Secret(value)
+                        } else {
+                            EnvironmentValue::Value(value)
+                        },
+                    );
+                });
+        }
+    }
+    Ok((envs, shared))
+}
+
+/// Writes the collection's environments as the public and private JetBrains environment files,
+/// `None` when a file would be empty. Secret collection variables go to the private file's
+/// [`SHARED_ENV`].
+pub fn write_environments(collection: &Collection) -> Result<(Option<String>, Option<String>)> {
+    let mut public = Map::new();
+    let mut private = Map::new();
+    let shared: Map<String, Value> = collection
+        .variables
+        .iter()
+        .filter_map(|(key, value)| match value {
+            EnvironmentValue::Secret(value) => Some((key.clone(), Value::String(value.clone()))),
+            EnvironmentValue::Value(_) => None,
+        })
+        .collect();
+    if !shared.is_empty() {
+        private.insert(SHARED_ENV.to_string(), Value::Object(shared));
+    }
+    for env in &collection.environments {
+        let mut public_values = Map::new();
+        let mut private_values = Map::new();
+        env.values
+            .iter()
+            .collect::<BTreeMap<_, _>>()
+            .into_iter()
+            .for_each(|(key, value)| match value {
+                EnvironmentValue::Value(value) => {
+                    public_values.insert(key.clone(), Value::String(value.clone()));
+                }
+                EnvironmentValue::Secret(value) => {
+                    private_values.insert(key.clone(), Value::String(value.clone()));
+                }
+            });
+        public.insert(env.name.clone(), Value::Object(public_values));
+        if !private_values.is_empty() {
+            private.insert(env.name.clone(), Value::Object(private_values));
+        }
+    }
+    Ok((
+        (!public.is_empty())
+            .then(|| serde_json::to_string_pretty(&public))
+            .transpose()?,
+        (!private.is_empty())
+            .then(|| serde_json::to_string_pretty(&private))
+            .transpose()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = r#"@host = https://pets.test
+@token = abc
+
+### List pets
+# @name listPets
+GET {{host}}/pets?limit=10 HTTP/1.1
+    &sort=name
+Accept: application/json
+
+### Create pet
+// @prompt name
+POST {{host}}/pets
+Content-Type: application/json
+Authorization: Bearer {{token}}
+
+{
+    "name": "Rex"
+}
+
+> {% client.global.set("id", response.body.id); %}
+
+###
+{{host}}/health
+"#;
+
+    #[test]
+    fn parse_requests() {
+        let (coll, _) = parse(FILE);
+
+        assert_eq!(coll.requests.len(), 3);
+        let list = &coll.requests[0];
+        assert_eq!(list.name, "listPets");
+        assert_eq!(list.method, Method::Get);
+        assert_eq!(list.url, "{{host}}/pets");
+        assert_eq!(list.query_params.as_ref().unwrap().len(), 2);
+        assert_eq!(list.headers[0].name.as_ref(), "Accept");
+
+        let create = &coll.requests[1];
+        assert_eq!(create.name, "Create pet");
+        assert_eq!(create.body.as_deref(), Some("{\n    \"name\": \"Rex\"\n}"));
+        assert_eq!(create.headers.len(), 2);
+
+        let health = &coll.requests[2];
+        assert_eq!(health.name, "GET {{host}}/health");
+        assert_eq!(health.method, Method::Get);
+    }
+
+    #[test]
+    fn parse_variables_and_report() {
+        let (coll, report) = parse(FILE);
+
+        assert_eq!(
+            coll.variables.get("host"),
+            Some(&EnvironmentValue::Value("https://pets.test".into()))
+        );
+        let messages: Vec<String> = report.entries.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Create pet: '@prompt name' annotation skipped",
+                "Create pet: response handler script skipped",
+            ]
+        );
+    }
+
+    #[test]
+    fn write_round_trip() {
+        let (mut coll, _) = parse(FILE);
+        coll.requests[2].folder = Some("ops".into());
+        coll.requests[2].auth = Some(Auth::ApiKey {
+            key: "key".into(),
+            value: "{{token}}".into(),
+            location: ApiKeyLocation::Query,
+        });
+
+        let (text, report) = write(&coll);
+        assert!(report.is_empty());
+        let (reparsed, report) = parse(&text);
+
+        assert!(report.is_empty());
+        assert_eq!(reparsed.variables, coll.variables);
+        assert_eq!(reparsed.requests[..2], coll.requests[..2]);
+        assert_eq!(reparsed.requests[2].folder.as_deref(), Some("ops"));
+        assert_eq!(
+            reparsed.requests[2]
+                .query_params
+                .as_ref()
+                .unwrap()
+                .get("key"),
+            Some(&"{{token}}".to_string())
+        );
+    }
+
+    #[test]
+    fn environment_files() {
+        let public =
+            r#"{ "dev": { "host": "http://localhost" }, "prod": { "host": "https://pets.test" } }"#;
+        let private = r#"{ "prod": { "token": "secret" } }"#;
+
+        let (envs, shared) = parse_environments(Some(public), Some(private)).unwrap();
+        let prod = envs.iter().find(|env| env.name == "prod").unwrap();
+        assert_eq!(
+            prod.values.get("token"),
+            Some(&EnvironmentValue::Secret("secret".into()))
+        );
+        assert!(shared.is_empty());
+
+        let coll = Collection {
+            environments: envs.clone(),
+            ..Default::default()
+        };
+        let (public, private) = write_environments(&coll).unwrap();
+        let (reparsed, _) = parse_environments(public.as_deref(), private.as_deref()).unwrap();
+        assert_eq!(reparsed, envs);
+    }
+
+    #[test]
+    fn write_keeps_collection_secrets_private() {
+        let (mut coll, _) = parse(FILE);
+        coll.variables
+            .insert("token".into(), EnvironmentValue::Secret("abc".into()));
+        coll.requests[0].pre_request_script = Some("vars.page = 2;".into());
+        coll.requests[0].path_params = Some([("id".into(), "1".into())].into());
+
+        let (text, report) = write(&coll);
+        assert!(!text.contains("@token"));
+        let messages: Vec<String> = report.entries.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "listPets: path parameters written into the url",
+                "listPets: pre-request script skipped",
+            ]
+        );
+
+        let (public, private) = write_environments(&coll).unwrap();
+        assert_eq!(public, None);
+        let (envs, shared) = parse_environments(None, private.as_deref()).unwrap();
+        assert!(envs.is_empty());
+        assert_eq!(
+            shared.get("token"),
+            Some(&EnvironmentValue::Secret("abc".into()))
+        );
+    }
+}
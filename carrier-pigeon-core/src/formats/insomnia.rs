@@ -0,0 +1,635 @@
+//! Import of Insomnia v4 export files into [`Collection`].
+
+use color_eyre::{eyre::bail, Result};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use super::{ensure_content_type, form_urlencode, join_path, split_query, Report};
+use crate::{
+    ApiKeyLocation, Auth, Collection, Environment, EnvironmentValue, EnvironmentValues, Header,
+    Request,
+};
+
+const EXPORT_FORMAT: u64 = 4;
+
+#[derive(Debug, Deserialize)]
+pub struct InsomniaExport {
+    #[serde(rename = "__export_format")]
+    pub export_format: u64,
+    #[serde(default)]
+    pub resources: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Workspace {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestGroup {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub parent_id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub environment: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InsomniaRequest {
+    pub parent_id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub method: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub body: InsomniaBody,
+    #[serde(default)]
+    pub headers: Vec<Param>,
+    #[serde(default)]
+    pub parameters: Vec<Param>,
+    #[serde(default)]
+    pub authentication: InsomniaAuth,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InsomniaBody {
+    pub mime_type: Option<String>,
+    pub text: Option<String>,
+    #[serde(default)]
+    pub params: Vec<Param>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Param {
+    pub name: String,
+    #[serde(default)]
+    pub value: String,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+/// Insomnia stores every auth type in one object, only the fields for `kind` are set.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InsomniaAuth {
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub disabled: bool,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+    #[serde(default)]
+    pub token: String,
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default)]
+    pub key: String,
+    #[serde(default)]
+    pub value: String,
+    pub add_to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InsomniaEnvironment {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub parent_id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub data: Map<String, Value>,
+    #[serde(default)]
+    pub is_private: bool,
+}
+
+/// The result of importing a single Insomnia workspace.
+#[derive(Debug)]
+pub struct InsomniaImport {
+    pub name: String,
+    pub collection: Collection,
+    pub report: Report,
+}
+
+/// Parses an Insomnia v4 export, producing one import per workspace.
+pub fn import_export(json: &str) -> Result<Vec<InsomniaImport>> {
+    let export: InsomniaExport = serde_json::from_str(json)?;
+    if export.export_format != EXPORT_FORMAT {
+        bail!(
+            "Unsupported Insomnia export format {}, only version {} is supported",
+            export.export_format,
+            EXPORT_FORMAT
+        );
+    }
+
+    let mut workspaces = vec![];
+    let mut groups = HashMap::new();
+    let mut requests = vec![];
+    let mut environments = vec![];
+    let mut skipped = vec![];
+    for resource in export.resources {
+        match resource.get("_type").and_then(Value::as_str) {
+            Some("workspace") => workspaces.push(serde_json::from_value::<Workspace>(resource)?),
+            Some("request_group") => {
+                let group: RequestGroup = serde_json::from_value(resource)?;
+                groups.insert(group.id.clone(), group);
+            }
+            Some("request") => requests.push(serde_json::from_value::<InsomniaRequest>(resource)?),
+            Some("environment") => {
+                environments.push(serde_json::from_value::<InsomniaEnvironment>(resource)?)
+            }
+            Some(kind @ ("grpc_request" | "websocket_request")) => skipped.push((
+                resource
+                    .get("parentId")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                resource
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+                kind.to_string(),
+            )),
+            _ => {}
+        }
+    }
+
+    let mut imports: Vec<InsomniaImport> = workspaces
+        .iter()
+        .map(|workspace| InsomniaImport {
+            name: workspace.name.clone(),
+            collection: Collection::default(),
+            report: Report::default(),
+        })
+        .collect();
+    let workspace_index = |id: &str| workspaces.iter().position(|workspace| workspace.id == id);
+
+    for group in groups.values() {
+        let (workspace, folder) = resolve_parent(&groups, group.parent_id.as_deref());
+        if let Some(import) = workspace.and_then(workspace_index).map(|i| &mut imports[i]) {
+            if !group.environment.is_empty() {
+                import.report.unsupported(
+                    join_path(folder.as_deref(), &group.name),
+                    "folder environment values are not supported",
+                );
+            }
+        }
+    }
+
+    for (parent, name, kind) in skipped {
+        let (workspace, folder) = resolve_parent(&groups, parent.as_deref());
+        if let Some(import) = workspace.and_then(workspace_index).map(|i| &mut imports[i]) {
+            import.report.unsupported(
+                join_path(folder.as_deref(), &name),
+                format!("{} skipped", kind.replace('_', " ")),
+            );
+        }
+    }
+
+    for insomnia_req in &requests {
+        let (workspace, folder) = resolve_parent(&groups, insomnia_req.parent_id.as_deref());
+        let Some(import) = workspace.and_then(workspace_index).map(|i| &mut imports[i]) else {
+            continue;
+        };
+        if let Some(req) = convert_request(&mut import.report, insomnia_req, folder) {
+            import.collection.requests.push(req);
+        }
+    }
+
+    // Base environments belong to a workspace, sub-environments belong to a base environment.
+    for env in &environments {
+        let Some(parent) = env.parent_id.as_deref() else {
+            continue;
+        };
+        if let Some(i) = workspace_index(parent) {
+            let import = &mut imports[i];
+            import
+                .collection
+                .variables
+                .extend(convert_values(&mut import.report, &env.name, env));
+        } else if let Some(i) = environments
+            .iter()
+            .find(|base| base.id == parent)
+            .and_then(|base| base.parent_id.as_deref())
+            .and_then(workspace_index)
+        {
+            let import = &mut imports[i];
+            let values = convert_values(&mut import.report, &env.name, env);
+            import.collection.environments.push(Environment {
+                name: env.name.clone(),
+                values,
+            });
+        }
+    }
+
+    Ok(imports)
+}
+
+/// Walks up the request groups above `parent`, returning the workspace id and folder path.
+fn resolve_parent<'a>(
+    groups: &'a HashMap<String, RequestGroup>,
+    mut parent: Option<&'a str>,
+) -> (Option<&'a str>, Option<String>) {
+    let mut folders = vec![];
+    while let Some(group) = parent.and_then(|id| groups.get(id)) {
+        folders.push(group.name.as_str());
+        parent = group.parent_id.as_deref();
+    }
+    folders.reverse();
+    let folder = (!folders.is_empty()).then(|| folders.join("/"));
+    (parent, folder)
+}
+
+fn convert_request(
+    report: &mut Report,
+    insomnia_req: &InsomniaRequest,
+    folder: Option<String>,
+) -> Option<Request> {
+    let location = join_path(folder.as_deref(), &insomnia_req.name);
+    let method = match insomnia_req.method.parse() {
+        Ok(method) => method,
+        Err(err) => {
+            report.unsupported(&location, format!("{}, request skipped", err));
+            return None;
+        }
+    };
+
+    let (url, mut query) = split_query(&convert_template(report, &location, &insomnia_req.url));
+    query.extend(
+        insomnia_req
+            .parameters
+            .iter()
+            .filter(|param| !param.disabled)
+            .map(|param| {
+                (
+                    convert_template(report, &location, &param.name),
+                    convert_template(report, &location, &param.value),
+                )
+            }),
+    );
+
+    let mut req = Request::builder()
+        .name(insomnia_req.name.clone())
+        .method(method)
+        .url(url)
+        .build();
+    if !query.is_empty() {
+        req.query_params = Some(query.into_iter().collect());
+    }
+    req.folder = folder;
+    req.auth = convert_auth(report, &location, &insomnia_req.authentication);
+
+    for header in &insomnia_req.headers {
+        if header.disabled {
+            report.unsupported(
+                &location,
+                format!("disabled header '{}' skipped", header.name),
+            );
+            continue;
+        }
+        req.headers.push(Header {
+            name: convert_template(report, &location, &header.name).into(),
+            value: convert_template(report, &location, &header.value).into(),
+        });
+    }
+
+    req.body = convert_body(report, &location, &insomnia_req.body, &mut req.headers);
+    Some(req)
+}
+
+fn convert_body(
+    report: &mut Report,
+    location: &str,
+    body: &InsomniaBody,
+    headers: &mut Vec<Header>,
+) -> Option<String> {
+    match body.mime_type.as_deref() {
+        Some("application/x-www-form-urlencoded") => {
+            ensure_content_type(headers, "application/x-www-form-urlencoded");
+            Some(form_urlencode(
+                body.params
+                    .iter()
+                    .filter(|param| !param.disabled)
+                    .map(|param| {
+                        (
+                            convert_template(report, location, &param.name),
+                            convert_template(report, location, &param.value),
+                        )
+                    }),
+            ))
+        }
+        Some(mime @ ("multipart/form-data" | "application/octet-stream")) => {
+            report.unsupported(location, format!("'{}' body is not supported", mime));
+            None
+        }
+        mime => {
+            let text = body.text.as_deref()?;
+            match mime {
+                // GraphQL bodies are already stored as a JSON document.
+                Some("application/graphql") => ensure_content_type(headers, "application/json"),
+                Some(mime) if !mime.is_empty() => ensure_content_type(headers, mime),
+                _ => {}
+            }
+            Some(convert_template(report, location, text))
+        }
+    }
+}
+
+fn convert_auth(report: &mut Report, location: &str, auth: &InsomniaAuth) -> Option<Auth> {
+    if auth.disabled {
+        return None;
+    }
+    let mut template = |value: &str| convert_template(report, location, value);
+    let converted = match auth.kind.as_deref()? {
+        "none" => return None,
+        "basic" => Auth::Basic {
+            username: template(&auth.username),
+            password: template(&auth.password),
+        },
+        "bearer" => Auth::Bearer {
+            token: template(&auth.token),
+        },
+        "apikey" => Auth::ApiKey {
+            key: template(&auth.key),
+            value: template(&auth.value),
+            location: match auth.add_to.as_deref() {
+                Some("queryParams") => ApiKeyLocation::Query,
+                _ => ApiKeyLocation::Header,
+            },
+        },
+        kind => {
+            report.unsupported(location, format!("'{}' auth is not supported", kind));
+            return None;
+        }
+    };
+    if auth.kind.as_deref() == Some("bearer") && !matches!(auth.prefix.as_str(), "" | "Bearer") {
+        report.unsupported(
+            location,
+            format!("bearer prefix '{}' replaced with 'Bearer'", auth.prefix),
+        );
+    }
+    Some(converted)
+}
+
+/// Flattens nested environment data into dotted keys, matching how `_.a.b` refers to it.
+fn convert_values(
+    report: &mut Report,
+    location: &str,
+    env: &InsomniaEnvironment,
+) -> EnvironmentValues {
+    let mut values = vec![];
+    flatten(None, &env.data, &mut values);
+    values
+        .into_iter()
+        .map(|(key, value)| {
+            let value = convert_template(report, location, &value);
+            let value = if env.is_private {
+                EnvironmentValue::Secret(value)
+            } else {
+                EnvironmentValue::Value(value)
+            };
+            (key, value)
+        })
+        .collect()
+}
+
+fn flatten(prefix: Option<&str>, data: &Map<String, Value>, out: &mut Vec<(String, String)>) {
+    for (key, value) in data {
+        let key = join_key(prefix, key);
+        match value {
+            Value::Object(nested) => flatten(Some(&key), nested, out),
+            Value::String(value) => out.push((key, value.clone())),
+            Value::Null => out.push((key, String::new())),
+            value => out.push((key, value.to_string())),
+        }
+    }
+}
+
+fn join_key(prefix: Option<&str>, key: &str) -> String {
+    match prefix {
+        Some(prefix) => format!("{}.{}", prefix, key),
+        None => key.to_string(),
+    }
+}
+
+/// Rewrites `{{ _.name }}` references to `{{name}}`, leaving and reporting every other tag.
+pub fn convert_template(report: &mut Report, location: &str, input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = [rest.find("{{"), rest.find("{%")]
+        .into_iter()
+        .flatten()
+        .min()
+    {
+        output.push_str(&rest[..start]);
+        let tag = &rest[start..];
+        let close = if tag.starts_with("{{") { "}}" } else { "%}" };
+        let Some(end) = tag.find(close) else {
+            rest = tag;
+            break;
+        };
+        let inner = tag[2..end].trim();
+        let whole = &tag[..end + 2];
+
+        match variable_name(inner) {
+            Some(name) if close == "}}" => {
+                output.push_str("{{");
+                output.push_str(name);
+                output.push_str("}}");
+            }
+            _ => {
+                let name = if close == "%}" {
+                    inner.split_whitespace().next().unwrap_or_default()
+                } else {
+                    inner
+                };
+                report.unsupported(
+                    location,
+                    format!("template tag '{}' can't be translated", name),
+                );
+                output.push_str(whole);
+            }
+        }
+        rest = &tag[end + 2..];
+    }
+    output.push_str(rest);
+    output
+}
+
+fn variable_name(inner: &str) -> Option<&str> {
+    let name = inner.strip_prefix("_.").unwrap_or(inner);
+    (!name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '-')))
+    .then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Method;
+
+    const EXPORT: &str = r#"{
+        "_type": "export",
+        "__export_format": 4,
+        "resources": [
+            { "_id": "wrk_1", "_type": "workspace", "name": "Pets" },
+            { "_id": "fld_1", "_type": "request_group", "parentId": "wrk_1", "name": "Pets" },
+            {
+                "_id": "fld_2", "_type": "request_group", "parentId": "fld_1", "name": "Admin",
+                "environment": { "admin": true }
+            },
+            {
+                "_id": "req_1", "_type": "request", "parentId": "fld_1", "name": "Get Pet",
+                "method": "GET",
+                "url": "{{ _.host }}/pets/{{ _.pet.id }}?verbose=true",
+                "headers": [
+                    { "name": "X-Request-Id", "value": "{% uuid 'v4' %}" },
+                    { "name": "X-Debug", "value": "1", "disabled": true }
+                ],
+                "parameters": [{ "name": "limit", "value": "10" }],
+                "authentication": { "type": "bearer", "token": "{{ _.token }}" }
+            },
+            {
+                "_id": "req_2", "_type": "request", "parentId": "fld_2", "name": "Create Pet",
+                "method": "POST",
+                "url": "{{ _.host }}/pets",
+                "body": {
+                    "mimeType": "application/x-www-form-urlencoded",
+                    "params": [{ "name": "name", "value": "Rex" }]
+                },
+                "authentication": {
+                    "type": "apikey", "key": "X-Api-Key", "value": "{{ _.apiKey }}", "addTo": "header"
+                }
+            },
+            { "_id": "grpc_1", "_type": "grpc_request", "parentId": "wrk_1", "name": "Stream" },
+            {
+                "_id": "env_1", "_type": "environment", "parentId": "wrk_1", "name": "Base Environment",
+                "data": { "host": "https://pets.test", "pet": { "id": 7 } }
+            },
+            {
+                "_id": "env_2", "_type": "environment", "parentId": "env_1", "name": "Staging",
+                "data": { "host": "https://staging.pets.test" }
+            },
+            {
+                "_id": "env_3", "_type": "environment", "parentId": "env_1", "name": "Secrets",
+                "data": { "token": "abc" }, "isPrivate": true
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn import_workspace() {
+        let imports = import_export(EXPORT).unwrap();
+        assert_eq!(imports.len(), 1);
+        let import = &imports[0];
+        assert_eq!(import.name, "Pets");
+
+        let get = &import.collection.requests[0];
+        assert_eq!(get.method, Method::Get);
+        assert_eq!(get.url, "{{host}}/pets/{{pet.id}}");
+        assert_eq!(get.folder.as_deref(), Some("Pets"));
+        assert_eq!(
+            get.query_params,
+            Some(HashMap::from([
+                ("verbose".into(), "true".into()),
+                ("limit".into(), "10".into())
+            ]))
+        );
+        assert_eq!(get.headers.len(), 1);
+        assert_eq!(&*get.headers[0].value, "{% uuid 'v4' %}");
+        assert_eq!(
+            get.auth,
+            Some(Auth::Bearer {
+                token: "{{token}}".into()
+            })
+        );
+
+        let create = &import.collection.requests[1];
+        assert_eq!(create.folder.as_deref(), Some("Pets/Admin"));
+        assert_eq!(create.body.as_deref(), Some("name=Rex"));
+        assert!(matches!(
+            create.auth,
+            Some(Auth::ApiKey {
+                location: ApiKeyLocation::Header,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn import_environments() {
+        let import = import_export(EXPORT).unwrap().remove(0);
+        let coll = import.collection;
+
+        assert_eq!(
+            coll.variables.get("pet.id"),
+            Some(&EnvironmentValue::Value("7".into()))
+        );
+        assert_eq!(coll.environments.len(), 2);
+        assert_eq!(coll.environments[0].name, "Staging");
+        assert_eq!(
+            coll.environments[0].values.get("host"),
+            Some(&EnvironmentValue::Value("https://staging.pets.test".into()))
+        );
+        assert_eq!(
+            coll.environments[1].values.get("token"),
+            Some(&EnvironmentValue::Secret("abc".into()))
+        );
+    }
+
+    #[test]
+    fn import_report() {
+        let import = import_export(EXPORT).unwrap().remove(0);
+        let mut messages: Vec<String> = import
+            .report
+            .entries
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        messages.sort();
+
+        assert_eq!(
+            messages,
+            vec![
+                "Pets/Admin: folder environment values are not supported",
+                "Pets/Get Pet: disabled header 'X-Debug' skipped",
+                "Pets/Get Pet: template tag 'uuid' can't be translated",
+                "Stream: grpc request skipped",
+            ]
+        );
+    }
+
+    #[test]
+    fn template_tags() {
+        let mut report = Report::default();
+        assert_eq!(
+            convert_template(
+                &mut report,
+                "Req",
+                "{{_.a}}-{{ b }}-{{ _.c | upper }}-{% response 'body', 'req_1', '$.id' %}-{{"
+            ),
+            "{{a}}-{{b}}-{{ _.c | upper }}-{% response 'body', 'req_1', '$.id' %}-{{"
+        );
+        assert_eq!(report.entries.len(), 2);
+        assert_eq!(
+            report.entries[1].message,
+            "template tag 'response' can't be translated"
+        );
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        assert!(import_export(r#"{ "__export_format": 3, "resources": [] }"#).is_err());
+    }
+}
@@ -0,0 +1,718 @@
+//! Generates a [`Collection`] from an OpenAPI 3 specification in either YAML or JSON.
+
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use super::{form_urlencode, Report};
+use crate::{
+    ApiKeyLocation, Auth, Collection, Environment, EnvironmentValue, EnvironmentValues, Header,
+    Method, Request,
+};
+
+/// Name of the environment value every generated url is prefixed with.
+pub const BASE_URL: &str = "baseUrl";
+
+const METHODS: [&str; 7] = ["get", "put", "post", "delete", "options", "head", "patch"];
+const MAX_DEPTH: usize = 8;
+
+/// The result of importing an OpenAPI specification.
+#[derive(Debug)]
+pub struct OpenApiImport {
+    pub name: String,
+    pub collection: Collection,
+    pub report: Report,
+}
+
+/// Parses an OpenAPI 3 specification, creating one request per operation grouped by tag.
+pub fn import_spec(spec: &str) -> Result<OpenApiImport> {
+    // JSON is a subset of YAML so a single parser covers both.
+    let spec: Value = serde_yaml::from_str(spec)?;
+    match spec.get("openapi").and_then(Value::as_str) {
+        Some(version) if version.starts_with("3.") => {}
+        Some(version) => bail!("Unsupported OpenAPI version: {}", version),
+        None => bail!("Document is not an OpenAPI 3 specification"),
+    }
+
+    let spec = Spec { root: &spec };
+    let mut report = Report::default();
+    let name = spec
+        .root
+        .pointer("/info/title")
+        .and_then(Value::as_str)
+        .unwrap_or("OpenAPI")
+        .to_string();
+
+    let environments = spec.environments(&mut report);
+    let requests = spec.requests(&mut report);
+
+    Ok(OpenApiImport {
+        name,
+        collection: Collection {
+            requests,
+            environments,
+            ..Default::default()
+        },
+        report,
+    })
+}
+
+/// Applies a fresh import of a specification to a collection that was previously generated from it.
+///
+/// Requests are matched on their `operationId`, or on method and url for operations without one.
+/// Matched requests keep any local edits and only gain the parameters and headers that are new in
+/// the specification, unmatched requests are appended.
+/// Environments are matched on name in the same way.
+pub fn update(existing: &mut Collection, imported: Collection) {
+    for req in imported.requests {
+        let matched = existing.requests.iter_mut().find(|current| {
+            match (&current.operation_id, &req.operation_id) {
+                (Some(current), Some(imported)) => current == imported,
+                _ => current.method == req.method && current.url == req.url,
+            }
+        });
+        match matched {
+            Some(current) => {
+                if current.operation_id.is_none() {
+                    current.operation_id = req.operation_id;
+                }
+                merge_params(&mut current.path_params, req.path_params);
+                merge_params(&mut current.query_params, req.query_params);
+                req.headers.into_iter().for_each(|header| {
+                    if !current
+                        .headers
+                        .iter()
+                        .any(|cur| cur.name.eq_ignore_ascii_case(&header.name))
+                    {
+                        current.headers.push(header);
+                    }
+                });
+                if current.body.is_none() {
+                    current.body = req.body;
+                }
+                if current.auth.is_none() {
+                    current.auth = req.auth;
+                }
+            }
+            None => existing.requests.push(req),
+        }
+    }
+
+    for env in imported.environments {
+        match existing
+            .environments
+            .iter_mut()
+            .find(|current| current.name == env.name)
+        {
+            Some(current) => env.values.into_iter().for_each(|(key, value)| {
+                current.values.entry(key).or_insert(value);
+            }),
+            None => existing.environments.push(env),
+        }
+    }
+
+    imported.variables.into_iter().for_each(|(key, value)| {
+        existing.variables.entry(key).or_insert(value);
+    });
+}
+
+fn merge_params(
+    current: &mut Option<HashMap<String, String>>,
+    imported: Option<HashMap<String, String>>,
+) {
+    if let Some(imported) = imported {
+        let current = current.get_or_insert_with(HashMap::new);
+        imported.into_iter().for_each(|(key, value)| {
+            current.entry(key).or_insert(value);
+        });
+    }
+}
+
+struct Spec<'a> {
+    root: &'a Value,
+}
+
+impl<'a> Spec<'a> {
+    /// Follows local `$ref` pointers, external references are returned unresolved.
+    fn resolve(&self, mut value: &'a Value) -> &'a Value {
+        for _ in 0..MAX_DEPTH {
+            match value
+                .get("$ref")
+                .and_then(Value::as_str)
+                .and_then(|reference| reference.strip_prefix('#'))
+                .and_then(|pointer| self.root.pointer(pointer))
+            {
+                Some(target) => value = target,
+                None => break,
+            }
+        }
+        value
+    }
+
+    fn environments(&self, report: &mut Report) -> Vec<Environment> {
+        let Some(servers) = self.root.get("servers").and_then(Value::as_array) else {
+            report.unsupported("servers", "no servers defined, set baseUrl manually");
+            return vec![];
+        };
+
+        servers
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, server)| {
+                let url = server.get("url").and_then(Value::as_str)?;
+                let url = server
+                    .get("variables")
+                    .and_then(Value::as_object)
+                    .into_iter()
+                    .flatten()
+                    .fold(url.to_string(), |url, (name, var)| {
+                        let default = var.get("default").and_then(Value::as_str).unwrap_or("");
+                        url.replace(&format!("{{{}}}", name), default)
+                    });
+                let name = server
+                    .get("description")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("Server {}", idx + 1));
+
+                let mut values = EnvironmentValues::new();
+                values.insert(
+                    BASE_URL.to_string(),
+                    EnvironmentValue::Value(url.trim_end_matches('/').to_string()),
+                );
+                Some(Environment { name, values })
+            })
+            .collect()
+    }
+
+    fn requests(&self, report: &mut Report) -> Vec<Request> {
+        let Some(paths) = self.root.get("paths").and_then(Value::as_object) else {
+            return vec![];
+        };
+
+        paths
+            .iter()
+            .flat_map(|(path, item)| {
+                let item = self.resolve(item);
+                let shared = item
+                    .get("parameters")
+                    .and_then(Value::as_array)
+                    .cloned()
+                    .unwrap_or_default();
+                METHODS
+                    .iter()
+                    .filter_map(|method| item.get(*method).map(|op| (*method, op)))
+                    .map(|(method, op)| self.request(report, path, method, op, &shared))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    fn request(
+        &self,
+        report: &mut Report,
+        path: &str,
+        method: &str,
+        op: &Value,
+        shared_params: &[Value],
+    ) -> Request {
+        let method: Method = method.parse().expect("Only known methods are visited");
+        let operation_id = op
+            .get("operationId")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let name = op
+            .get("operationId")
+            .or_else(|| op.get("summary"))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{} {}", method, path));
+        let location = format!("{} {}", method, path);
+
+        let mut req = Request::builder()
+            .name(name)
+            .method(method)
+            .url(format!("{{{{{}}}}}{}", BASE_URL, path))
+            .build();
+        req.operation_id = operation_id;
+        req.folder = op
+            .pointer("/tags/0")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        let mut params: Vec<&Value> = shared_params.iter().map(|p| self.resolve(p)).collect();
+        op.get("parameters")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .map(|param| self.resolve(param))
+            .for_each(|param| {
+                params.retain(|existing| {
+                    existing.get("name") != param.get("name")
+                        || existing.get("in") != param.get("in")
+                });
+                params.push(param);
+            });
+
+        let mut path_params = HashMap::new();
+        let mut query_params = HashMap::new();
+        for param in params {
+            let Some(name) = param.get("name").and_then(Value::as_str) else {
+                continue;
+            };
+            let value = self.param_value(report, &location, param);
+            match param.get("in").and_then(Value::as_str) {
+                Some("path") => {
+                    path_params.insert(name.to_string(), value);
+                }
+                Some("query") => {
+                    query_params.insert(name.to_string(), value);
+                }
+                Some("header") => req.headers.push(Header {
+                    name: name.into(),
+                    value: value.into(),
+                }),
+                Some(kind) => report.unsupported(
+                    &location,
+                    format!("'{}' parameter '{}' skipped", kind, name),
+                ),
+                None => {}
+            }
+        }
+        req.path_params = (!path_params.is_empty()).then_some(path_params);
+        req.query_params = (!query_params.is_empty()).then_some(query_params);
+
+        if let Some(body) = op.get("requestBody").map(|body| self.resolve(body)) {
+            self.body(report, &location, &mut req, body);
+        }
+
+        req.auth = self.auth(report, &location, op);
+        req
+    }
+
+    /// The value of one of several named `examples`, the one named `default` if there is one or
+    /// else the first as written in the spec, which is reported.
+    fn example(
+        &self,
+        report: &mut Report,
+        location: &str,
+        examples: &'a Value,
+    ) -> Option<&'a Value> {
+        let examples = examples.as_object()?;
+        let (name, example) = examples
+            .get_key_value("default")
+            .or_else(|| examples.iter().next())?;
+        if examples.len() > 1 && name != "default" {
+            report.unsupported(
+                location,
+                format!("{} examples, used '{}'", examples.len(), name),
+            );
+        }
+        self.resolve(example).get("value")
+    }
+
+    fn param_value(&self, report: &mut Report, location: &str, param: &'a Value) -> String {
+        let example = param
+            .get("example")
+            .or_else(|| self.example(report, location, param.get("examples")?))
+            .or_else(|| {
+                param
+                    .get("schema")
+                    .map(|schema| self.resolve(schema))
+                    .and_then(|schema| {
+                        schema
+                            .get("example")
+                            .or_else(|| schema.get("default"))
+                            .or_else(|| schema.pointer("/enum/0"))
+                    })
+            });
+        match example {
+            Some(Value::String(value)) => value.clone(),
+            Some(Value::Null) | None => String::new(),
+            Some(value) => value.to_string(),
+        }
+    }
+
+    fn body(&self, report: &mut Report, location: &str, req: &mut Request, body: &Value) {
+        let Some(content) = body.get("content").and_then(Value::as_object) else {
+            return;
+        };
+        let Some((content_type, media)) = content
+            .iter()
+            .find(|(content_type, _)| is_json(content_type))
+            .or_else(|| content.iter().next())
+        else {
+            return;
+        };
+
+        let example = media
+            .get("example")
+            .or_else(|| self.example(report, location, media.get("examples")?))
+            .cloned()
+            .or_else(|| media.get("schema").map(|schema| self.sample(schema, 0)));
+
+        let body = match (content_type.as_str(), example) {
+            (content_type, Some(example)) if is_json(content_type) => {
+                serde_json::to_string_pretty(&example).ok()
+            }
+            ("application/x-www-form-urlencoded", Some(Value::Object(fields))) => {
+                Some(form_urlencode(fields.iter().map(|(key, value)| {
+                    let value = match value {
+                        Value::String(value) => value.clone(),
+                        value => value.to_string(),
+                    };
+                    (key, value)
+                })))
+            }
+            (content_type, Some(Value::String(text))) if content_type.starts_with("text/") => {
+                Some(text)
+            }
+            (content_type, _) => {
+                report.unsupported(
+                    location,
+                    format!("'{}' request body was not generated", content_type),
+                );
+                None
+            }
+        };
+
+        if body.is_some() {
+            req.headers.push(Header {
+                name: "Content-Type".into(),
+                value: content_type.as_str().into(),
+            });
+            req.body = body;
+        }
+    }
+
+    fn auth(&self, report: &mut Report, location: &str, op: &Value) -> Option<Auth> {
+        let requirement = op
+            .get("security")
+            .or_else(|| self.root.get("security"))
+            .and_then(Value::as_array)?
+            .first()?
+            .as_object()?;
+        let scheme_name = requirement.keys().next()?;
+        let scheme = self
+            .root
+            .pointer(&format!("/components/securitySchemes/{}", scheme_name))
+            .map(|scheme| self.resolve(scheme))?;
+        let field = |key: &str| scheme.get(key).and_then(Value::as_str).unwrap_or("");
+
+        match (field("type"), field("scheme").to_ascii_lowercase().as_str()) {
+            ("http", "bearer") => Some(Auth::Bearer {
+                token: "{{token}}".into(),
+            }),
+            ("http", "basic") => Some(Auth::Basic {
+                username: "{{username}}".into(),
+                password: "{{password}}".into(),
+            }),
+            ("apiKey", _) if field("in") != "cookie" => Some(Auth::ApiKey {
+                key: field("name").into(),
+                value: "{{apiKey}}".into(),
+                location: if field("in") == "query" {
+                    ApiKeyLocation::Query
+                } else {
+                    ApiKeyLocation::Header
+                },
+            }),
+            (kind, _) => {
+                report.unsupported(
+                    location,
+                    format!("'{}' security scheme '{}' skipped", kind, scheme_name),
+                );
+                None
+            }
+        }
+    }
+
+    /// Generates an example value for a schema when the spec does not provide one.
+    fn sample(&self, schema: &Value, depth: usize) -> Value {
+        if depth > MAX_DEPTH {
+            return Value::Null;
+        }
+        let schema = self.resolve(schema);
+        if let Some(example) = schema
+            .get("example")
+            .or_else(|| schema.get("default"))
+            .or_else(|| schema.pointer("/enum/0"))
+        {
+            return example.clone();
+        }
+        if let Some(all_of) = schema.get("allOf").and_then(Value::as_array) {
+            let merged = all_of
+                .iter()
+                .filter_map(|part| match self.sample(part, depth + 1) {
+                    Value::Object(fields) => Some(fields),
+                    _ => None,
+                })
+                .fold(Map::new(), |mut merged, fields| {
+                    merged.extend(fields);
+                    merged
+                });
+            return Value::Object(merged);
+        }
+        if let Some(first) = schema
+            .pointer("/oneOf/0")
+            .or_else(|| schema.pointer("/anyOf/0"))
+        {
+            return self.sample(first, depth + 1);
+        }
+
+        let kind = match schema.get("type") {
+            Some(Value::String(kind)) => kind.as_str(),
+            Some(Value::Array(kinds)) => kinds
+                .iter()
+                .filter_map(Value::as_str)
+                .find(|kind| *kind != "null")
+                .unwrap_or("null"),
+            _ if schema.get("properties").is_some() => "object",
+            _ => "null",
+        };
+        match kind {
+            "object" => Value::Object(
+                schema
+                    .get("properties")
+                    .and_then(Value::as_object)
+                    .into_iter()
+                    .flatten()
+                    .map(|(key, prop)| (key.clone(), self.sample(prop, depth + 1)))
+                    .collect(),
+            ),
+            "array" => Value::Array(
+                schema
+                    .get("items")
+                    .map(|items| vec![self.sample(items, depth + 1)])
+                    .unwrap_or_default(),
+            ),
+            "string" => Value::String(
+                match schema.get("format").and_then(Value::as_str) {
+                    Some("date-time") => "1970-01-01T00:00:00Z",
+                    Some("date") => "1970-01-01",
+                    Some("uuid") => "00000000-0000-0000-0000-000000000000",
+                    Some("email") => "user@example.com",
+                    Some("uri") | Some("url") => "https://example.com",
+                    _ => "string",
+                }
+                .to_string(),
+            ),
+            "integer" => schema.get("minimum").cloned().unwrap_or(Value::from(0)),
+            "number" => schema.get("minimum").cloned().unwrap_or(Value::from(0.0)),
+            "boolean" => Value::Bool(false),
+            _ => Value::Null,
+        }
+    }
+}
+
+fn is_json(content_type: &str) -> bool {
+    content_type == "application/json" || content_type.ends_with("+json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = r#"
+openapi: 3.0.3
+info:
+  title: Petstore
+  version: 1.0.0
+servers:
+  - url: https://{region}.pets.test/v1/
+    description: Production
+    variables:
+      region:
+        default: eu
+  - url: http://localhost:8080
+security:
+  - bearer: []
+paths:
+  /pets/{petId}:
+    parameters:
+      - name: petId
+        in: path
+        required: true
+        schema:
+          type: integer
+          example: 42
+    get:
+      operationId: getPet
+      tags: [pets]
+      parameters:
+        - name: verbose
+          in: query
+          schema:
+            type: boolean
+            default: false
+        - name: X-Trace
+          in: header
+          example: abc
+        - name: session
+          in: cookie
+    put:
+      operationId: updatePet
+      tags: [pets]
+      security: []
+      requestBody:
+        content:
+          application/json:
+            schema:
+              $ref: '#/components/schemas/Pet'
+components:
+  securitySchemes:
+    bearer:
+      type: http
+      scheme: bearer
+  schemas:
+    Pet:
+      type: object
+      properties:
+        name:
+          type: string
+        born:
+          type: string
+          format: date
+        tags:
+          type: array
+          items:
+            type: string
+"#;
+
+    fn request<'a>(coll: &'a Collection, name: &str) -> &'a Request {
+        coll.requests.iter().find(|req| req.name == name).unwrap()
+    }
+
+    #[test]
+    fn servers_become_environments() {
+        let import = import_spec(SPEC).unwrap();
+        let envs = &import.collection.environments;
+
+        assert_eq!(import.name, "Petstore");
+        assert_eq!(envs.len(), 2);
+        assert_eq!(envs[0].name, "Production");
+        assert_eq!(
+            envs[0].values.get(BASE_URL),
+            Some(&EnvironmentValue::Value("https://eu.pets.test/v1".into()))
+        );
+        assert_eq!(envs[1].name, "Server 2");
+    }
+
+    #[test]
+    fn operations_become_requests() {
+        let import = import_spec(SPEC).unwrap();
+        let get = request(&import.collection, "getPet");
+
+        assert_eq!(get.method, Method::Get);
+        assert_eq!(get.url, "{{baseUrl}}/pets/{petId}");
+        assert_eq!(get.folder.as_deref(), Some("pets"));
+        assert_eq!(
+            get.path_params.as_ref().unwrap().get("petId"),
+            Some(&"42".to_string())
+        );
+        assert_eq!(
+            get.query_params.as_ref().unwrap().get("verbose"),
+            Some(&"false".to_string())
+        );
+        assert_eq!(get.headers[0].value.as_ref(), "abc");
+        assert_eq!(
+            get.auth,
+            Some(Auth::Bearer {
+                token: "{{token}}".into()
+            })
+        );
+        assert_eq!(
+            import.report.entries[0].to_string(),
+            "GET /pets/{petId}: 'cookie' parameter 'session' skipped"
+        );
+    }
+
+    #[test]
+    fn body_generated_from_schema() {
+        let import = import_spec(SPEC).unwrap();
+        let put = request(&import.collection, "updatePet");
+        let body: Value = serde_json::from_str(put.body.as_ref().unwrap()).unwrap();
+
+        assert_eq!(put.auth, None);
+        assert_eq!(
+            body,
+            serde_json::json!({ "name": "string", "born": "1970-01-01", "tags": ["string"] })
+        );
+    }
+
+    #[test]
+    fn reimport_keeps_edits() {
+        let mut coll = import_spec(SPEC).unwrap().collection;
+        let get = coll
+            .requests
+            .iter_mut()
+            .find(|req| req.name == "getPet")
+            .unwrap();
+        get.name = "Fetch pet".into();
+        get.url = "{{baseUrl}}/v2/pets/{petId}".into();
+        get.query_params = None;
+        coll.environments[0].values.insert(
+            BASE_URL.into(),
+            EnvironmentValue::Value("https://mine".into()),
+        );
+
+        let spec = SPEC.replace(
+            "  /pets/{petId}:",
+            "  /health:\n    get:\n      operationId: health\n  /pets/{petId}:",
+        );
+        update(&mut coll, import_spec(&spec).unwrap().collection);
+
+        assert_eq!(coll.requests.len(), 3);
+        let get = request(&coll, "Fetch pet");
+        assert_eq!(
+            get.query_params.as_ref().unwrap().get("verbose"),
+            Some(&"false".to_string())
+        );
+        assert_eq!(
+            coll.environments[0].values.get(BASE_URL),
+            Some(&EnvironmentValue::Value("https://mine".into()))
+        );
+        assert!(coll.requests.iter().any(|req| req.name == "health"));
+    }
+
+    #[test]
+    fn picks_default_or_first_example() {
+        let spec = r#"
+openapi: 3.0.3
+info:
+  title: Pets
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      parameters:
+        - name: species
+          in: query
+          examples:
+            zebra: { value: zebra }
+            default: { value: dog }
+        - name: sort
+          in: query
+          examples:
+            newest: { value: -born }
+            name: { value: name }
+"#;
+        let import = import_spec(spec).unwrap();
+        let params = request(&import.collection, "listPets")
+            .query_params
+            .clone()
+            .unwrap();
+
+        assert_eq!(params.get("species"), Some(&"dog".to_string()));
+        assert_eq!(params.get("sort"), Some(&"-born".to_string()));
+        assert_eq!(
+            import.report.entries.last().unwrap().to_string(),
+            "GET /pets: 2 examples, used 'newest'"
+        );
+    }
+
+    #[test]
+    fn rejects_swagger() {
+        assert!(import_spec("swagger: '2.0'\ninfo:\n  title: Old\n").is_err());
+    }
+}
@@ -0,0 +1,911 @@
+//! Conversion between Postman v2.1 collection/environment exports and [`Collection`].
+
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+use super::{ensure_content_type, form_urlencode, join_path, split_query, Report};
+use crate::{
+    ApiKeyLocation, Auth, Collection, Environment, EnvironmentValue, EnvironmentValues, Header,
+    Method, Request,
+};
+
+const SCHEMA: &str = "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct PostmanCollection {
+    pub info: Info,
+    #[serde(default)]
+    pub item: Vec<Item>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<PostmanAuth>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub event: Vec<Event>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variable: Vec<KeyValue>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Info {
+    pub name: String,
+    #[serde(
+        rename = "_postman_id",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub postman_id: Option<String>,
+    #[serde(default)]
+    pub schema: String,
+}
+
+/// Either a folder, when `item` is present, or a single request.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Item {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item: Option<Vec<Item>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<RequestSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<PostmanAuth>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub event: Vec<Event>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub response: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum RequestSpec {
+    Url(String),
+    Request(Box<PostmanRequest>),
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct PostmanRequest {
+    #[serde(default)]
+    pub method: Option<String>,
+    #[serde(default)]
+    pub header: Vec<KeyValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Body>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<UrlSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<PostmanAuth>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum UrlSpec {
+    Raw(String),
+    Detailed(Box<PostmanUrl>),
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct PostmanUrl {
+    #[serde(default)]
+    pub raw: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub query: Vec<KeyValue>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variable: Vec<KeyValue>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct KeyValue {
+    pub key: String,
+    #[serde(default)]
+    pub value: Option<Value>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub disabled: bool,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+}
+
+impl KeyValue {
+    fn new(key: &str, value: &str) -> Self {
+        KeyValue {
+            key: key.to_string(),
+            value: Some(Value::String(value.to_string())),
+            ..Default::default()
+        }
+    }
+
+    fn value_string(&self) -> String {
+        match &self.value {
+            Some(Value::String(value)) => value.clone(),
+            Some(Value::Null) | None => String::new(),
+            Some(value) => value.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Body {
+    pub mode: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub urlencoded: Vec<KeyValue>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub formdata: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub graphql: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<Value>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct PostmanAuth {
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Attributes for the auth type, keyed by the type name e.g. `"bearer": [...]`.
+    #[serde(flatten)]
+    pub attributes: HashMap<String, Value>,
+}
+
+impl PostmanAuth {
+    fn attribute(&self, key: &str) -> String {
+        self.attributes
+            .get(&self.kind)
+            .and_then(Value::as_array)
+            .and_then(|attrs| {
+                attrs
+                    .iter()
+                    .find(|attr| attr.get("key").and_then(Value::as_str) == Some(key))
+            })
+            .and_then(|attr| attr.get("value"))
+            .map(|value| match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            })
+            .unwrap_or_default()
+    }
+
+    fn with_attributes(kind: &str, attrs: &[(&str, &str)]) -> Self {
+        let attrs = attrs
+            .iter()
+            .map(|(key, value)| serde_json::json!({ "key": key, "value": value, "type": "string" }))
+            .collect();
+        let mut attributes = HashMap::new();
+        attributes.insert(kind.to_string(), Value::Array(attrs));
+        PostmanAuth {
+            kind: kind.to_string(),
+            attributes,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Event {
+    pub listen: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<Value>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct PostmanEnvironment {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub values: Vec<EnvironmentEntry>,
+    #[serde(
+        rename = "_postman_variable_scope",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct EnvironmentEntry {
+    pub key: String,
+    #[serde(default)]
+    pub value: String,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// The result of importing a Postman collection.
+#[derive(Debug)]
+pub struct PostmanImport {
+    pub name: String,
+    pub collection: Collection,
+    pub report: Report,
+}
+
+/// Parses a Postman v2.1 collection export.
+pub fn import_collection(json: &str) -> Result<PostmanImport> {
+    let postman: PostmanCollection = serde_json::from_str(json)?;
+    let mut report = Report::default();
+    let mut collection = Collection::default();
+
+    postman.event.iter().for_each(|event| {
+        report_event(&mut report, &postman.info.name, event);
+    });
+
+    postman
+        .variable
+        .iter()
+        .filter(|var| !var.disabled)
+        .for_each(|var| {
+            let value = match var.kind.as_deref() {
+                Some("secret") => EnvironmentValue::Secret(var.value_string()),
+                _ => EnvironmentValue::Value(var.value_string()),
+            };
+            collection.variables.insert(var.key.clone(), value);
+        });
+
+    let auth = convert_auth(&mut report, &postman.info.name, postman.auth.as_ref());
+    import_items(
+        &mut collection.requests,
+        &mut report,
+        &postman.item,
+        None,
+        &auth,
+    );
+
+    Ok(PostmanImport {
+        name: postman.info.name,
+        collection,
+        report,
+    })
+}
+
+/// Parses a Postman environment export.
+pub fn import_environment(json: &str) -> Result<Environment> {
+    let postman: PostmanEnvironment = serde_json::from_str(json)?;
+    let values: EnvironmentValues = postman
+        .values
+        .into_iter()
+        .filter(|entry| entry.enabled)
+        .map(|entry| {
+            let value = if entry.kind.as_deref() == Some("secret") {
+                EnvironmentValue::Secret(entry.value)
+            } else {
+                EnvironmentValue::Value(entry.value)
+            };
+            (entry.key, value)
+        })
+        .collect();
+
+    Ok(Environment {
+        name: postman.name,
+        values,
+    })
+}
+
+fn import_items(
+    requests: &mut Vec<Request>,
+    report: &mut Report,
+    items: &[Item],
+    folder: Option<&str>,
+    inherited_auth: &Option<Auth>,
+) {
+    for item in items {
+        let location = join_path(folder, &item.name);
+        item.event
+            .iter()
+            .for_each(|event| report_event(report, &location, event));
+
+        if let Some(children) = &item.item {
+            let auth = if item.auth.is_some() {
+                convert_auth(report, &location, item.auth.as_ref())
+            } else {
+                inherited_auth.clone()
+            };
+            import_items(requests, report, children, Some(&location), &auth);
+        } else if let Some(spec) = &item.request {
+            if !item.response.is_empty() {
+                report.unsupported(
+                    &location,
+                    format!("{} saved example response(s) skipped", item.response.len()),
+                );
+            }
+            if let Some(req) =
+                convert_request(report, &location, item, spec, folder, inherited_auth)
+            {
+                requests.push(req);
+            }
+        }
+    }
+}
+
+fn convert_request(
+    report: &mut Report,
+    location: &str,
+    item: &Item,
+    spec: &RequestSpec,
+    folder: Option<&str>,
+    inherited_auth: &Option<Auth>,
+) -> Option<Request> {
+    let postman_req = match spec {
+        RequestSpec::Url(url) => {
+            return Some(build_request(
+                item.name.clone(),
+                Method::Get,
+                url,
+                folder,
+                inherited_auth.clone(),
+            ))
+        }
+        RequestSpec::Request(req) => req,
+    };
+
+    let method = match postman_req.method.as_deref().unwrap_or("GET").parse() {
+        Ok(method) => method,
+        Err(err) => {
+            report.unsupported(location, format!("{}, request skipped", err));
+            return None;
+        }
+    };
+
+    let auth = if postman_req.auth.is_some() {
+        convert_auth(report, location, postman_req.auth.as_ref())
+    } else {
+        inherited_auth.clone()
+    };
+
+    let (url, query, variables) = match &postman_req.url {
+        Some(UrlSpec::Raw(raw)) => (raw.clone(), None, &[][..]),
+        Some(UrlSpec::Detailed(url)) => (
+            url.raw.clone().unwrap_or_else(|| raw_from_parts(url)),
+            Some(&url.query),
+            &url.variable[..],
+        ),
+        None => (String::new(), None, &[][..]),
+    };
+
+    let mut req = build_request(item.name.clone(), method, &url, folder, auth);
+
+    if let Some(query) = query {
+        let params: HashMap<String, String> = query
+            .iter()
+            .filter(|param| !param.disabled)
+            .map(|param| (param.key.clone(), param.value_string()))
+            .collect();
+        req.query_params = if params.is_empty() {
+            None
+        } else {
+            Some(params)
+        };
+    }
+
+    if !variables.is_empty() {
+        req.path_params = Some(
+            variables
+                .iter()
+                .map(|var| (var.key.clone(), var.value_string()))
+                .collect(),
+        );
+    }
+
+    req.headers = postman_req
+        .header
+        .iter()
+        .filter(|header| {
+            if header.disabled {
+                report.unsupported(
+                    location,
+                    format!("disabled header '{}' skipped", header.key),
+                );
+            }
+            !header.disabled
+        })
+        .map(|header| Header {
+            name: header.key.as_str().into(),
+            value: header.value_string().into(),
+        })
+        .collect();
+
+    if let Some(body) = &postman_req.body {
+        req.body = convert_body(report, location, body, &mut req.headers);
+    }
+
+    Some(req)
+}
+
+fn build_request(
+    name: String,
+    method: Method,
+    url: &str,
+    folder: Option<&str>,
+    auth: Option<Auth>,
+) -> Request {
+    let (url, query) = split_query(url);
+    let mut req = Request::builder()
+        .name(name)
+        .method(method)
+        .url(url)
+        .build();
+    if !query.is_empty() {
+        req.query_params = Some(query.into_iter().collect());
+    }
+    req.folder = folder.map(str::to_string);
+    req.auth = auth;
+    req
+}
+
+fn raw_from_parts(url: &PostmanUrl) -> String {
+    let join = |value: &Option<Value>, sep: &str| match value {
+        Some(Value::String(value)) => value.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(sep),
+        _ => String::new(),
+    };
+
+    let mut raw = String::new();
+    if let Some(protocol) = &url.protocol {
+        raw.push_str(protocol);
+        raw.push_str("://");
+    }
+    raw.push_str(&join(&url.host, "."));
+    if let Some(port) = &url.port {
+        raw.push(':');
+        raw.push_str(port);
+    }
+    let path = join(&url.path, "/");
+    if !path.is_empty() {
+        raw.push('/');
+        raw.push_str(path.trim_start_matches('/'));
+    }
+    raw
+}
+
+fn convert_body(
+    report: &mut Report,
+    location: &str,
+    body: &Body,
+    headers: &mut Vec<Header>,
+) -> Option<String> {
+    match body.mode.as_str() {
+        "raw" => body.raw.clone(),
+        "urlencoded" => {
+            ensure_content_type(headers, "application/x-www-form-urlencoded");
+            Some(form_urlencode(
+                body.urlencoded
+                    .iter()
+                    .filter(|field| !field.disabled)
+                    .map(|field| (&field.key, field.value_string())),
+            ))
+        }
+        "graphql" => {
+            ensure_content_type(headers, "application/json");
+            let graphql = body.graphql.clone().unwrap_or_default();
+            let variables = graphql
+                .get("variables")
+                .and_then(Value::as_str)
+                .and_then(|vars| serde_json::from_str::<Value>(vars).ok())
+                .unwrap_or(Value::Null);
+            Some(
+                serde_json::json!({
+                    "query": graphql.get("query").cloned().unwrap_or_default(),
+                    "variables": variables,
+                })
+                .to_string(),
+            )
+        }
+        mode => {
+            report.unsupported(location, format!("'{}' body mode is not supported", mode));
+            None
+        }
+    }
+}
+
+fn convert_auth(report: &mut Report, location: &str, auth: Option<&PostmanAuth>) -> Option<Auth> {
+    let auth = auth?;
+    match auth.kind.as_str() {
+        "noauth" => None,
+        "basic" => Some(Auth::Basic {
+            username: auth.attribute("username"),
+            password: auth.attribute("password"),
+        }),
+        "bearer" => Some(Auth::Bearer {
+            token: auth.attribute("token"),
+        }),
+        "apikey" => Some(Auth::ApiKey {
+            key: auth.attribute("key"),
+            value: auth.attribute("value"),
+            location: if auth.attribute("in") == "query" {
+                ApiKeyLocation::Query
+            } else {
+                ApiKeyLocation::Header
+            },
+        }),
+        kind => {
+            report.unsupported(location, format!("'{}' auth is not supported", kind));
+            None
+        }
+    }
+}
+
+fn report_event(report: &mut Report, location: &str, event: &Event) {
+    let has_script = match event.script.as_ref().and_then(|script| script.get("exec")) {
+        Some(Value::Array(lines)) => !lines.is_empty(),
+        Some(Value::String(line)) => !line.is_empty(),
+        _ => false,
+    };
+    if has_script {
+        report.unsupported(location, format!("'{}' script skipped", event.listen));
+    }
+}
+
+/// Converts a collection into a Postman v2.1 collection export.
+pub fn export_collection(name: &str, collection: &Collection) -> Result<String> {
+    let mut root: Vec<Item> = vec![];
+    collection.requests.iter().for_each(|req| {
+        let folder = req
+            .folder
+            .as_deref()
+            .map(|folder| folder.split('/').filter(|seg| !seg.is_empty()).collect())
+            .unwrap_or_else(Vec::new);
+        let items = folder.iter().fold(&mut root, |items, segment| {
+            let idx = match items
+                .iter()
+                .position(|item| item.item.is_some() && item.name == *segment)
+            {
+                Some(idx) => idx,
+                None => {
+                    items.push(Item {
+                        name: segment.to_string(),
+                        item: Some(vec![]),
+                        ..Default::default()
+                    });
+                    items.len() - 1
+                }
+            };
+            items[idx]
+                .item
+                .as_mut()
+                .expect("Folder items are always present")
+        });
+        items.push(export_request(req));
+    });
+
+    let variable = collection
+        .variables
+        .iter()
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .map(|(key, value)| match value {
+            // Collections get shared, so secrets are left for whoever imports it to fill in.
+            EnvironmentValue::Secret(_) => KeyValue {
+                kind: Some("secret".to_string()),
+                ..KeyValue::new(key, "")
+            },
+            EnvironmentValue::Value(value) => KeyValue::new(key, value),
+        })
+        .collect();
+
+    let postman = PostmanCollection {
+        info: Info {
+            name: name.to_string(),
+            postman_id: None,
+            schema: SCHEMA.to_string(),
+        },
+        item: root,
+        auth: None,
+        event: vec![],
+        variable,
+    };
+    Ok(serde_json::to_string_pretty(&postman)?)
+}
+
+fn export_request(req: &Request) -> Item {
+    let query: Vec<KeyValue> = req
+        .query_params
+        .iter()
+        .flatten()
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .map(|(key, value)| KeyValue::new(key, value))
+        .collect();
+    let raw = if query.is_empty() {
+        req.url.clone()
+    } else {
+        format!(
+            "{}?{}",
+            req.url,
+            form_urlencode(query.iter().map(|param| (&param.key, param.value_string())))
+        )
+    };
+    let variable = req
+        .path_params
+        .iter()
+        .flatten()
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .map(|(key, value)| KeyValue::new(key, value))
+        .collect();
+
+    let is_json = req.headers.iter().any(|header| {
+        header.name.eq_ignore_ascii_case("content-type") && header.value.contains("json")
+    });
+    let body = req.body.as_ref().map(|body| Body {
+        mode: "raw".into(),
+        raw: Some(body.clone()),
+        options: if is_json {
+            Some(serde_json::json!({ "raw": { "language": "json" } }))
+        } else {
+            None
+        },
+        ..Default::default()
+    });
+
+    let auth = req.auth.as_ref().map(|auth| match auth {
+        Auth::Basic { username, password } => {
+            PostmanAuth::with_attributes("basic", &[("username", username), ("password", password)])
+        }
+        Auth::Bearer { token } => PostmanAuth::with_attributes("bearer", &[("token", token)]),
+        Auth::ApiKey {
+            key,
+            value,
+            location,
+        } => PostmanAuth::with_attributes(
+            "apikey",
+            &[
+                ("key", key),
+                ("value", value),
+                (
+                    "in",
+                    match location {
+                        ApiKeyLocation::Header => "header",
+                        ApiKeyLocation::Query => "query",
+                    },
+                ),
+            ],
+        ),
+    });
+
+    Item {
+        name: req.name.clone(),
+        request: Some(RequestSpec::Request(Box::new(PostmanRequest {
+            method: Some(req.method.to_string()),
+            header: req
+                .headers
+                .iter()
+                .map(|header| KeyValue::new(&header.name, &header.value))
+                .collect(),
+            body,
+            url: Some(UrlSpec::Detailed(Box::new(PostmanUrl {
+                raw: Some(raw),
+                query,
+                variable,
+                ..Default::default()
+            }))),
+            auth,
+        }))),
+        ..Default::default()
+    }
+}
+
+/// Converts an environment into a Postman environment export. Secret values are blanked, like
+/// the secret variables of [`export_collection`].
+pub fn export_environment(env: &Environment) -> Result<String> {
+    let values = env
+        .values
+        .iter()
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .map(|(key, value)| {
+            let (value, kind) = match value {
+                EnvironmentValue::Secret(_) => (String::new(), "secret"),
+                EnvironmentValue::Value(value) => (value.clone(), "default"),
+            };
+            EnvironmentEntry {
+                key: key.clone(),
+                value,
+                kind: Some(kind.to_string()),
+                enabled: true,
+            }
+        })
+        .collect();
+
+    let postman = PostmanEnvironment {
+        id: None,
+        name: env.name.clone(),
+        values,
+        scope: Some("environment".to_string()),
+    };
+    Ok(serde_json::to_string_pretty(&postman)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLLECTION: &str = r#"{
+        "info": {
+            "name": "Pets",
+            "schema": "https://schema.getpostman.com/json/collection/v2.1.0/collection.json"
+        },
+        "auth": { "type": "bearer", "bearer": [{ "key": "token", "value": "{{token}}" }] },
+        "variable": [{ "key": "host", "value": "https://pets.test" }],
+        "item": [
+            {
+                "name": "Pets",
+                "item": [
+                    {
+                        "name": "Get Pet",
+                        "event": [{ "listen": "test", "script": { "exec": ["pm.test()"] } }],
+                        "request": {
+                            "method": "GET",
+                            "header": [
+                                { "key": "Accept", "value": "application/json" },
+                                { "key": "X-Debug", "value": "1", "disabled": true }
+                            ],
+                            "url": {
+                                "raw": "{{host}}/pets/:id?verbose=true",
+                                "query": [{ "key": "verbose", "value": "true" }],
+                                "variable": [{ "key": "id", "value": "7" }]
+                            }
+                        }
+                    },
+                    {
+                        "name": "Create Pet",
+                        "request": {
+                            "method": "POST",
+                            "auth": { "type": "noauth" },
+                            "body": { "mode": "urlencoded", "urlencoded": [{ "key": "name", "value": "Rex & Co" }, { "key": "owner", "value": "{{owner}}" }] },
+                            "url": "{{host}}/pets"
+                        }
+                    }
+                ]
+            },
+            {
+                "name": "Health",
+                "request": {
+                    "method": "GET",
+                    "auth": { "type": "digest", "digest": [] },
+                    "url": "{{host}}/health"
+                }
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn import_folders_and_requests() {
+        let import = import_collection(COLLECTION).unwrap();
+        let requests = &import.collection.requests;
+
+        assert_eq!(import.name, "Pets");
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].folder.as_deref(), Some("Pets"));
+        assert_eq!(requests[0].url, "{{host}}/pets/:id");
+        assert_eq!(
+            requests[0].query_params.as_ref().unwrap().get("verbose"),
+            Some(&"true".to_string())
+        );
+        assert_eq!(
+            requests[0].path_params.as_ref().unwrap().get("id"),
+            Some(&"7".to_string())
+        );
+        assert_eq!(requests[0].headers.len(), 1);
+        assert_eq!(requests[2].folder, None);
+    }
+
+    #[test]
+    fn import_auth_inheritance() {
+        let requests = import_collection(COLLECTION).unwrap().collection.requests;
+
+        assert_eq!(
+            requests[0].auth,
+            Some(Auth::Bearer {
+                token: "{{token}}".into()
+            })
+        );
+        assert_eq!(requests[1].auth, None);
+        assert_eq!(requests[2].auth, None);
+    }
+
+    #[test]
+    fn import_body_and_variables() {
+        let import = import_collection(COLLECTION).unwrap();
+        let create = &import.collection.requests[1];
+
+        assert_eq!(create.method, Method::Post);
+        assert_eq!(
+            create.body.as_deref(),
+            Some("name=Rex+%26+Co&owner={{owner}}")
+        );
+        assert_eq!(
+            create.headers[0].value.as_ref(),
+            "application/x-www-form-urlencoded"
+        );
+        assert_eq!(
+            import.collection.variables.get("host"),
+            Some(&EnvironmentValue::Value("https://pets.test".into()))
+        );
+    }
+
+    #[test]
+    fn import_reports_unsupported() {
+        let report = import_collection(COLLECTION).unwrap().report;
+        let messages: Vec<String> = report.entries.iter().map(|e| e.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "Pets/Get Pet: 'test' script skipped",
+                "Pets/Get Pet: disabled header 'X-Debug' skipped",
+                "Health: 'digest' auth is not supported",
+            ]
+        );
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let import = import_collection(COLLECTION).unwrap();
+        let exported = export_collection(&import.name, &import.collection).unwrap();
+        let reimport = import_collection(&exported).unwrap();
+
+        assert_eq!(reimport.collection.requests, import.collection.requests);
+        assert_eq!(reimport.collection.variables, import.collection.variables);
+        assert!(reimport.report.is_empty());
+    }
+
+    #[test]
+    fn export_blanks_secret_variables() {
+        let mut collection = Collection::default();
+        collection
+            .variables
+            .insert("apiKey".into(), EnvironmentValue::Secret("hunter2".into()));
+
+        let exported = export_collection("Pets", &collection).unwrap();
+        let reimport = import_collection(&exported).unwrap();
+
+        assert!(!exported.contains("hunter2"));
+        assert_eq!(
+            reimport.collection.variables.get("apiKey"),
+            Some(&EnvironmentValue::Secret(String::new()))
+        );
+    }
+
+    #[test]
+    fn environment_round_trip() {
+        let json = r#"{
+            "name": "Staging",
+            "values": [
+                { "key": "host", "value": "https://staging.test", "enabled": true },
+                { "key": "token", "value": "abc", "type": "secret", "enabled": true },
+                { "key": "unused", "value": "x", "enabled": false }
+            ]
+        }"#;
+
+        let env = import_environment(json).unwrap();
+        assert_eq!(env.values.len(), 2);
+        assert_eq!(
+            env.values.get("token"),
+            Some(&EnvironmentValue::Secret("abc".into()))
+        );
+
+        let exported = export_environment(&env).unwrap();
+        let reimport = import_environment(&exported).unwrap();
+        assert!(!exported.contains("abc"));
+        assert_eq!(reimport.values.get("host"), env.values.get("host"));
+        assert_eq!(
+            reimport.values.get("token"),
+            Some(&EnvironmentValue::Secret(String::new()))
+        );
+    }
+}
@@ -0,0 +1,291 @@
+//! Executions saved in the collection directory, one JSON object per line of the history file.
+
+use color_eyre::Result;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::{Duration, OffsetDateTime};
+
+use crate::{
+    collection::{self, REDACTED},
+    response::SpilledBody,
+    Collection, Execution,
+};
+
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+/// Headers of the sent request whose values are always redacted.
+const CREDENTIAL_HEADERS: [&str; 2] = ["authorization", "proxy-authorization"];
+
+/// Request and response bodies are saved up to this size, only the start of larger ones is kept.
+pub const MAX_BODY: usize = 1024 * 1024;
+
+/// How much history is kept, entries past either limit are removed oldest first.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Retention {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_entries: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_days: Option<u32>,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            max_entries: Some(500),
+            max_age_days: Some(30),
+        }
+    }
+}
+
+impl Retention {
+    /// Whether `history`, ordered oldest first, has grown far enough past the limits to prune.
+    /// The limits are overshot by a tenth of the entries or a day, so the history file is
+    /// rewritten once for a batch of entries rather than for each new one.
+    pub fn exceeded(&self, history: &[Execution], now: OffsetDateTime) -> bool {
+        self.max_entries
+            .is_some_and(|max| history.len() > max + (max / 10).max(1))
+            || history
+                .first()
+                .is_some_and(|oldest| self.expired(oldest, now - Duration::days(1)))
+    }
+
+    /// Removes the entries past the limits from `history`, ordered oldest first. Returns how many
+    /// were removed.
+    pub fn apply(&self, history: &mut Vec<Execution>, now: OffsetDateTime) -> usize {
+        let before = history.len();
+        history.retain(|execution| !self.expired(execution, now));
+        if let Some(max) = self.max_entries {
+            history.drain(..history.len().saturating_sub(max));
+        }
+        before - history.len()
+    }
+
+    fn expired(&self, execution: &Execution, now: OffsetDateTime) -> bool {
+        self.max_age_days
+            .is_some_and(|days| now - execution.started > Duration::days(days.into()))
+    }
+}
+
+/// Reads a history file, skipping lines that can't be parsed.
+pub fn parse(content: &str) -> Vec<Execution> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(idx, line)| match serde_json::from_str(line) {
+            Ok(execution) => Some(execution),
+            Err(err) => {
+                warn!("Skipping history line {}: {}", idx + 1, err);
+                None
+            }
+        })
+        .collect()
+}
+
+/// A single history line, including the trailing newline, for appending to the file. Bodies are
+/// cut to [`MAX_BODY`] and the secrets of `coll` are redacted.
+pub fn line(execution: &Execution, coll: Option<&Collection>) -> Result<String> {
+    let secrets = coll
+        .map(|coll| coll.secrets(execution.environment.as_deref()))
+        .unwrap_or_default();
+    let mut execution = execution.clone();
+    let res = &mut execution.response;
+    if res.spilled.is_some() || res.body.len() > MAX_BODY {
+        res.spilled = Some(SpilledBody::truncated(res.body_len()));
+        res.body.truncate(MAX_BODY);
+    }
+    res.body = redact_bytes(&res.body, &secrets);
+    if let Some(sent) = &mut execution.sent {
+        sent.body.truncate(MAX_BODY);
+        sent.body = redact_bytes(&sent.body, &secrets);
+        // Credentials are encoded, like Basic auth's base64, so they can't be found by value.
+        sent.headers
+            .iter_mut()
+            .filter(|header| CREDENTIAL_HEADERS.contains(&&*header.name.to_ascii_lowercase()))
+            .for_each(|header| header.value = redact_credentials(&header.value).into());
+    }
+
+    // Bodies are saved as base64, which secrets can't be searched for, so they are set aside
+    // while the rest is redacted.
+    let mut value = serde_json::to_value(&execution)?;
+    let bodies: Vec<_> = ["response", "sent"]
+        .into_iter()
+        .filter_map(|key| {
+            let body = value.get_mut(key)?.as_object_mut()?.remove("body")?;
+            Some((key, body))
+        })
+        .collect();
+    redact_value(&mut value, &secrets);
+    for (key, body) in bodies {
+        if let Some(object) = value.get_mut(key).and_then(Value::as_object_mut) {
+            object.insert("body".into(), body);
+        }
+    }
+    Ok(serde_json::to_string(&value)? + "\n")
+}
+
+pub fn write(history: &[Execution], coll: Option<&Collection>) -> Result<String> {
+    history
+        .iter()
+        .map(|execution| line(execution, coll))
+        .collect()
+}
+
+/// Redacts the credentials of an `Authorization` value, keeping the scheme in front of them.
+fn redact_credentials(value: &str) -> String {
+    match value.split_once(' ') {
+        Some((scheme, _)) => format!("{} {}", scheme, REDACTED),
+        None => REDACTED.to_string(),
+    }
+}
+
+fn redact_value(value: &mut Value, secrets: &[String]) {
+    match value {
+        Value::String(text) => *text = collection::redact(text, secrets),
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| redact_value(value, secrets)),
+        Value::Object(map) => map
+            .values_mut()
+            .for_each(|value| redact_value(value, secrets)),
+        _ => {}
+    }
+}
+
+fn redact_bytes(body: &[u8], secrets: &[String]) -> Vec<u8> {
+    secrets.iter().fold(body.to_vec(), |body, secret| {
+        let secret = secret.as_bytes();
+        let mut redacted = Vec::with_capacity(body.len());
+        let mut rest = body.as_slice();
+        while let Some(idx) = rest
+            .windows(secret.len())
+            .position(|window| window == secret)
+        {
+            redacted.extend_from_slice(&rest[..idx]);
+            redacted.extend_from_slice(REDACTED.as_bytes());
+            rest = &rest[idx + secret.len()..];
+        }
+        redacted.extend_from_slice(rest);
+        redacted
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{wire::SentRequest, Auth, EnvironmentValue, Header, Method, Request, Response};
+
+    fn execution(name: &str, days_ago: i64) -> Execution {
+        Execution {
+            request: Request::builder()
+                .name(name.into())
+                .method(Method::Get)
+                .url("https://pets.test/pets".into())
+                .build(),
+            response: Response {
+                body: vec![0, 159, 146, 150],
+                duration: std::time::Duration::from_millis(12),
+                ..Default::default()
+            },
+            started: OffsetDateTime::UNIX_EPOCH + Duration::days(100 - days_ago),
+            tests: vec![],
+            environment: Some("Staging".into()),
+            sent: None,
+        }
+    }
+
+    fn now() -> OffsetDateTime {
+        OffsetDateTime::UNIX_EPOCH + Duration::days(100)
+    }
+
+    #[test]
+    fn round_trip() {
+        let history = vec![execution("List", 1), execution("Get", 0)];
+
+        let content = write(&history, None).unwrap();
+
+        assert_eq!(content.lines().count(), 2);
+        assert_eq!(parse(&(content + "not json\n")), history);
+    }
+
+    #[test]
+    fn retention() {
+        let mut history = vec![
+            execution("Old", 40),
+            execution("A", 3),
+            execution("B", 2),
+            execution("C", 1),
+        ];
+        let retention = Retention {
+            max_entries: Some(2),
+            max_age_days: Some(30),
+        };
+
+        assert!(!retention.exceeded(&history[1..], now()));
+        assert!(retention.exceeded(&history, now()));
+        assert_eq!(retention.apply(&mut history, now()), 2);
+
+        let names: Vec<_> = history.iter().map(|e| e.request.name.as_str()).collect();
+        assert_eq!(names, vec!["B", "C"]);
+        assert!(!retention.exceeded(&history, now()));
+    }
+
+    #[test]
+    fn line_redacts_secrets_and_cuts_bodies() {
+        let mut coll = Collection::default();
+        coll.variables
+            .insert("key".into(), EnvironmentValue::Secret("hunter2".into()));
+        let mut secret = execution("List", 0);
+        secret.request.url = "https://pets.test/pets?key=hunter2".into();
+        secret.response.body = br#"{"key":"hunter2"}"#.to_vec();
+        let mut large = execution("Download", 0);
+        large.response.body = vec![b'a'; MAX_BODY + 1];
+
+        let content = write(&[secret, large], Some(&coll)).unwrap();
+        let saved = parse(&content);
+
+        assert!(!content.contains("hunter2"));
+        assert_eq!(
+            saved[0].request.url,
+            "https://pets.test/pets?key=[REDACTED]"
+        );
+        assert_eq!(saved[0].response.body, br#"{"key":"[REDACTED]"}"#);
+        assert_eq!(saved[1].response.body.len(), MAX_BODY);
+        assert_eq!(saved[1].response.body_len(), MAX_BODY as u64 + 1);
+        assert!(saved[1].response.paged_body().is_none());
+    }
+
+    #[test]
+    fn line_redacts_basic_auth() {
+        let mut coll = Collection::default();
+        coll.variables.insert(
+            "password".into(),
+            EnvironmentValue::Secret("hunter2".into()),
+        );
+        let mut execution = execution("Login", 0);
+        execution.request.auth = Some(Auth::Basic {
+            username: "admin".into(),
+            password: "hunter2".into(),
+        });
+        execution.sent = Some(SentRequest {
+            method: "GET".into(),
+            url: "https://pets.test/pets".into(),
+            version: "HTTP/1.1".into(),
+            headers: vec![Header {
+                name: "authorization".into(),
+                value: "Basic YWRtaW46aHVudGVyMg==".into(),
+            }],
+            body: vec![],
+        });
+
+        let content = line(&execution, Some(&coll)).unwrap();
+
+        assert!(!content.contains("hunter2"));
+        assert!(!content.contains("YWRtaW46aHVudGVyMg"));
+        assert_eq!(
+            &*parse(&content)[0].sent.as_ref().unwrap().headers[0].value,
+            "Basic [REDACTED]"
+        );
+    }
+}
@@ -1,15 +1,46 @@
-use color_eyre::eyre::bail;
+use color_eyre::eyre::{bail, eyre};
 use color_eyre::Result;
 use std::collections::HashMap;
 use std::fmt;
+use std::str::FromStr;
 
 use reqwest::header::HeaderMap;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub mod assertion;
+pub mod bench;
+pub mod body;
+pub mod collection;
+pub mod compression;
+pub mod diff;
+pub mod executor;
+pub mod extract;
+pub mod filter;
+pub mod formats;
+pub mod history;
+pub mod response;
+pub mod runner;
+pub mod script;
+#[cfg(test)]
+mod test_util;
+pub mod vars;
+pub mod wire;
+
+pub use assertion::Assertions;
+pub use collection::{Collection, Environment, EnvironmentValue, EnvironmentValues};
+pub use extract::Extraction;
+pub use response::{Example, Execution, Response};
+pub use vars::Variables;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub enum Method {
     Get,
     Post,
+    Put,
+    Patch,
+    Delete,
+    Head,
+    Options,
 }
 
 impl fmt::Display for Method {
@@ -20,21 +51,48 @@ impl fmt::Display for Method {
             match self {
                 Self::Get => "GET",
                 Self::Post => "POST",
+                Self::Put => "PUT",
+                Self::Patch => "PATCH",
+                Self::Delete => "DELETE",
+                Self::Head => "HEAD",
+                Self::Options => "OPTIONS",
             }
         )
     }
 }
 
+impl FromStr for Method {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_uppercase().as_str() {
+            "GET" => Ok(Self::Get),
+            "POST" => Ok(Self::Post),
+            "PUT" => Ok(Self::Put),
+            "PATCH" => Ok(Self::Patch),
+            "DELETE" => Ok(Self::Delete),
+            "HEAD" => Ok(Self::Head),
+            "OPTIONS" => Ok(Self::Options),
+            _ => Err(eyre!("Unsupported HTTP method: {}", s)),
+        }
+    }
+}
+
 impl From<Method> for reqwest::Method {
     fn from(val: Method) -> Self {
         match val {
             Method::Get => reqwest::Method::GET,
             Method::Post => reqwest::Method::POST,
+            Method::Put => reqwest::Method::PUT,
+            Method::Patch => reqwest::Method::PATCH,
+            Method::Delete => reqwest::Method::DELETE,
+            Method::Head => reqwest::Method::HEAD,
+            Method::Options => reqwest::Method::OPTIONS,
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub enum Protocol {
     Http,
     Tcp,
@@ -42,7 +100,7 @@ pub enum Protocol {
     Grpc,
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct Header {
     pub name: Box<str>,
     pub value: Box<str>,
@@ -64,6 +122,28 @@ impl Header {
     }
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum ApiKeyLocation {
+    Header,
+    Query,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum Auth {
+    Basic {
+        username: String,
+        password: String,
+    },
+    Bearer {
+        token: String,
+    },
+    ApiKey {
+        key: String,
+        value: String,
+        location: ApiKeyLocation,
+    },
+}
+
 #[derive(Debug, PartialEq)]
 pub struct NoName;
 #[derive(Debug, PartialEq)]
@@ -269,11 +349,20 @@ impl RequestBuilder<Name, HasMethod, Url> {
             body: self.body,
             path_params: self.path_params,
             query_params: self.query_params,
+            folder: None,
+            auth: None,
+            pre_request_script: None,
+            assertions: Assertions::default(),
+            extractions: vec![],
+            response_filter: None,
+            compression: None,
+            examples: vec![],
+            operation_id: None,
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct Request {
     pub name: String,
     pub protocol: Option<Protocol>,
@@ -283,6 +372,32 @@ pub struct Request {
     pub body: Option<String>,
     pub path_params: Option<HashMap<String, String>>,
     pub query_params: Option<HashMap<String, String>>,
+    /// Slash separated path of the folder this request is grouped under.
+    #[serde(default)]
+    pub folder: Option<String>,
+    #[serde(default)]
+    pub auth: Option<Auth>,
+    /// Rhai script run before the request is sent, see [`script`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_request_script: Option<String>,
+    #[serde(default, skip_serializing_if = "Assertions::is_empty")]
+    pub assertions: Assertions,
+    /// Values copied into the active environment after a successful response.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extractions: Vec<Extraction>,
+    /// JSONPath or jq expression the response body is shown through, see [`filter`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_filter: Option<String>,
+    /// Encoding the body is compressed with before sending, announced in `Content-Encoding`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<compression::Encoding>,
+    /// Responses saved to compare later ones against, see [`diff`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<Example>,
+    /// The `operationId` of the OpenAPI operation the request was imported from, which a later
+    /// import of the specification matches it on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operation_id: Option<String>,
 }
 
 impl Request {
@@ -298,6 +413,62 @@ impl Request {
             query_params: None,
         }
     }
+
+    /// Returns a copy of the request with every `{{variable}}` replaced.
+    pub fn resolve(&self, vars: &Variables) -> Request {
+        let sub = |value: &str| vars::substitute(value, vars);
+        let sub_params = |params: &Option<HashMap<String, String>>| {
+            params.as_ref().map(|params| {
+                params
+                    .iter()
+                    .map(|(key, value)| (key.clone(), sub(value)))
+                    .collect()
+            })
+        };
+
+        Request {
+            name: self.name.clone(),
+            protocol: self.protocol.clone(),
+            url: sub(&self.url),
+            method: self.method.clone(),
+            headers: self
+                .headers
+                .iter()
+                .map(|header| Header {
+                    name: sub(&header.name).into(),
+                    value: sub(&header.value).into(),
+                })
+                .collect(),
+            body: self.body.as_deref().map(sub),
+            path_params: sub_params(&self.path_params),
+            query_params: sub_params(&self.query_params),
+            folder: self.folder.clone(),
+            auth: self.auth.as_ref().map(|auth| match auth {
+                Auth::Basic { username, password } => Auth::Basic {
+                    username: sub(username),
+                    password: sub(password),
+                },
+                Auth::Bearer { token } => Auth::Bearer { token: sub(token) },
+                Auth::ApiKey {
+                    key,
+                    value,
+                    location,
+                } => Auth::ApiKey {
+                    key: sub(key),
+                    value: sub(value),
+                    location: location.clone(),
+                },
+            }),
+            pre_request_script: self.pre_request_script.clone(),
+            assertions: self.assertions.resolve(vars),
+            extractions: self.extractions.clone(),
+            response_filter: self.response_filter.clone(),
+            compression: self.compression,
+            // Examples aren't sent, and would otherwise be saved again with every execution.
+            examples: vec![],
+            operation_id: self.operation_id.clone(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -605,6 +776,15 @@ mod request_builder {
                     params.insert("key".to_string(), "value".to_string());
                     params
                 }),
+                folder: None,
+                auth: None,
+                pre_request_script: None,
+                assertions: Assertions::default(),
+                extractions: vec![],
+                response_filter: None,
+                compression: None,
+                examples: vec![],
+                operation_id: None,
             }
         );
     }
@@ -666,6 +846,15 @@ mod request_builder {
                     params.insert("key".to_string(), "value".to_string());
                     params
                 }),
+                folder: None,
+                auth: None,
+                pre_request_script: None,
+                assertions: Assertions::default(),
+                extractions: vec![],
+                response_filter: None,
+                compression: None,
+                examples: vec![],
+                operation_id: None,
             }
         );
     }
@@ -0,0 +1,315 @@
+use color_eyre::{eyre::bail, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use time::OffsetDateTime;
+
+use crate::{assertion::TestResult, wire::SentRequest, Header, Request};
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub version: String,
+    pub headers: Vec<Header>,
+    #[serde(with = "base64_body")]
+    pub body: Vec<u8>,
+    /// Set when the body outgrew the executor's memory limit or what history keeps, `body` then
+    /// holds only its start.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spilled: Option<SpilledBody>,
+    /// Size of the body as received, set when the executor decoded its `Content-Encoding`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compressed_len: Option<u64>,
+    #[serde(default)]
+    pub timings: Timings,
+    pub duration: Duration,
+}
+
+/// An empty `200 OK` HTTP/1.1 response, to fill in with struct update syntax.
+impl Default for Response {
+    fn default() -> Self {
+        Response {
+            status: 200,
+            version: "HTTP/1.1".into(),
+            headers: vec![],
+            body: vec![],
+            spilled: None,
+            compressed_len: None,
+            timings: Timings::default(),
+            duration: Duration::ZERO,
+        }
+    }
+}
+
+impl Response {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|header| header.name.eq_ignore_ascii_case(name))
+            .map(|header| header.value.as_ref())
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// The body, when all of it is held in memory. Bodies spilled to disk are too large to parse
+    /// or search, so they fail here rather than giving only their start.
+    pub fn complete_body(&self) -> Result<&[u8]> {
+        if let Some(spilled) = &self.spilled {
+            bail!(
+                "body of {} bytes is too large to parse, only its start is in memory",
+                spilled.len
+            );
+        }
+        Ok(&self.body)
+    }
+
+    /// The body's file, when the rest of it can still be read from there.
+    pub fn paged_body(&self) -> Option<&SpilledBody> {
+        self.spilled.as_ref().filter(|spilled| spilled.is_paged())
+    }
+
+    /// Size of the whole body, including any part spilled to disk.
+    pub fn body_len(&self) -> u64 {
+        self.spilled
+            .as_ref()
+            .map_or(self.body.len() as u64, |spilled| spilled.len)
+    }
+}
+
+/// A response saved on its request under a name, to compare later responses against.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Example {
+    pub name: String,
+    pub response: Response,
+}
+
+/// How long each phase of a request took. Connection phases are `None` when they don't apply,
+/// like TLS for plain HTTP, or weren't measured.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Timings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dns: Option<Duration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect: Option<Duration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<Duration>,
+    /// The connection phases were measured on a probe connection of their own rather than the
+    /// request's, see [`crate::executor::probe_connection`], so they are not part of its timeline.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub estimated: bool,
+    /// From sending the request until its response headers arrived. Unless the connection phases
+    /// are its own, this includes opening the connection when the request needed a new one.
+    pub ttfb: Duration,
+    /// From the response headers until the end of the body.
+    pub download: Duration,
+}
+
+impl Timings {
+    /// Each phase of the request's own timeline with its name, in the order they happen.
+    pub fn phases(&self) -> Vec<(&'static str, Duration)> {
+        let connection = if self.estimated {
+            vec![]
+        } else {
+            self.connection()
+        };
+        connection
+            .into_iter()
+            .chain([("TTFB", self.ttfb), ("Download", self.download)])
+            .collect()
+    }
+
+    /// Connection phases measured on a probe connection, which may differ from the request's.
+    pub fn estimates(&self) -> Vec<(&'static str, Duration)> {
+        if self.estimated {
+            self.connection()
+        } else {
+            vec![]
+        }
+    }
+
+    fn connection(&self) -> Vec<(&'static str, Duration)> {
+        [
+            ("DNS", self.dns),
+            ("Connect", self.connect),
+            ("TLS", self.tls),
+        ]
+        .into_iter()
+        .filter_map(|(name, phase)| phase.map(|phase| (name, phase)))
+        .collect()
+    }
+}
+
+/// A response body written to a temp file, read back a page at a time. The file is removed once
+/// the last copy of the body made by [`SpilledBody::create`] is dropped, so it lives as long as
+/// the execution it belongs to. Bodies read back from saved history have only their start.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SpilledBody {
+    #[serde(default, skip_serializing_if = "no_path")]
+    pub path: PathBuf,
+    pub len: u64,
+    #[serde(skip)]
+    file: Option<Arc<SpillFile>>,
+}
+
+impl PartialEq for SpilledBody {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path && self.len == other.len
+    }
+}
+
+fn no_path(path: &Path) -> bool {
+    path.as_os_str().is_empty()
+}
+
+/// Removes the body file when dropped.
+#[derive(Debug)]
+struct SpillFile(PathBuf);
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// How far back [`SpilledBody::previous_line`] looks for the start of a line.
+const LINE_SEARCH: u64 = 4096;
+
+impl SpilledBody {
+    /// A new, uniquely named, body file in the temp directory.
+    pub fn create() -> io::Result<Self> {
+        let dir = std::env::temp_dir().join("carrier-pigeon");
+        std::fs::create_dir_all(&dir)?;
+        let name: String = std::iter::repeat_with(fastrand::alphanumeric)
+            .take(16)
+            .collect();
+        let path = dir.join(format!("{}.body", name));
+        Ok(Self {
+            file: Some(Arc::new(SpillFile(path.clone()))),
+            path,
+            len: 0,
+        })
+    }
+
+    /// A body of `len` bytes cut short without a file holding the rest, as saved in history.
+    pub fn truncated(len: u64) -> Self {
+        Self {
+            path: PathBuf::new(),
+            len,
+            file: None,
+        }
+    }
+
+    /// Whether the rest of the body can be read from its file, which is only kept while the
+    /// execution that spilled it is.
+    pub fn is_paged(&self) -> bool {
+        self.file.is_some()
+    }
+
+    /// Reads up to `len` bytes starting at `offset`.
+    pub fn read_at(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut bytes = vec![];
+        file.take(len as u64).read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Offset of the line after the one at `offset`. Lines longer than the search window are
+    /// stepped through a window at a time.
+    pub fn next_line(&self, offset: u64) -> io::Result<u64> {
+        let bytes = self.read_at(offset, LINE_SEARCH as usize)?;
+        let next = match bytes.iter().position(|byte| *byte == b'\n') {
+            Some(idx) => offset + idx as u64 + 1,
+            None => offset + bytes.len() as u64,
+        };
+        Ok(if next >= self.len { offset } else { next })
+    }
+
+    /// Offset of the line before the one at `offset`.
+    pub fn previous_line(&self, offset: u64) -> io::Result<u64> {
+        let start = offset.saturating_sub(LINE_SEARCH);
+        let bytes = self.read_at(start, (offset - start) as usize)?;
+        // The byte before `offset` ends the previous line, so skip it.
+        let before = &bytes[..bytes.len().saturating_sub(1)];
+        Ok(match before.iter().rposition(|byte| *byte == b'\n') {
+            Some(idx) => start + idx as u64 + 1,
+            None => start,
+        })
+    }
+}
+
+/// A request as it was sent, after variable resolution, along with the response it received.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Execution {
+    pub request: Request,
+    pub response: Response,
+    #[serde(with = "time::serde::rfc3339")]
+    pub started: OffsetDateTime,
+    /// Results of the request's assertions against the response.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<TestResult>,
+    /// The environment the request's variables were resolved from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+    /// The request as the HTTP client sent it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sent: Option<SentRequest>,
+}
+
+/// Bodies are stored as base64, which keeps binary responses intact and saved history compact.
+pub(crate) mod base64_body {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(body: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(body))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        STANDARD
+            .decode(String::deserialize(deserializer)?)
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_through_spilled_body() {
+        let mut spilled = SpilledBody::create().unwrap();
+        let body = b"first\nsecond line\n\nlast";
+        std::fs::write(&spilled.path, body).unwrap();
+        spilled.len = body.len() as u64;
+
+        assert_eq!(spilled.read_at(6, 6).unwrap(), b"second");
+        assert_eq!(spilled.next_line(0).unwrap(), 6);
+        assert_eq!(spilled.next_line(6).unwrap(), 18);
+        assert_eq!(spilled.next_line(19).unwrap(), 19);
+        assert_eq!(spilled.previous_line(19).unwrap(), 18);
+        assert_eq!(spilled.previous_line(18).unwrap(), 6);
+        assert_eq!(spilled.previous_line(6).unwrap(), 0);
+        assert_eq!(spilled.previous_line(0).unwrap(), 0);
+    }
+
+    #[test]
+    fn removes_file_with_last_copy() {
+        let spilled = SpilledBody::create().unwrap();
+        std::fs::write(&spilled.path, b"body").unwrap();
+        let path = spilled.path.clone();
+
+        let copy = spilled.clone();
+        drop(spilled);
+        assert!(path.exists());
+        drop(copy);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,307 @@
+//! Runs a collection's requests one after another, carrying variables between them.
+
+use log::warn;
+use std::time::{Duration, Instant};
+
+use crate::{
+    executor::{self, HttpClient},
+    extract, script, Collection, Execution, Method, Request, Variables,
+};
+
+pub mod data;
+pub mod report;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RunOptions {
+    /// Stop after the first request that fails to send or fails an assertion.
+    pub stop_on_failure: bool,
+    /// Pause between requests.
+    pub delay: Duration,
+    /// Rows of iteration data, the selection runs once per row. Runs once when empty.
+    pub data: Vec<Variables>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Outcome {
+    Completed(Box<Execution>),
+    /// The request could not be prepared or sent.
+    Failed(String),
+}
+
+/// The outcome of one request in a run.
+#[derive(Clone, Debug)]
+pub struct RunResult {
+    /// Index of the request in `Collection::requests`.
+    pub index: usize,
+    /// Index of the iteration data row the request ran with.
+    pub iteration: usize,
+    pub name: String,
+    pub method: Method,
+    pub outcome: Outcome,
+}
+
+impl RunResult {
+    pub fn execution(&self) -> Option<&Execution> {
+        match &self.outcome {
+            Outcome::Completed(execution) => Some(execution),
+            Outcome::Failed(_) => None,
+        }
+    }
+
+    /// Whether the request was sent and passed all of its assertions.
+    pub fn passed(&self) -> bool {
+        self.execution()
+            .is_some_and(|execution| execution.tests.iter().all(|result| result.passed))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RunSummary {
+    /// Number of requests to run, across all iterations.
+    pub total: usize,
+    pub iterations: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub tests_passed: usize,
+    pub tests_failed: usize,
+    pub duration: Duration,
+    /// Whether the run ended early because of `stop_on_failure`.
+    pub stopped: bool,
+}
+
+impl RunSummary {
+    pub fn record(&mut self, result: &RunResult) {
+        if result.passed() {
+            self.passed += 1;
+        } else {
+            self.failed += 1;
+        }
+        if let Some(execution) = result.execution() {
+            let tests_passed = execution.tests.iter().filter(|test| test.passed).count();
+            self.tests_passed += tests_passed;
+            self.tests_failed += execution.tests.len() - tests_passed;
+        }
+    }
+
+    pub fn completed(&self) -> usize {
+        self.passed + self.failed
+    }
+}
+
+/// Prepares, sends and runs the extraction rules for a single request.
+pub async fn run_request(
+    client: &HttpClient,
+    coll: &mut Collection,
+    req: &Request,
+    env_name: Option<&str>,
+    iteration: &Variables,
+) -> Outcome {
+    let prepared = match script::prepare(coll, req, env_name, iteration) {
+        Ok(prepared) => prepared,
+        Err(err) => return Outcome::Failed(err.to_string()),
+    };
+    let options = coll.settings.executor.clone();
+    match executor::execute_with(client, prepared.request, &options, |_| {}).await {
+        Ok(mut execution) => {
+            execution.environment = env_name.map(str::to_string);
+            for err in extract::apply(coll, env_name, &execution) {
+                warn!("{}", err);
+            }
+            Outcome::Completed(Box::new(execution))
+        }
+        Err(err) => Outcome::Failed(err.to_string()),
+    }
+}
+
+/// Runs the requests at `selection`, in that order, calling `on_result` after each one. With
+/// iteration data the whole selection runs once per row.
+///
+/// Variables set by scripts and extraction rules are written to `coll`, so each request sees the
+/// values produced by the ones before it.
+pub async fn run(
+    client: &HttpClient,
+    coll: &mut Collection,
+    selection: &[usize],
+    env_name: Option<&str>,
+    options: &RunOptions,
+    mut on_result: impl FnMut(&RunResult),
+) -> RunSummary {
+    let timer = Instant::now();
+    let no_data = [Variables::new()];
+    let rows = if options.data.is_empty() {
+        &no_data[..]
+    } else {
+        &options.data[..]
+    };
+    let mut summary = RunSummary {
+        total: selection.len() * rows.len(),
+        iterations: rows.len(),
+        ..Default::default()
+    };
+
+    let mut position = 0;
+    'run: for (iteration, row) in rows.iter().enumerate() {
+        for &index in selection {
+            let Some(req) = coll.requests.get(index).cloned() else {
+                continue;
+            };
+            if position > 0 && !options.delay.is_zero() {
+                tokio::time::sleep(options.delay).await;
+            }
+            position += 1;
+
+            let result = RunResult {
+                index,
+                iteration,
+                name: req.name.clone(),
+                method: req.method.clone(),
+                outcome: run_request(client, coll, &req, env_name, row).await,
+            };
+            summary.record(&result);
+            on_result(&result);
+
+            if options.stop_on_failure && !result.passed() {
+                summary.stopped = position < summary.total;
+                break 'run;
+            }
+        }
+    }
+
+    summary.duration = timer.elapsed();
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assertion::Check, test_util, EnvironmentValue};
+
+    /// Serves one canned JSON body per connection, in order.
+    fn serve(bodies: Vec<&'static str>) -> String {
+        test_util::serve("Content-Type: application/json\r\n", bodies)
+    }
+
+    /// A collection without connection probes, which would take the canned responses.
+    fn collection(requests: Vec<Request>) -> Collection {
+        let mut coll = Collection {
+            requests,
+            ..Default::default()
+        };
+        coll.settings.executor.probe_connection = false;
+        coll
+    }
+
+    fn request(name: &str, url: &str) -> Request {
+        Request::builder()
+            .name(name.into())
+            .method(Method::Get)
+            .url(url.into())
+            .build()
+    }
+
+    #[tokio::test]
+    async fn chains_variables_between_requests() {
+        let base = serve(vec![r#"{"token":"abc"}"#, r#"{"ok":true}"#]);
+        let mut login = request("Login", &format!("{}/login", base));
+        login.extractions = vec![serde_json::from_str(
+            r#"{ "variable": "token", "from": "json_path", "path": "$.token" }"#,
+        )
+        .unwrap()];
+        let mut me = request("Me", &format!("{}/me", base));
+        me.query_params = Some([("token".into(), "{{token}}".into())].into());
+        let mut coll = collection(vec![login, me]);
+
+        let mut results = vec![];
+        let summary = run(
+            &HttpClient::default(),
+            &mut coll,
+            &[0, 1],
+            None,
+            &RunOptions::default(),
+            |result| results.push(result.clone()),
+        )
+        .await;
+
+        assert_eq!(summary.passed, 2);
+        assert_eq!(
+            coll.variables.get("token"),
+            Some(&EnvironmentValue::Value("abc".into()))
+        );
+        let sent = &results[1].execution().unwrap().request;
+        assert_eq!(
+            sent.query_params
+                .as_ref()
+                .and_then(|query| query.get("token")),
+            Some(&"abc".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn iterates_over_data() {
+        let base = serve(vec![r#"{}"#, r#"{}"#]);
+        let mut coll = collection(vec![request(
+            "Account",
+            &format!("{}/accounts/{{{{id}}}}", base),
+        )]);
+        coll.variables
+            .insert("id".into(), EnvironmentValue::Value("default".into()));
+        let options = RunOptions {
+            data: data::parse_csv("id\n7\n8\n").unwrap(),
+            ..Default::default()
+        };
+
+        let mut sent = vec![];
+        let summary = run(
+            &HttpClient::default(),
+            &mut coll,
+            &[0],
+            None,
+            &options,
+            |result| {
+                sent.push((
+                    result.iteration,
+                    result.execution().unwrap().request.url.clone(),
+                ))
+            },
+        )
+        .await;
+
+        assert_eq!(summary.iterations, 2);
+        assert_eq!(summary.total, 2);
+        assert_eq!(
+            sent,
+            vec![
+                (0, format!("{}/accounts/7", base)),
+                (1, format!("{}/accounts/8", base))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn stops_on_failure() {
+        let base = serve(vec![r#"{}"#, r#"{}"#]);
+        let mut first = request("First", &base);
+        first.assertions.checks = vec![Check::Status { equals: 201 }];
+        let mut coll = collection(vec![first, request("Second", &base)]);
+        let options = RunOptions {
+            stop_on_failure: true,
+            ..Default::default()
+        };
+
+        let mut names = vec![];
+        let summary = run(
+            &HttpClient::default(),
+            &mut coll,
+            &[0, 1],
+            None,
+            &options,
+            |result| names.push(result.name.clone()),
+        )
+        .await;
+
+        assert_eq!(names, vec!["First"]);
+        assert_eq!(summary.failed, 1);
+        assert_eq!(summary.tests_failed, 1);
+        assert!(summary.stopped);
+    }
+}
@@ -0,0 +1,91 @@
+//! Iteration data for data-driven runs, where the selected requests run once per row.
+
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
+use serde_json::Value;
+use std::path::Path;
+
+use crate::Variables;
+
+/// Parses a data file as CSV or JSON depending on its extension.
+pub fn parse(path: &Path, content: &str) -> Result<Vec<Variables>> {
+    match path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+        .as_deref()
+    {
+        Some("csv") => parse_csv(content),
+        Some("json") => parse_json(content),
+        _ => bail!(
+            "Iteration data must be a .csv or .json file: {}",
+            path.display()
+        ),
+    }
+}
+
+/// The first line holds the variable names, each following line is an iteration.
+pub fn parse_csv(content: &str) -> Result<Vec<Variables>> {
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+    let headers = reader.headers()?.clone();
+    reader
+        .records()
+        .map(|record| {
+            Ok(headers
+                .iter()
+                .zip(record?.iter())
+                .map(|(name, value)| (name.trim().to_string(), value.to_string()))
+                .collect())
+        })
+        .collect()
+}
+
+/// An array of objects, one per iteration. Strings are used as they are, other values as JSON.
+pub fn parse_json(content: &str) -> Result<Vec<Variables>> {
+    let Value::Array(rows) = serde_json::from_str(content)? else {
+        bail!("Iteration data must be a JSON array of objects");
+    };
+    rows.into_iter()
+        .enumerate()
+        .map(|(idx, row)| match row {
+            Value::Object(values) => Ok(values
+                .into_iter()
+                .map(|(name, value)| match value {
+                    Value::String(value) => (name, value),
+                    value => (name, value.to_string()),
+                })
+                .collect()),
+            _ => Err(eyre!("Iteration {} is not a JSON object", idx + 1)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_rows() {
+        let rows = parse_csv("accountId, name\n7,\"Rex, the dog\"\n8,Tom\n").unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["accountId"], "7");
+        assert_eq!(rows[0]["name"], "Rex, the dog");
+        assert_eq!(rows[1]["name"], "Tom");
+        assert!(parse_csv("a,b\n1,2,3\n").is_err());
+    }
+
+    #[test]
+    fn json_rows() {
+        let rows = parse_json(r#"[{ "accountId": 7, "name": "Rex", "tags": ["good"] }]"#).unwrap();
+
+        assert_eq!(rows[0]["accountId"], "7");
+        assert_eq!(rows[0]["name"], "Rex");
+        assert_eq!(rows[0]["tags"], r#"["good"]"#);
+        assert!(parse_json(r#"[1, 2]"#).is_err());
+        assert!(parse_json(r#"{ "a": 1 }"#).is_err());
+        assert!(parse(Path::new("rows.txt"), "").is_err());
+    }
+}
@@ -0,0 +1,535 @@
+//! Reports of a collection run for CI dashboards, as JUnit XML, JSON or a standalone HTML page.
+
+use color_eyre::{eyre::bail, Result};
+use serde::Serialize;
+use std::{fmt::Write, path::Path, str::FromStr};
+
+use super::{Outcome, RunResult, RunSummary};
+use crate::{assertion::TestResult, collection, Collection};
+
+/// How much of a failed response's body is included in a report.
+const SNIPPET_LENGTH: usize = 2048;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReportFormat {
+    Html,
+    Json,
+    Junit,
+}
+
+impl FromStr for ReportFormat {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "html" => Ok(Self::Html),
+            "json" => Ok(Self::Json),
+            "junit" | "xml" => Ok(Self::Junit),
+            _ => bail!("Unknown report format: {}", s),
+        }
+    }
+}
+
+impl ReportFormat {
+    /// Picks the format from a file extension, e.g. `results.xml` is JUnit.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) => ext.parse(),
+            None => bail!("Can't tell the report format of {}", path.display()),
+        }
+    }
+}
+
+/// The results of a run, with the collection's secrets replaced by `[REDACTED]`.
+#[derive(Debug, Serialize)]
+pub struct RunReport {
+    pub name: String,
+    pub summary: SummaryReport,
+    pub results: Vec<ResultReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SummaryReport {
+    pub total: usize,
+    pub iterations: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub tests_passed: usize,
+    pub tests_failed: usize,
+    pub duration_ms: u128,
+    pub stopped: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResultReport {
+    /// The iteration data row the request ran with, counting from 1.
+    pub iteration: usize,
+    pub name: String,
+    pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub passed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u128>,
+    /// Why the request could not be sent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub tests: Vec<TestResult>,
+    /// The start of the response body, only kept for failed requests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_snippet: Option<String>,
+}
+
+impl RunReport {
+    pub fn new(
+        name: impl Into<String>,
+        results: &[RunResult],
+        summary: &RunSummary,
+        coll: &Collection,
+        env_name: Option<&str>,
+    ) -> Self {
+        let secrets = coll.secrets(env_name);
+        let redact = |text: &str| collection::redact(text, &secrets);
+
+        let results = results
+            .iter()
+            .map(|result| {
+                let mut report = ResultReport {
+                    iteration: result.iteration + 1,
+                    name: result.name.clone(),
+                    method: result.method.to_string(),
+                    url: None,
+                    passed: result.passed(),
+                    status: None,
+                    duration_ms: None,
+                    error: None,
+                    tests: vec![],
+                    response_snippet: None,
+                };
+                match &result.outcome {
+                    Outcome::Completed(execution) => {
+                        report.url = Some(redact(&execution.request.url));
+                        report.status = Some(execution.response.status);
+                        report.duration_ms = Some(execution.response.duration.as_millis());
+                        report.tests = execution
+                            .tests
+                            .iter()
+                            .map(|test| TestResult {
+                                name: redact(&test.name),
+                                passed: test.passed,
+                                message: test.message.as_deref().map(redact),
+                            })
+                            .collect();
+                        if !report.passed {
+                            let body = &execution.response.body;
+                            let mut snippet =
+                                String::from_utf8_lossy(&body[..body.len().min(SNIPPET_LENGTH)])
+                                    .into_owned();
+                            if body.len() > SNIPPET_LENGTH {
+                                snippet.push('…');
+                            }
+                            report.response_snippet = Some(redact(&snippet));
+                        }
+                    }
+                    Outcome::Failed(err) => report.error = Some(redact(err)),
+                }
+                report
+            })
+            .collect();
+
+        Self {
+            name: name.into(),
+            summary: SummaryReport {
+                total: summary.total,
+                iterations: summary.iterations,
+                passed: summary.passed,
+                failed: summary.failed,
+                tests_passed: summary.tests_passed,
+                tests_failed: summary.tests_failed,
+                duration_ms: summary.duration.as_millis(),
+                stopped: summary.stopped,
+            },
+            results,
+        }
+    }
+
+    pub fn render(&self, format: ReportFormat) -> Result<String> {
+        match format {
+            ReportFormat::Html => Ok(self.html()),
+            ReportFormat::Json => self.json(),
+            ReportFormat::Junit => Ok(self.junit()),
+        }
+    }
+
+    pub fn json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Each request is a test case, failed assertions are failures and requests that could not be
+    /// sent are errors. Data-driven runs get a test suite per iteration.
+    pub fn junit(&self) -> String {
+        let errors = self
+            .results
+            .iter()
+            .filter(|result| result.error.is_some())
+            .count();
+        let name = escape(&self.name);
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            xml,
+            "<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{}\">",
+            name,
+            self.summary.total,
+            self.summary.failed - errors,
+            errors,
+            seconds(self.summary.duration_ms)
+        );
+        let per_iteration = self.summary.total / self.summary.iterations.max(1);
+        for results in self.results.chunk_by(|a, b| a.iteration == b.iteration) {
+            let suite = if self.summary.iterations > 1 {
+                escape(&format!(
+                    "{} (iteration {})",
+                    self.name, results[0].iteration
+                ))
+            } else {
+                name.clone()
+            };
+            let errors = results
+                .iter()
+                .filter(|result| result.error.is_some())
+                .count();
+            let failed = results.iter().filter(|result| !result.passed).count();
+            let _ = writeln!(
+                xml,
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{}\">",
+                suite,
+                per_iteration,
+                failed - errors,
+                errors,
+                per_iteration.saturating_sub(results.len()),
+                seconds(results.iter().filter_map(|result| result.duration_ms).sum())
+            );
+            for result in results {
+                xml.push_str(&Self::junit_case(result, &suite));
+            }
+            xml.push_str("  </testsuite>\n");
+        }
+        xml.push_str("</testsuites>\n");
+        xml
+    }
+
+    fn junit_case(result: &ResultReport, suite: &str) -> String {
+        let mut xml = String::new();
+        let _ = write!(
+            xml,
+            "    <testcase name=\"{} {}\" classname=\"{}\" time=\"{}\"",
+            result.method,
+            escape(&result.name),
+            suite,
+            seconds(result.duration_ms.unwrap_or_default())
+        );
+        if result.passed {
+            xml.push_str("/>\n");
+            return xml;
+        }
+        xml.push_str(">\n");
+        if let Some(error) = &result.error {
+            let _ = writeln!(
+                xml,
+                "      <error message=\"{}\">{}</error>",
+                escape(error),
+                escape(error)
+            );
+        } else {
+            let failed = result
+                .tests
+                .iter()
+                .filter(|test| !test.passed)
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            let mut details = format!("Status: {}\n", result.status.unwrap_or_default());
+            for test in &result.tests {
+                let _ = writeln!(details, "{}", test);
+            }
+            if let Some(snippet) = &result.response_snippet {
+                let _ = write!(details, "\nResponse:\n{}", snippet);
+            }
+            let _ = writeln!(
+                xml,
+                "      <failure message=\"{}\">{}</failure>",
+                escape(&failed.join("; ")),
+                escape(&details)
+            );
+        }
+        xml.push_str("    </testcase>\n");
+        xml
+    }
+
+    /// A single page with inline styles, so it can be published as a build artifact on its own.
+    pub fn html(&self) -> String {
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{name}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; color: #222; }}
+table {{ border-collapse: collapse; width: 100%; }}
+th, td {{ text-align: left; padding: 0.4em 0.8em; border-bottom: 1px solid #ddd; vertical-align: top; }}
+.pass {{ color: #1a7f37; }}
+.fail {{ color: #cf222e; }}
+ul {{ margin: 0; padding-left: 1.2em; }}
+pre {{ background: #f6f8fa; padding: 0.6em; white-space: pre-wrap; word-break: break-all; }}
+</style>
+</head>
+<body>
+<h1>{name}</h1>
+<p>{passed} passed, {failed} failed of {total} requests &middot; tests {tests_passed}/{tests} passed &middot; {duration}ms{stopped}</p>
+<table>
+<tr><th></th><th>Request</th><th>Status</th><th>Time</th><th>Tests</th></tr>
+"#,
+            name = escape(&self.name),
+            passed = self.summary.passed,
+            failed = self.summary.failed,
+            total = self.summary.total,
+            tests_passed = self.summary.tests_passed,
+            tests = self.summary.tests_passed + self.summary.tests_failed,
+            duration = self.summary.duration_ms,
+            stopped = if self.summary.stopped {
+                " &middot; stopped on failure"
+            } else {
+                ""
+            },
+        );
+        for (idx, result) in self.results.iter().enumerate() {
+            if self.summary.iterations > 1
+                && (idx == 0 || self.results[idx - 1].iteration != result.iteration)
+            {
+                let _ = writeln!(
+                    html,
+                    "<tr><th colspan=\"5\">Iteration {}</th></tr>",
+                    result.iteration
+                );
+            }
+            let (class, mark) = if result.passed {
+                ("pass", "✓")
+            } else {
+                ("fail", "✗")
+            };
+            let _ = write!(
+                html,
+                "<tr class=\"{}\"><td>{}</td><td><strong>{} {}</strong>",
+                class,
+                mark,
+                result.method,
+                escape(&result.name)
+            );
+            if let Some(url) = &result.url {
+                let _ = write!(html, "<br><small>{}</small>", escape(url));
+            }
+            let _ = write!(
+                html,
+                "</td><td>{}</td><td>{}</td><td>",
+                result
+                    .status
+                    .map(|status| status.to_string())
+                    .unwrap_or_default(),
+                result
+                    .duration_ms
+                    .map(|ms| format!("{}ms", ms))
+                    .unwrap_or_default()
+            );
+            if let Some(error) = &result.error {
+                let _ = write!(html, "{}", escape(error));
+            }
+            if !result.tests.is_empty() {
+                html.push_str("<ul>");
+                for test in &result.tests {
+                    let _ = write!(
+                        html,
+                        "<li class=\"{}\">{}</li>",
+                        if test.passed { "pass" } else { "fail" },
+                        escape(&test.to_string())
+                    );
+                }
+                html.push_str("</ul>");
+            }
+            if let Some(snippet) = &result.response_snippet {
+                let _ = write!(html, "<pre>{}</pre>", escape(snippet));
+            }
+            html.push_str("</td></tr>\n");
+        }
+        html.push_str("</table>\n</body>\n</html>\n");
+        html
+    }
+}
+
+fn seconds(ms: u128) -> String {
+    format!("{:.3}", ms as f64 / 1000.0)
+}
+
+/// Escapes text for use in XML or HTML content and attribute values.
+fn escape(text: &str) -> String {
+    text.chars()
+        .fold(String::with_capacity(text.len()), |mut out, c| {
+            match c {
+                '&' => out.push_str("&amp;"),
+                '<' => out.push_str("&lt;"),
+                '>' => out.push_str("&gt;"),
+                '"' => out.push_str("&quot;"),
+                '\'' => out.push_str("&apos;"),
+                c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {}
+                c => out.push(c),
+            }
+            out
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EnvironmentValue, Execution, Method, Request, Response};
+    use std::time::Duration;
+    use time::OffsetDateTime;
+
+    fn report() -> RunReport {
+        let request = Request::builder()
+            .name("Create <Pet>".into())
+            .method(Method::Post)
+            .url("https://pets.test/pets?key=hunter2".into())
+            .build();
+        let results = vec![
+            RunResult {
+                index: 0,
+                iteration: 0,
+                name: request.name.clone(),
+                method: Method::Post,
+                outcome: Outcome::Completed(Box::new(Execution {
+                    request,
+                    response: Response {
+                        status: 500,
+                        body: br#"{"error":"bad key hunter2"}"#.to_vec(),
+                        duration: Duration::from_millis(42),
+                        ..Default::default()
+                    },
+                    started: OffsetDateTime::UNIX_EPOCH,
+                    tests: vec![
+                        TestResult::pass("header Content-Type exists"),
+                        TestResult::fail("status == 201", "status was 500"),
+                    ],
+                    environment: None,
+                    sent: None,
+                })),
+            },
+            RunResult {
+                index: 1,
+                iteration: 0,
+                name: "Me".into(),
+                method: Method::Get,
+                outcome: Outcome::Failed("connection refused".into()),
+            },
+        ];
+        let summary = RunSummary {
+            total: 2,
+            iterations: 1,
+            passed: 0,
+            failed: 2,
+            tests_passed: 1,
+            tests_failed: 1,
+            duration: Duration::from_millis(1500),
+            stopped: false,
+        };
+        let mut coll = Collection::default();
+        coll.variables
+            .insert("key".into(), EnvironmentValue::Secret("hunter2".into()));
+
+        RunReport::new("Pets", &results, &summary, &coll, None)
+    }
+
+    #[test]
+    fn junit() {
+        assert_eq!(
+            report().junit(),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="Pets" tests="2" failures="1" errors="1" time="1.500">
+  <testsuite name="Pets" tests="2" failures="1" errors="1" skipped="0" time="0.042">
+    <testcase name="POST Create &lt;Pet&gt;" classname="Pets" time="0.042">
+      <failure message="FAIL status == 201: status was 500">Status: 500
+PASS header Content-Type exists
+FAIL status == 201: status was 500
+
+Response:
+{&quot;error&quot;:&quot;bad key [REDACTED]&quot;}</failure>
+    </testcase>
+    <testcase name="GET Me" classname="Pets" time="0.000">
+      <error message="connection refused">connection refused</error>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+
+    #[test]
+    fn junit_suite_per_iteration() {
+        let mut report = report();
+        report.summary.iterations = 2;
+        report.results[1].iteration = 2;
+
+        let xml = report.junit();
+
+        assert!(xml.contains(
+            r#"<testsuite name="Pets (iteration 1)" tests="1" failures="1" errors="0" skipped="0" time="0.042">"#
+        ));
+        assert!(xml.contains(
+            r#"<testsuite name="Pets (iteration 2)" tests="1" failures="0" errors="1" skipped="0" time="0.000">"#
+        ));
+        assert!(xml.contains(r#"<testcase name="GET Me" classname="Pets (iteration 2)""#));
+    }
+
+    #[test]
+    fn json() {
+        let json: serde_json::Value = serde_json::from_str(&report().json().unwrap()).unwrap();
+
+        assert_eq!(json["summary"]["duration_ms"], 1500);
+        assert_eq!(
+            json["results"][0]["url"],
+            "https://pets.test/pets?key=[REDACTED]"
+        );
+        assert_eq!(json["results"][0]["duration_ms"], 42);
+        assert_eq!(json["results"][0]["tests"][1]["message"], "status was 500");
+        assert_eq!(
+            json["results"][0]["response_snippet"],
+            r#"{"error":"bad key [REDACTED]"}"#
+        );
+        assert_eq!(json["results"][1]["error"], "connection refused");
+    }
+
+    #[test]
+    fn html_is_escaped_and_redacted() {
+        let html = report().html();
+
+        assert!(html.contains("<strong>POST Create &lt;Pet&gt;</strong>"));
+        assert!(html.contains("<li class=\"fail\">FAIL status == 201: status was 500</li>"));
+        assert!(!html.contains("hunter2"));
+    }
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(
+            ReportFormat::from_path(Path::new("out/results.xml")).unwrap(),
+            ReportFormat::Junit
+        );
+        assert_eq!(
+            ReportFormat::from_path(Path::new("report.HTML")).unwrap(),
+            ReportFormat::Html
+        );
+        assert!(ReportFormat::from_path(Path::new("report")).is_err());
+    }
+}
@@ -0,0 +1,493 @@
+//! Sandboxed [Rhai](https://rhai.rs) scripts that run before a request is sent.
+//!
+//! Scripts see the outgoing request as the `request` map and the active variables as the `env`
+//! map, both of which they may modify. A repeated header shows its last value, and is sent as it
+//! was unless the script sets it:
+//!
+//! ```rhai
+//! let ts = timestamp();
+//! env.nonce = nonce(16);
+//! request.headers["X-Signature"] = hmac_sha256(env.secret, `${ts}${request.body}`);
+//! ```
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use color_eyre::{eyre::eyre, Result};
+use hmac::{Hmac, Mac};
+use log::info;
+use rhai::{
+    module_resolvers::DummyModuleResolver, serde::to_dynamic, Dynamic, Engine, EvalAltResult, Map,
+    Scope,
+};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::{assertion::TestResult, Collection, Header, Request, Response, Variables};
+
+/// Upper bound on the work a single script may do, so a runaway loop can't hang the app.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// A request that is ready to send, along with anything its scripts printed.
+#[derive(Debug)]
+pub struct Prepared {
+    pub request: Request,
+    pub output: Vec<String>,
+}
+
+/// Resolves a request's variables and runs the collection and request pre-request scripts.
+///
+/// `iteration` holds the current row of a data-driven run, its values take precedence over the
+/// environment. Variables the scripts change are written back to the active environment, or the
+/// collection variables when no environment is active.
+pub fn prepare(
+    coll: &mut Collection,
+    req: &Request,
+    env_name: Option<&str>,
+    iteration: &Variables,
+) -> Result<Prepared> {
+    let mut vars = coll.variables_for(env_name);
+    vars.extend(iteration.clone());
+    let mut request = req.resolve(&vars);
+    let scripts = [
+        (
+            String::from("collection"),
+            coll.pre_request_script.as_deref(),
+        ),
+        (format!("'{}'", req.name), req.pre_request_script.as_deref()),
+    ];
+
+    let original = vars.clone();
+    let mut output = vec![];
+    for (source, script) in scripts {
+        if let Some(script) = script.filter(|script| !script.trim().is_empty()) {
+            output.extend(
+                pre_request(script, &mut request, &mut vars)
+                    .map_err(|err| eyre!("Pre-request script for {} failed: {}", source, err))?,
+            );
+        }
+    }
+
+    vars.iter()
+        .filter(|(key, value)| original.get(*key) != Some(value))
+        .for_each(|(key, value)| coll.set_variable(env_name, key.clone(), value.clone()));
+
+    Ok(Prepared {
+        request: request.resolve(&vars),
+        output,
+    })
+}
+
+/// Runs a single script against `req` and `vars`, returning the lines it printed.
+pub fn pre_request(script: &str, req: &mut Request, vars: &mut Variables) -> Result<Vec<String>> {
+    let output = Rc::new(RefCell::new(vec![]));
+    let engine = engine(output.clone());
+
+    let mut scope = Scope::new();
+    scope.push("request", request_map(req));
+    scope.push(
+        "env",
+        vars.iter()
+            .map(|(key, value)| (key.into(), value.clone().into()))
+            .collect::<Map>(),
+    );
+
+    engine
+        .run_with_scope(&mut scope, script)
+        .map_err(|err| eyre!("{}", err))?;
+
+    let request = scope
+        .get_value::<Map>("request")
+        .ok_or_else(|| eyre!("`request` must remain a map"))?;
+    apply_request(req, request)?;
+
+    let env = scope
+        .get_value::<Map>("env")
+        .ok_or_else(|| eyre!("`env` must remain a map"))?;
+    *vars = env
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+
+    Ok(output.take())
+}
+
+/// Runs a test script against a response, returning the results it recorded with `check`.
+///
+/// The response is available as the `response` map with `status`, `headers`, `body`, `time_ms`
+/// and, when the body is JSON, `json`. Anything the script prints goes to the log.
+pub fn post_response(script: &str, res: &Response) -> Result<Vec<TestResult>> {
+    let output = Rc::new(RefCell::new(vec![]));
+    let mut engine = engine(output.clone());
+
+    let results = Rc::new(RefCell::new(vec![]));
+    let record = results.clone();
+    engine.register_fn("check", move |name: &str, passed: bool| {
+        record.borrow_mut().push(if passed {
+            TestResult::pass(name)
+        } else {
+            TestResult::fail(name, "check failed")
+        });
+    });
+    let record = results.clone();
+    engine.register_fn("check", move |name: &str, passed: bool, message: &str| {
+        record.borrow_mut().push(if passed {
+            TestResult::pass(name)
+        } else {
+            TestResult::fail(name, message)
+        });
+    });
+
+    let mut scope = Scope::new();
+    scope.push_constant("response", response_map(res)?);
+    let outcome = engine.run_with_scope(&mut scope, script);
+
+    output
+        .take()
+        .iter()
+        .for_each(|line| info!("Test script: {}", line));
+    outcome.map_err(|err| eyre!("{}", err))?;
+    Ok(results.take())
+}
+
+fn engine(output: Rc<RefCell<Vec<String>>>) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(32)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(1 << 20)
+        .set_max_array_size(10_000)
+        .set_max_map_size(10_000)
+        .set_module_resolver(DummyModuleResolver::new())
+        .disable_symbol("eval");
+
+    let debug_output = output.clone();
+    engine.on_print(move |line| output.borrow_mut().push(line.to_string()));
+    engine.on_debug(move |line, _, pos| {
+        debug_output
+            .borrow_mut()
+            .push(format!("[{}] {}", pos, line));
+    });
+
+    engine
+        .register_fn("timestamp", || OffsetDateTime::now_utc().unix_timestamp())
+        .register_fn("timestamp_ms", || {
+            (OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000) as i64
+        })
+        .register_fn("now_iso", || {
+            OffsetDateTime::now_utc()
+                .format(&Rfc3339)
+                .unwrap_or_default()
+        })
+        .register_fn("uuid", || uuid::Uuid::new_v4().to_string())
+        .register_fn("nonce", |len: i64| {
+            (0..len.max(0))
+                .map(|_| fastrand::alphanumeric())
+                .collect::<String>()
+        })
+        .register_fn("random_int", |min: i64, max: i64| {
+            fastrand::i64(min.min(max)..=max.max(min))
+        })
+        .register_fn("sha1", |input: &str| hex::encode(Sha1::digest(input)))
+        .register_fn("sha256", |input: &str| hex::encode(Sha256::digest(input)))
+        .register_fn("hmac_sha1", |key: &str, input: &str| {
+            let mut mac =
+                Hmac::<Sha1>::new_from_slice(key.as_bytes()).expect("HMAC accepts any key length");
+            mac.update(input.as_bytes());
+            hex::encode(mac.finalize().into_bytes())
+        })
+        .register_fn("hmac_sha256", |key: &str, input: &str| {
+            let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes())
+                .expect("HMAC accepts any key length");
+            mac.update(input.as_bytes());
+            hex::encode(mac.finalize().into_bytes())
+        })
+        .register_fn("base64_encode", |input: &str| STANDARD.encode(input))
+        .register_fn(
+            "base64_decode",
+            |input: &str| -> Result<String, Box<EvalAltResult>> {
+                let bytes = STANDARD
+                    .decode(input)
+                    .map_err(|err| format!("Invalid base64: {}", err))?;
+                String::from_utf8(bytes).map_err(|err| format!("Invalid UTF-8: {}", err).into())
+            },
+        );
+
+    engine
+}
+
+fn response_map(res: &Response) -> Result<Map> {
+    let body = res.complete_body()?;
+    let mut map = Map::new();
+    map.insert("status".into(), i64::from(res.status).into());
+    map.insert(
+        "headers".into(),
+        res.headers
+            .iter()
+            .map(|header| (header.name.as_ref().into(), header.value.to_string().into()))
+            .collect::<Map>()
+            .into(),
+    );
+    map.insert(
+        "body".into(),
+        String::from_utf8_lossy(body).into_owned().into(),
+    );
+    map.insert("time_ms".into(), (res.duration.as_millis() as i64).into());
+    let json = match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(json) => to_dynamic(json).map_err(|err| eyre!("{}", err))?,
+        Err(_) => Dynamic::UNIT,
+    };
+    map.insert("json".into(), json);
+    Ok(map)
+}
+
+fn request_map(req: &Request) -> Map {
+    let params = |params: &Option<HashMap<String, String>>| -> Dynamic {
+        params
+            .iter()
+            .flatten()
+            .map(|(key, value)| (key.into(), value.clone().into()))
+            .collect::<Map>()
+            .into()
+    };
+
+    let mut map = Map::new();
+    map.insert("name".into(), req.name.clone().into());
+    map.insert("method".into(), req.method.to_string().into());
+    map.insert("url".into(), req.url.clone().into());
+    map.insert(
+        "body".into(),
+        req.body.clone().map(Dynamic::from).unwrap_or(Dynamic::UNIT),
+    );
+    map.insert("headers".into(), header_map(&req.headers).into());
+    map.insert("query".into(), params(&req.query_params));
+    map.insert("path".into(), params(&req.path_params));
+    map
+}
+
+/// Headers keyed by name, a repeated header shows its last value.
+fn header_map(headers: &[Header]) -> Map {
+    headers
+        .iter()
+        .map(|header| (header.name.as_ref().into(), header.value.to_string().into()))
+        .collect()
+}
+
+fn apply_request(req: &mut Request, mut map: Map) -> Result<()> {
+    let original = header_map(&req.headers);
+    let mut take = |key: &str| map.remove(key).unwrap_or(Dynamic::UNIT);
+
+    req.method = take("method").to_string().parse()?;
+    req.url = take("url").to_string();
+    let body = take("body");
+    req.body = (!body.is_unit()).then(|| body.to_string());
+
+    // Repeated headers the script left alone are all kept, one it set is sent once.
+    let mut headers = take_map(take("headers"), "headers")?;
+    let mut set = HashSet::new();
+    req.headers.retain_mut(|header| {
+        let name = header.name.as_ref();
+        let Some(value) = headers.get(name).map(Dynamic::to_string) else {
+            return false;
+        };
+        if original.get(name).map(Dynamic::to_string) == Some(value.clone()) {
+            return true;
+        }
+        header.value = value.into();
+        set.insert(name.to_string())
+    });
+    headers.retain(|name, _| {
+        !req.headers
+            .iter()
+            .any(|header| header.name.as_ref() == name.as_str())
+    });
+    req.headers
+        .extend(headers.into_iter().map(|(name, value)| Header {
+            name: name.as_str().into(),
+            value: value.to_string().into(),
+        }));
+
+    req.query_params = take_params(take("query"), "query")?;
+    req.path_params = take_params(take("path"), "path")?;
+    Ok(())
+}
+
+fn take_map(value: Dynamic, name: &str) -> Result<Map> {
+    if value.is_unit() {
+        return Ok(Map::new());
+    }
+    value
+        .try_cast::<Map>()
+        .ok_or_else(|| eyre!("`request.{}` must be a map", name))
+}
+
+fn take_params(value: Dynamic, name: &str) -> Result<Option<HashMap<String, String>>> {
+    let params = take_map(value, name)?;
+    Ok((!params.is_empty()).then(|| {
+        params
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EnvironmentValue, Method};
+
+    fn request() -> Request {
+        Request::builder()
+            .name("Create Pet".into())
+            .method(Method::Post)
+            .url("{{host}}/pets".into())
+            .headers(vec![
+                Header {
+                    name: "Accept".into(),
+                    value: "application/json".into(),
+                },
+                Header {
+                    name: "X-Remove".into(),
+                    value: "1".into(),
+                },
+            ])
+            .body(r#"{"name":"Rex"}"#.into())
+            .build()
+    }
+
+    #[test]
+    fn keeps_repeated_headers() {
+        let mut req = request();
+        req.headers = ["a=1", "b=2", "x=1", "x=2"]
+            .iter()
+            .enumerate()
+            .map(|(idx, value)| Header {
+                name: if idx < 2 { "Cookie" } else { "X-Forwarded-For" }.into(),
+                value: (*value).into(),
+            })
+            .collect();
+        pre_request(
+            r#"request.headers["X-Forwarded-For"] = "10.0.0.1";"#,
+            &mut req,
+            &mut Variables::new(),
+        )
+        .unwrap();
+
+        let headers: Vec<_> = req
+            .headers
+            .iter()
+            .map(|header| format!("{}: {}", header.name, header.value))
+            .collect();
+        assert_eq!(
+            headers,
+            vec!["Cookie: a=1", "Cookie: b=2", "X-Forwarded-For: 10.0.0.1"]
+        );
+    }
+
+    #[test]
+    fn modifies_request_and_variables() {
+        let mut req = request();
+        let mut vars = Variables::from([("secret".into(), "key".into())]);
+        let output = pre_request(
+            r#"
+                request.method = "put";
+                request.headers.remove("X-Remove");
+                request.headers["X-Signature"] = hmac_sha256(env.secret, request.body);
+                request.query.page = 2;
+                env.nonce = nonce(12);
+                print(`signed ${request.name}`);
+            "#,
+            &mut req,
+            &mut vars,
+        )
+        .unwrap();
+
+        assert_eq!(req.method, Method::Put);
+        assert_eq!(&*req.headers[0].name, "Accept");
+        assert_eq!(&*req.headers[1].name, "X-Signature");
+        assert_eq!(
+            &*req.headers[1].value,
+            "23b7d9d73cf58b0c0909cef3a3df6f39e8226357feee1a9731fa9c000adabade"
+        );
+        assert_eq!(
+            req.query_params,
+            Some(HashMap::from([("page".into(), "2".into())]))
+        );
+        assert_eq!(vars.get("nonce").map(String::len), Some(12));
+        assert_eq!(output, vec!["signed Create Pet"]);
+    }
+
+    #[test]
+    fn helper_functions() {
+        let mut req = request();
+        let mut vars = Variables::new();
+        pre_request(
+            r#"
+                env.sha = sha256("abc");
+                env.hmac = hmac_sha1("key", "The quick brown fox jumps over the lazy dog");
+                env.b64 = base64_decode(base64_encode("pigeon"));
+            "#,
+            &mut req,
+            &mut vars,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vars["sha"],
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(vars["hmac"], "de7c9b85b8b78aa6bc8a7a36f70a90701c9db4d9");
+        assert_eq!(vars["b64"], "pigeon");
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        let mut req = request();
+        let mut vars = Variables::new();
+
+        assert!(pre_request("let x = ;", &mut req, &mut vars).is_err());
+        assert!(pre_request("loop {}", &mut req, &mut vars).is_err());
+        assert!(pre_request(r#"import "fs" as fs;"#, &mut req, &mut vars).is_err());
+        assert!(pre_request(r#"request.method = "FETCH";"#, &mut req, &mut vars).is_err());
+    }
+
+    #[test]
+    fn prepare_writes_back_variables() {
+        let mut coll = Collection {
+            requests: vec![Request {
+                pre_request_script: Some(r#"env.token = "fresh";"#.into()),
+                ..request()
+            }],
+            pre_request_script: Some(r#"env.host = "https://pets.test";"#.into()),
+            ..Default::default()
+        };
+        coll.variables
+            .insert("token".into(), EnvironmentValue::Secret("stale".into()));
+        let req = coll.requests[0].clone();
+
+        let prepared = prepare(&mut coll, &req, None, &Variables::new()).unwrap();
+
+        assert_eq!(prepared.request.url, "https://pets.test/pets");
+        assert_eq!(
+            coll.variables.get("token"),
+            Some(&EnvironmentValue::Secret("fresh".into()))
+        );
+        assert!(prepare(
+            &mut coll,
+            &Request {
+                pre_request_script: Some("throw \"nope\";".into()),
+                ..request()
+            },
+            None,
+            &Variables::new()
+        )
+        .unwrap_err()
+        .to_string()
+        .contains("'Create Pet'"));
+    }
+}
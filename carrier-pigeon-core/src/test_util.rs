@@ -0,0 +1,36 @@
+//! Fixtures shared by the tests of several modules.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    thread,
+};
+
+/// Starts a server on a free local port that answers the n-th connection with the n-th of
+/// `bodies`, repeating the last one once they run out. `headers` are extra header lines, each
+/// ending in `\r\n`, sent with every response. Returns the server's base URL.
+pub fn serve<B: Into<Vec<u8>>>(headers: &str, bodies: Vec<B>) -> String {
+    let headers = headers.to_string();
+    let bodies: Vec<Vec<u8>> = bodies.into_iter().map(Into::into).collect();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        for (index, stream) in listener.incoming().enumerate() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|read| read > 0) && line != "\r\n" {
+                line.clear();
+            }
+            let body = &bodies[index.min(bodies.len() - 1)];
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                headers,
+                body.len()
+            );
+            let _ = stream.write_all(body);
+        }
+    });
+    format!("http://{}", addr)
+}
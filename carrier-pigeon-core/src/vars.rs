@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+pub type Variables = HashMap<String, String>;
+
+/// Replaces every `{{name}}` in the input with its value, unknown names are left in place.
+pub fn substitute(input: &str, vars: &Variables) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                match vars.get(after[..end].trim()) {
+                    Some(value) => output.push_str(value),
+                    None => output.push_str(&rest[start..start + end + 4]),
+                }
+                rest = &after[end + 2..];
+            }
+            None => {
+                output.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> Variables {
+        let mut vars = Variables::new();
+        vars.insert("host".into(), "https://pets.test".into());
+        vars.insert("id".into(), "7".into());
+        vars
+    }
+
+    #[test]
+    fn substitute_known() {
+        assert_eq!(
+            substitute("{{host}}/pets/{{ id }}", &vars()),
+            "https://pets.test/pets/7"
+        );
+    }
+
+    #[test]
+    fn substitute_unknown_and_unclosed() {
+        assert_eq!(substitute("{{missing}}/{{id}}", &vars()), "{{missing}}/7");
+        assert_eq!(substitute("{{id}} {{host", &vars()), "7 {{host");
+    }
+}
@@ -0,0 +1,248 @@
+//! Requests and responses as they went over the wire, for checking exactly what was sent and
+//! received.
+
+use reqwest::header::{HeaderMap, CONTENT_LENGTH, HOST};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    body::{self, Highlight, StyledLine},
+    response::base64_body,
+    Header, Response,
+};
+
+/// A request as it was handed to the HTTP client, after variable resolution, auth and query
+/// encoding.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct SentRequest {
+    pub method: String,
+    /// The full URL, including the encoded query.
+    pub url: String,
+    /// The version the connection used, taken from the response.
+    pub version: String,
+    /// Headers in the order they were sent, with the client's defaults like `user-agent` and the
+    /// ones its connection adds like `host` and `content-length`. Names are lowercase, as the
+    /// client writes them.
+    pub headers: Vec<Header>,
+    #[serde(default, with = "base64_body", skip_serializing_if = "Vec::is_empty")]
+    pub body: Vec<u8>,
+}
+
+impl SentRequest {
+    /// The request as the client sends it, `defaults` being the headers the client adds to any
+    /// request that doesn't set them itself, see [`crate::executor::HttpClient`].
+    pub fn new(req: &reqwest::Request, defaults: &HeaderMap) -> Self {
+        let body = req
+            .body()
+            .and_then(reqwest::Body::as_bytes)
+            .unwrap_or_default()
+            .to_vec();
+        let header = |name: &str, value: &str| Header {
+            name: name.into(),
+            value: value.into(),
+        };
+        // The client appends its defaults to the request's own headers, then the connection adds
+        // the rest in the order below.
+        let mut headers: Vec<Header> = req
+            .headers()
+            .iter()
+            .chain(
+                defaults
+                    .iter()
+                    .filter(|(name, _)| !req.headers().contains_key(*name)),
+            )
+            .map(|(name, value)| header(name.as_str(), &String::from_utf8_lossy(value.as_bytes())))
+            .collect();
+        if !req.headers().contains_key(HOST) {
+            let url = req.url();
+            let host = match url.port() {
+                Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+                None => url.host_str().unwrap_or_default().to_string(),
+            };
+            headers.push(header(HOST.as_str(), &host));
+        }
+        if !body.is_empty() && !req.headers().contains_key(CONTENT_LENGTH) {
+            headers.push(header(CONTENT_LENGTH.as_str(), &body.len().to_string()));
+        }
+
+        Self {
+            method: req.method().to_string(),
+            url: req.url().to_string(),
+            version: format!("{:?}", req.version()),
+            headers,
+            body,
+        }
+    }
+
+    /// The request line, headers and body.
+    pub fn lines(&self) -> Vec<StyledLine> {
+        let target = reqwest::Url::parse(&self.url)
+            .map(|url| match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            })
+            .unwrap_or_else(|_| self.url.clone());
+        let mut lines = vec![vec![
+            (Highlight::Tag, self.method.clone()),
+            (Highlight::Plain, format!(" {} {}", target, self.version)),
+        ]];
+        lines.extend(header_lines(&self.headers));
+        lines.push(vec![]);
+        lines.extend(body_lines(
+            &self.body,
+            header(&self.headers, "content-type"),
+        ));
+        lines
+    }
+}
+
+/// The status line, headers and body of a response as received, with a note on how the body was
+/// encoded in transit.
+pub fn response_lines(res: &Response) -> Vec<StyledLine> {
+    let reason = reqwest::StatusCode::from_u16(res.status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or_default();
+    let mut lines = vec![vec![
+        (Highlight::Plain, format!("{} ", res.version)),
+        (Highlight::Tag, format!("{} {}", res.status, reason)),
+    ]];
+    lines.extend(header_lines(&res.headers));
+    lines.push(vec![]);
+    lines.push(vec![(Highlight::Comment, encoding_note(res))]);
+    lines.extend(body_lines(&res.body, res.header("content-type")));
+    if let Some(spilled) = &res.spilled {
+        let more = spilled.len - res.body.len() as u64;
+        let note = if spilled.is_paged() {
+            format!("# {} more bytes in {}", more, spilled.path.display())
+        } else {
+            format!("# {} more bytes were not kept", more)
+        };
+        lines.push(vec![(Highlight::Comment, note)]);
+    }
+    lines
+}
+
+/// Describes the transfer and content encodings of the response body, and what was undone.
+fn encoding_note(res: &Response) -> String {
+    let mut note = format!("# {} bytes", res.body_len());
+    if let Some(transfer) = res.header("transfer-encoding") {
+        note.push_str(&format!(
+            ", transfer-encoding {} removed by the client",
+            transfer
+        ));
+    }
+    match (res.header("content-encoding"), res.compressed_len) {
+        (Some(encoding), Some(compressed)) => note.push_str(&format!(
+            ", content-encoding {} decoded from {} bytes",
+            encoding, compressed
+        )),
+        (Some(encoding), None) if !encoding.eq_ignore_ascii_case("identity") => {
+            note.push_str(&format!(", content-encoding {} not decoded", encoding));
+        }
+        _ => note.push_str(", no content-encoding"),
+    }
+    note
+}
+
+fn header<'a>(headers: &'a [Header], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|header| header.name.eq_ignore_ascii_case(name))
+        .map(|header| header.value.as_ref())
+}
+
+fn header_lines(headers: &[Header]) -> impl Iterator<Item = StyledLine> + '_ {
+    headers.iter().map(|header| {
+        vec![
+            (Highlight::Key, header.name.to_string()),
+            (Highlight::Punctuation, String::from(": ")),
+            (Highlight::Plain, header.value.to_string()),
+        ]
+    })
+}
+
+/// The body as text, or a note in its place when it is binary.
+fn body_lines(body: &[u8], content_type: Option<&str>) -> Vec<StyledLine> {
+    if body::is_binary(content_type, body) {
+        return vec![vec![(
+            Highlight::Comment,
+            format!("# {} bytes of binary data", body.len()),
+        )]];
+    }
+    body::decode(body, content_type)
+        .lines()
+        .map(|line| vec![(Highlight::Plain, line.to_string())])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::HttpClient;
+
+    fn text(lines: &[StyledLine]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| line.iter().map(|(_, text)| text.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn sent_request_lines() {
+        let client = HttpClient::new("carrier-pigeon-test").unwrap();
+        let defaults = client.default_headers();
+        let req = reqwest::Client::new()
+            .post("http://pets.test:8080/pets?dry=true")
+            .header("X-Trace", "abc")
+            .bearer_auth("t0k3n")
+            .body(r#"{"name":"Rex"}"#)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            text(&SentRequest::new(&req, defaults).lines()),
+            vec![
+                "POST /pets?dry=true HTTP/1.1",
+                "x-trace: abc",
+                "authorization: Bearer t0k3n",
+                "accept: */*",
+                "user-agent: carrier-pigeon-test",
+                "host: pets.test:8080",
+                "content-length: 14",
+                "",
+                r#"{"name":"Rex"}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn received_response_lines() {
+        let res = Response {
+            status: 404,
+            headers: vec![
+                Header {
+                    name: "Content-Type".into(),
+                    value: "text/plain".into(),
+                },
+                Header {
+                    name: "Transfer-Encoding".into(),
+                    value: "chunked".into(),
+                },
+            ],
+            body: b"gone".to_vec(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            text(&response_lines(&res)),
+            vec![
+                "HTTP/1.1 404 Not Found",
+                "Content-Type: text/plain",
+                "Transfer-Encoding: chunked",
+                "",
+                "# 4 bytes, transfer-encoding chunked removed by the client, no content-encoding",
+                "gone",
+            ]
+        );
+    }
+}